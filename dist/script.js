@@ -24,10 +24,24 @@ function getInvokeFunction() {
     return null;
 }
 
+// Words below this confidence are highlighted in the debug panel
+const LOW_WORD_CONFIDENCE = 60;
+
+// Backend errors arrive as { code, message, details }; anything else is shown as is
+function errorMessage(error) {
+    return error && error.message ? error.message : String(error);
+}
+
 class UmaHelper {
     constructor() {
         console.log('UmaHelper constructor called');
         this.isCapturing = false;
+        this.weightProfiles = null;
+        this.runContext = null;
+        this.preprocessPresets = [];
+        this.variantConfig = null;
+        this.ocrConfig = null;
+        this.isWatching = false;
         this.init();
     }
 
@@ -35,6 +49,10 @@ class UmaHelper {
         console.log('UmaHelper init called');
         this.setupEventListeners();
         this.setupResizableRectangle();
+        this.loadWeightProfiles();
+        this.loadRunContext();
+        this.loadPreprocessPresets();
+        this.setupWatchListeners();
         this.updateStatus('Ready', 'success');
     }
 
@@ -73,6 +91,14 @@ class UmaHelper {
             console.log('Debug toggle listener added');
         }
 
+        // Watch mode toggle
+        const watchToggle = document.getElementById('watch-toggle');
+        if (watchToggle) {
+            watchToggle.addEventListener('click', () => {
+                this.toggleWatch();
+            });
+        }
+
         // Manual lookup
         const manualLookupBtn = document.getElementById('manual-lookup-btn');
         if (manualLookupBtn) {
@@ -83,6 +109,64 @@ class UmaHelper {
             console.log('Manual lookup listener added');
         }
 
+        // Event database reload
+        const reloadEventsBtn = document.getElementById('reload-events-btn');
+        if (reloadEventsBtn) {
+            reloadEventsBtn.addEventListener('click', () => {
+                this.reloadEvents();
+            });
+        }
+
+        // Scoring profile switcher
+        const profileSelect = document.getElementById('profile-select');
+        if (profileSelect) {
+            profileSelect.addEventListener('change', () => {
+                this.setActiveWeightProfile(profileSelect.value);
+            });
+        }
+
+        // Tessdata help dismiss
+        const tessdataHelpClose = document.getElementById('tessdata-help-close');
+        if (tessdataHelpClose) {
+            tessdataHelpClose.addEventListener('click', () => {
+                document.getElementById('tessdata-help').classList.add('hidden');
+            });
+        }
+
+        // Preprocessing pipeline
+        const presetSelect = document.getElementById('preprocess-preset-select');
+        const stagesToggle = document.getElementById('preprocess-stages-toggle');
+        if (presetSelect && stagesToggle) {
+            presetSelect.addEventListener('change', () => this.applyPreprocessConfig());
+            stagesToggle.addEventListener('change', () => this.applyPreprocessConfig());
+        }
+
+        const languageSelect = document.getElementById('ocr-language-select');
+        if (languageSelect) {
+            languageSelect.addEventListener('change', () => this.setOcrLanguage(languageSelect.value));
+        }
+
+        const variantsToggle = document.getElementById('ocr-variants-toggle');
+        if (variantsToggle) {
+            variantsToggle.addEventListener('change', () => this.setVariantsEnabled(variantsToggle.checked));
+        }
+
+        // Run context save
+        const runContextSaveBtn = document.getElementById('run-context-save-btn');
+        if (runContextSaveBtn) {
+            runContextSaveBtn.addEventListener('click', () => {
+                this.saveRunContext();
+            });
+        }
+
+        // Scoring profile save
+        const profileSaveBtn = document.getElementById('profile-save-btn');
+        if (profileSaveBtn) {
+            profileSaveBtn.addEventListener('click', () => {
+                this.saveWeightProfile();
+            });
+        }
+
         // Manual input enter key
         const manualInput = document.getElementById('manual-input');
         if (manualInput) {
@@ -203,7 +287,8 @@ class UmaHelper {
             
         } catch (error) {
             console.error('Capture failed:', error);
-            this.updateStatus(`Capture failed: ${error}`, 'error');
+            this.updateStatus(`Capture failed: ${errorMessage(error)}`, 'error');
+            this.showErrorHelp(error);
             this.isCapturing = false;
         }
     }
@@ -395,48 +480,120 @@ class UmaHelper {
             console.log('Using invoke function:', invoke);
             
             // Perform OCR
-            const ocrResult = await invoke('capture_screen_area', { area });
+            const ocrResult = await invoke('capture_screen_area', { area, colorKey: this.selectedColorKey() });
             
             this.hideLoading();
-            
-            if (ocrResult.text && ocrResult.text.trim()) {
-                // Debug console output
-                console.log('=== OCR RESULT ===');
-                console.log('Raw text:', ocrResult.text);
-                console.log('Trimmed text:', ocrResult.text.trim());
-                console.log('Confidence:', ocrResult.confidence);
-                console.log('Matched events:', ocrResult.matched_events);
-                console.log('Text length:', ocrResult.text.length);
-                console.log('==================');
-                
-                // Update debug panel with OCR result
-                this.updateDebugPanel(ocrResult);
-                
-                // Display matched events or recognized text
-                if (ocrResult.matched_events && ocrResult.matched_events.length > 0) {
-                    this.displayMatchedEvents(ocrResult.text, ocrResult.confidence, ocrResult.matched_events);
-                    this.updateStatus(`Found ${ocrResult.matched_events.length} matching event(s)`, 'success');
-                } else {
-                    this.displayRecognizedText(ocrResult.text, ocrResult.confidence);
-                    this.updateStatus(`Text recognized but no events matched (${ocrResult.confidence.toFixed(1)}% confidence)`, 'success');
-                }
-            } else {
-                console.log('=== OCR RESULT ===');
-                console.log('No text detected or empty result');
-                console.log('Raw result:', ocrResult);
-                console.log('==================');
-                this.updateStatus('No text detected', 'error');
-            }
+            this.handleOcrResult(ocrResult);
             
         } catch (error) {
             console.error('OCR failed:', error);
-            this.updateStatus(`OCR failed: ${error}`, 'error');
+            this.updateStatus(`OCR failed: ${errorMessage(error)}`, 'error');
+            this.showErrorHelp(error);
             this.hideLoading();
         } finally {
             this.isCapturing = false;
         }
     }
 
+    handleOcrResult(ocrResult) {
+        if (ocrResult.text && ocrResult.text.trim()) {
+            // Debug console output
+            console.log('=== OCR RESULT ===');
+            console.log('Raw text:', ocrResult.text);
+            console.log('Trimmed text:', ocrResult.text.trim());
+            console.log('Confidence:', ocrResult.confidence);
+            console.log('Matched events:', ocrResult.matched_events);
+            console.log('Text length:', ocrResult.text.length);
+            console.log('==================');
+            
+            // Update debug panel with OCR result
+            this.updateDebugPanel(ocrResult);
+            
+            // Display matched events or recognized text
+            if (ocrResult.matched_events && ocrResult.matched_events.length > 0) {
+                this.displayMatchedEvents(ocrResult.text, ocrResult.confidence, ocrResult.matched_events);
+                this.updateStatus(`Found ${ocrResult.matched_events.length} matching event(s)`, 'success');
+            } else {
+                this.displayRecognizedText(ocrResult.text, ocrResult.confidence);
+                this.updateStatus(`Text recognized but no events matched (${ocrResult.confidence.toFixed(1)}% confidence)`, 'success');
+            }
+        } else {
+            console.log('=== OCR RESULT ===');
+            console.log('No text detected or empty result');
+            console.log('Raw result:', ocrResult);
+            console.log('==================');
+            this.updateStatus('No text detected', 'error');
+        }
+    }
+
+    setupWatchListeners() {
+        const listen = window.__TAURI__ && window.__TAURI__.event && window.__TAURI__.event.listen;
+        if (!listen) {
+            console.error('Tauri event API not available, watch mode disabled');
+            return;
+        }
+
+        listen('watch-ocr-result', (event) => {
+            console.log('Watch mode result:', event.payload);
+            this.handleOcrResult(event.payload);
+        });
+
+        // Sent for manual reloads and for edits picked up by the file watcher
+        listen('events-reloaded', (event) => {
+            const { count, previous_count: previousCount } = event.payload;
+            this.updateStatus(`Events reloaded: ${count} event(s) (was ${previousCount})`, 'success');
+            this.loadRunContext();
+        });
+
+        listen('events-reload-failed', (event) => {
+            this.updateStatus(`Events reload failed, keeping the loaded events: ${errorMessage(event.payload)}`, 'error');
+        });
+
+        listen('watch-status', (event) => {
+            if (!event.payload.running) {
+                this.setWatching(false);
+                if (event.payload.last_error) {
+                    this.updateStatus(`Watch stopped: ${errorMessage(event.payload.last_error)}`, 'error');
+                    this.showErrorHelp(event.payload.last_error);
+                }
+            } else if (event.payload.last_error) {
+                // Still watching; capture or OCR failed on this frame
+                this.updateStatus(`Watch: ${errorMessage(event.payload.last_error)}`, 'error');
+            }
+        });
+    }
+
+    async toggleWatch() {
+        try {
+            const invoke = getInvokeFunction();
+            if (!invoke) {
+                throw new Error('Tauri invoke function not available');
+            }
+
+            if (this.isWatching) {
+                await invoke('stop_watch');
+                this.setWatching(false);
+                this.updateStatus('Watch mode stopped', 'success');
+            } else {
+                const area = await this.getTargetingArea();
+                await invoke('start_watch', { area, config: { color_key: this.selectedColorKey() } });
+                this.setWatching(true);
+                this.updateStatus('Watching for new events...', 'processing');
+            }
+        } catch (error) {
+            console.error('Watch toggle failed:', error);
+            this.updateStatus(`Watch mode failed: ${errorMessage(error)}`, 'error');
+        }
+    }
+
+    setWatching(watching) {
+        this.isWatching = watching;
+        const watchToggle = document.getElementById('watch-toggle');
+        if (watchToggle) {
+            watchToggle.classList.toggle('active', watching);
+        }
+    }
+
     displayMatchedEvents(extractedText, confidence, matchedEvents) {
         const eventSection = document.getElementById('event-result');
         const nameDiv = document.getElementById('event-name');
@@ -489,13 +646,47 @@ class UmaHelper {
                 charDiv.textContent = `${eventMatch.event.character_name} • ${eventMatch.event.relation_type}`;
                 eventDiv.appendChild(charDiv);
             }
+
+            // Why it matched: each OCR line that matched a field of the event
+            if (eventMatch.signals && eventMatch.signals.length > 0) {
+                const signalsDiv = document.createElement('div');
+                signalsDiv.style.cssText = 'font-size: 10px; color: #495057; margin-bottom: 8px;';
+                signalsDiv.textContent = 'Matched ' + eventMatch.signals
+                    .map(signal => {
+                        const field = signal.choice ? `choice ${signal.choice}` : signal.field.replace(/_/g, ' ');
+                        return `${field} ${(signal.score * 100).toFixed(0)}% ("${signal.text}")`;
+                    })
+                    .join(', ');
+                eventDiv.appendChild(signalsDiv);
+            }
+
+            // Events added or changed by the user's own event files
+            const provenance = eventMatch.provenance;
+            if (provenance && (provenance.source !== 'bundled' || provenance.patched_by.length > 0)) {
+                const provenanceDiv = document.createElement('div');
+                provenanceDiv.style.cssText = 'font-size: 10px; color: #8a6d3b; margin-bottom: 8px;';
+                const notes = [];
+                if (provenance.source !== 'bundled') notes.push(`from ${provenance.source}`);
+                if (provenance.patched_by.length > 0) notes.push(`patched by ${provenance.patched_by.join(', ')}`);
+                provenanceDiv.textContent = notes.join(' • ');
+                eventDiv.appendChild(provenanceDiv);
+            }
             
             // Choices
             if (eventMatch.event.choices && eventMatch.event.choices.length > 0) {
                 const choicesContainer = document.createElement('div');
                 choicesContainer.style.cssText = 'margin-top: 8px;';
                 
-                eventMatch.event.choices.forEach((choice) => {
+                eventMatch.event.choices.forEach((choice, choiceIndex) => {
+                    const parsed = eventMatch.choice_outcomes ? eventMatch.choice_outcomes[choiceIndex] : null;
+                    const scored = eventMatch.choice_scores ? eventMatch.choice_scores[choiceIndex] : null;
+                    const isRecommended = eventMatch.recommended_choice === choice.number;
+                    const scoreHtml = scored
+                        ? `<span style="font-weight: 400; color: #6c757d; margin-left: 6px;">score ${scored.score.toFixed(1)}${scored.partial ? '*' : ''}</span>`
+                        : '';
+                    const unparsedHtml = parsed && parsed.unparsed.length > 0
+                        ? `<div style="color: #b8860b; font-size: 9px;">⚠ Unrecognized: ${parsed.unparsed.join('; ')}</div>`
+                        : '';
                     const choiceDiv = document.createElement('div');
                     choiceDiv.style.cssText = `
                         padding: 6px 8px; 
@@ -503,16 +694,17 @@ class UmaHelper {
                         border-radius: 3px; 
                         margin-bottom: 4px; 
                         font-size: 10px;
-                        border: 1px solid #e9ecef;
+                        border: 1px solid ${isRecommended ? '#28a745' : '#e9ecef'};
                     `;
                     
                     choiceDiv.innerHTML = `
                         <div style="font-weight: 600; margin-bottom: 2px;">
-                            Choice ${choice.number}: ${choice.text}
+                            ${isRecommended ? '⭐ ' : ''}Choice ${choice.number}: ${choice.text}${scoreHtml}
                         </div>
                         <div style="color: #28a745; font-size: 9px;">
                             ${choice.outcome}
                         </div>
+                        ${unparsedHtml}
                     `;
                     choicesContainer.appendChild(choiceDiv);
                 });
@@ -631,6 +823,22 @@ class UmaHelper {
         document.getElementById('capture-btn').style.pointerEvents = 'none';
     }
 
+    // Setup instructions for errors the user can fix themselves
+    showErrorHelp(error) {
+        if (!error || error.code !== 'tessdata_missing') return;
+
+        const details = error.details || {};
+        const lines = [
+            `Language: ${details.language}`,
+            `Tessdata: ${details.tessdata_path || 'none found, tried Tesseract defaults'}`,
+            `Needs: ${(details.language || '').split('+').map((code) => `${code}.traineddata`).join(', ')}`,
+            '',
+            details.hint
+        ];
+        document.getElementById('tessdata-help-text').textContent = lines.join('\n');
+        document.getElementById('tessdata-help').classList.remove('hidden');
+    }
+
     hideLoading() {
         document.getElementById('loading').classList.add('hidden');
         document.getElementById('capture-btn').style.opacity = '1';
@@ -689,21 +897,49 @@ class UmaHelper {
             processedContainer.querySelector('.no-image').style.display = 'none';
         }
 
+        this.renderPreprocessStages(ocrResult.debug_stages || []);
+
         // Update OCR result text
         const ocrText = document.getElementById('ocr-result-text');
         const ocrConfidence = document.getElementById('ocr-confidence');
         const ocrContainer = document.getElementById('ocr-result-container');
         
         if (ocrResult.text && ocrResult.text.trim()) {
-            ocrText.textContent = ocrResult.text;
+            this.renderOcrText(ocrText, ocrResult);
             ocrConfidence.textContent = `Confidence: ${ocrResult.confidence.toFixed(1)}%`;
             
             ocrText.classList.remove('hidden');
             ocrConfidence.classList.remove('hidden');
+            this.renderVariantScores(ocrResult);
             ocrContainer.querySelector('.no-result-text').style.display = 'none';
         }
     }
 
+    // One span per recognised word, so low-confidence words stand out
+    renderOcrText(container, ocrResult) {
+        const lines = ocrResult.lines || [];
+        if (lines.length === 0) {
+            container.textContent = ocrResult.text;
+            return;
+        }
+
+        container.innerHTML = '';
+        lines.forEach((line) => {
+            const lineElement = document.createElement('div');
+            line.words.forEach((word, i) => {
+                if (i > 0) lineElement.appendChild(document.createTextNode(' '));
+                const wordElement = document.createElement('span');
+                wordElement.textContent = word.text;
+                wordElement.title = `${word.confidence.toFixed(0)}% at ${word.bbox.x},${word.bbox.y} (${word.bbox.width}x${word.bbox.height})`;
+                if (word.confidence < LOW_WORD_CONFIDENCE) {
+                    wordElement.className = 'low-confidence';
+                }
+                lineElement.appendChild(wordElement);
+            });
+            container.appendChild(lineElement);
+        });
+    }
+
     clearDebugPanel() {
         // Clear images
         const capturedImg = document.getElementById('captured-image');
@@ -727,13 +963,351 @@ class UmaHelper {
         ocrConfidence.textContent = '';
         ocrText.classList.add('hidden');
         ocrConfidence.classList.add('hidden');
+        this.renderVariantScores(null);
         ocrContainer.querySelector('.no-result-text').style.display = 'block';
 
+        this.renderPreprocessStages([]);
+
         // Clear manual input
         const manualInput = document.getElementById('manual-input');
         manualInput.value = '';
     }
 
+    renderPreprocessStages(stages) {
+        const container = document.getElementById('preprocess-stages');
+        if (!container) return;
+
+        container.innerHTML = '';
+        stages.forEach((stage, i) => {
+            const item = document.createElement('div');
+            const name = document.createElement('div');
+            name.className = 'preprocess-stage-name';
+            name.textContent = `${i}. ${stage.name}`;
+            const img = document.createElement('img');
+            img.className = 'debug-image';
+            img.src = stage.image;
+            img.alt = stage.name;
+            item.appendChild(name);
+            item.appendChild(img);
+            container.appendChild(item);
+        });
+    }
+
+    async loadPreprocessPresets() {
+        try {
+            const invoke = getInvokeFunction();
+            if (!invoke) return;
+
+            const [presets, config] = await Promise.all([
+                invoke('list_preprocess_presets'),
+                invoke('get_preprocess_config')
+            ]);
+            this.preprocessPresets = presets;
+
+            const presetSelect = document.getElementById('preprocess-preset-select');
+            presetSelect.innerHTML = '';
+            presets.forEach((preset) => {
+                const option = document.createElement('option');
+                option.value = preset.name;
+                option.textContent = preset.name;
+                option.selected = JSON.stringify(preset.config.steps) === JSON.stringify(config.steps);
+                presetSelect.appendChild(option);
+            });
+            document.getElementById('preprocess-stages-toggle').checked = config.debug_stages;
+
+            this.ocrConfig = await invoke('get_ocr_config');
+            const languageSelect = document.getElementById('ocr-language-select');
+            if (![...languageSelect.options].some((option) => option.value === this.ocrConfig.language)) {
+                const option = document.createElement('option');
+                option.value = this.ocrConfig.language;
+                option.textContent = this.ocrConfig.language;
+                languageSelect.appendChild(option);
+            }
+            languageSelect.value = this.ocrConfig.language;
+
+            this.variantConfig = await invoke('get_ocr_variants');
+            document.getElementById('ocr-variants-toggle').checked = this.variantConfig.enabled;
+
+            const colorKeySelect = document.getElementById('color-key-select');
+            const colorKeyPresets = await invoke('list_color_key_presets');
+            colorKeyPresets.forEach((preset) => {
+                const option = document.createElement('option');
+                option.value = preset.name;
+                option.textContent = preset.name;
+                option.title = preset.description;
+                colorKeySelect.appendChild(option);
+            });
+        } catch (error) {
+            console.error('Failed to load preprocessing presets:', error);
+        }
+    }
+
+    async setOcrLanguage(language) {
+        if (!this.ocrConfig) return;
+
+        try {
+            const invoke = getInvokeFunction();
+            if (!invoke) {
+                throw new Error('Tauri invoke function not available');
+            }
+
+            this.updateStatus(`Loading OCR language ${language}...`, 'processing');
+            this.ocrConfig = await invoke('set_ocr_config', {
+                config: { ...this.ocrConfig, language }
+            });
+            this.updateStatus(`OCR language: ${language}`, 'success');
+        } catch (error) {
+            console.error('Failed to set OCR language:', error);
+            this.updateStatus(`OCR language change failed: ${errorMessage(error)}`, 'error');
+            this.showErrorHelp(error);
+            document.getElementById('ocr-language-select').value = this.ocrConfig.language;
+        }
+    }
+
+    async setVariantsEnabled(enabled) {
+        if (!this.variantConfig) return;
+
+        try {
+            const invoke = getInvokeFunction();
+            if (!invoke) {
+                throw new Error('Tauri invoke function not available');
+            }
+
+            this.variantConfig = await invoke('set_ocr_variants', {
+                config: { ...this.variantConfig, enabled }
+            });
+            this.updateStatus(enabled ? 'Voting across OCR variants' : 'Using a single OCR pipeline', 'success');
+        } catch (error) {
+            console.error('Failed to update OCR variants:', error);
+            this.updateStatus(`OCR variants update failed: ${errorMessage(error)}`, 'error');
+        }
+    }
+
+    renderVariantScores(ocrResult) {
+        const container = document.getElementById('ocr-variants');
+        if (!container) return;
+
+        container.innerHTML = '';
+        const scores = ocrResult ? ocrResult.variant_scores || [] : [];
+        if (scores.length < 2) {
+            container.classList.add('hidden');
+            return;
+        }
+
+        scores.forEach((score) => {
+            const line = document.createElement('div');
+            if (score.name === ocrResult.chosen_variant) {
+                line.className = 'chosen';
+            }
+            const event = score.top_event ? ` → ${score.top_event} (${score.votes} votes)` : '';
+            line.textContent = `${score.name}: ${score.score.toFixed(2)} · OCR ${score.ocr_confidence.toFixed(0)}% · match ${(score.match_confidence * 100).toFixed(0)}%${event}`;
+            container.appendChild(line);
+        });
+        container.classList.remove('hidden');
+    }
+
+    // Colour-key preset for the captured region, null for plain grayscale
+    selectedColorKey() {
+        const colorKeySelect = document.getElementById('color-key-select');
+        return colorKeySelect && colorKeySelect.value ? colorKeySelect.value : null;
+    }
+
+    async applyPreprocessConfig() {
+        const presetName = document.getElementById('preprocess-preset-select').value;
+        const preset = this.preprocessPresets.find((candidate) => candidate.name === presetName);
+        if (!preset) return;
+
+        const config = {
+            ...preset.config,
+            debug_stages: document.getElementById('preprocess-stages-toggle').checked
+        };
+
+        try {
+            const invoke = getInvokeFunction();
+            if (!invoke) {
+                throw new Error('Tauri invoke function not available');
+            }
+
+            await invoke('set_preprocess_config', { config });
+            this.updateStatus(`Preprocessing: ${presetName}`, 'success');
+        } catch (error) {
+            console.error('Failed to update preprocessing:', error);
+            this.updateStatus(`Preprocessing update failed: ${errorMessage(error)}`, 'error');
+        }
+    }
+
+    async loadWeightProfiles() {
+        try {
+            const invoke = getInvokeFunction();
+            if (!invoke) return;
+
+            const profileList = await invoke('get_weight_profiles');
+            this.renderWeightProfiles(profileList);
+        } catch (error) {
+            console.error('Failed to load weight profiles:', error);
+        }
+    }
+
+    renderWeightProfiles(profileList) {
+        this.weightProfiles = profileList;
+
+        const profileSelect = document.getElementById('profile-select');
+        if (!profileSelect) return;
+
+        profileSelect.innerHTML = '';
+        profileList.profiles.forEach((profile) => {
+            const option = document.createElement('option');
+            option.value = profile.name;
+            option.textContent = profile.name;
+            option.selected = profile.name === profileList.active;
+            profileSelect.appendChild(option);
+        });
+    }
+
+    async setActiveWeightProfile(name) {
+        try {
+            const invoke = getInvokeFunction();
+            if (!invoke) {
+                throw new Error('Tauri invoke function not available');
+            }
+
+            const profileList = await invoke('set_active_weight_profile', { name });
+            this.renderWeightProfiles(profileList);
+            this.updateStatus(`Scoring profile: ${name}`, 'success');
+        } catch (error) {
+            console.error('Failed to switch weight profile:', error);
+            this.updateStatus(`Profile switch failed: ${errorMessage(error)}`, 'error');
+        }
+    }
+
+    // Parses "Speed 1.2, Wit 0.8, Skill Hint 15 points" into profile fields
+    parseWeightSpec(spec) {
+        const weights = {};
+        spec.split(/[,\n]/).forEach((part) => {
+            const match = part.trim().match(/^([a-zA-Z ]+?)\s*[:=]?\s*(-?\d+(?:\.\d+)?)(?:\s*points?)?$/i);
+            if (match) {
+                const key = match[1].trim().toLowerCase().replace(/\s+/g, '_');
+                weights[key] = parseFloat(match[2]);
+            }
+        });
+        return weights;
+    }
+
+    async saveWeightProfile() {
+        const nameInput = document.getElementById('profile-name-input');
+        const weightsInput = document.getElementById('profile-weights-input');
+        const name = nameInput.value.trim();
+
+        if (!name) {
+            this.updateStatus('Please enter a profile name', 'error');
+            return;
+        }
+
+        try {
+            const invoke = getInvokeFunction();
+            if (!invoke) {
+                throw new Error('Tauri invoke function not available');
+            }
+
+            // Start from the active profile so unspecified weights keep their values
+            const base = this.weightProfiles
+                ? this.weightProfiles.profiles.find((profile) => profile.name === this.weightProfiles.active)
+                : null;
+            const profile = { ...(base || {}), ...this.parseWeightSpec(weightsInput.value), name };
+
+            await invoke('save_weight_profile', { profile });
+            const profileList = await invoke('set_active_weight_profile', { name });
+            this.renderWeightProfiles(profileList);
+            this.updateStatus(`Saved scoring profile: ${name}`, 'success');
+        } catch (error) {
+            console.error('Failed to save weight profile:', error);
+            this.updateStatus(`Profile save failed: ${errorMessage(error)}`, 'error');
+        }
+    }
+
+    async loadRunContext() {
+        try {
+            const invoke = getInvokeFunction();
+            if (!invoke) return;
+
+            const [characters, runContext] = await Promise.all([
+                invoke('list_characters'),
+                invoke('get_run_context')
+            ]);
+            this.renderRunContext(characters, runContext);
+        } catch (error) {
+            console.error('Failed to load run context:', error);
+        }
+    }
+
+    renderRunContext(characters, runContext) {
+        this.runContext = runContext;
+
+        const traineeSelect = document.getElementById('run-trainee-select');
+        const supportSelect = document.getElementById('run-support-select');
+        const modeSelect = document.getElementById('run-mode-select');
+        if (!traineeSelect || !supportSelect || !modeSelect) return;
+
+        traineeSelect.innerHTML = '<option value="">Any Umamusume</option>';
+        supportSelect.innerHTML = '';
+        characters.forEach((character) => {
+            const option = document.createElement('option');
+            option.value = character.name;
+            option.textContent = character.name;
+
+            if (character.relation_type === 'Umamusume') {
+                option.selected = character.name === runContext.trainee;
+                traineeSelect.appendChild(option);
+            } else {
+                option.selected = runContext.support_cards.includes(character.name);
+                supportSelect.appendChild(option);
+            }
+        });
+        modeSelect.value = runContext.mode;
+    }
+
+    async saveRunContext() {
+        const traineeSelect = document.getElementById('run-trainee-select');
+        const supportSelect = document.getElementById('run-support-select');
+        const modeSelect = document.getElementById('run-mode-select');
+
+        const context = {
+            trainee: traineeSelect.value || null,
+            support_cards: Array.from(supportSelect.selectedOptions).map((option) => option.value),
+            mode: modeSelect.value
+        };
+
+        try {
+            const invoke = getInvokeFunction();
+            if (!invoke) {
+                throw new Error('Tauri invoke function not available');
+            }
+
+            this.runContext = await invoke('set_run_context', { context });
+            const deckSize = this.runContext.support_cards.length;
+            this.updateStatus(`Run saved: ${this.runContext.trainee || 'any Umamusume'}, ${deckSize} support card(s)`, 'success');
+        } catch (error) {
+            console.error('Failed to save run context:', error);
+            this.updateStatus(`Run save failed: ${errorMessage(error)}`, 'error');
+        }
+    }
+
+    async reloadEvents() {
+        try {
+            const invoke = getInvokeFunction();
+            if (!invoke) {
+                throw new Error('Tauri invoke function not available');
+            }
+
+            this.updateStatus('Reloading events...', 'processing');
+            // Success is reported by the events-reloaded listener
+            await invoke('reload_events');
+        } catch (error) {
+            console.error('Events reload failed:', error);
+            this.updateStatus(`Events reload failed, keeping the loaded events: ${errorMessage(error)}`, 'error');
+        }
+    }
+
     async performManualLookup() {
         const manualInput = document.getElementById('manual-input');
         const inputText = manualInput.value.trim();
@@ -763,7 +1337,7 @@ class UmaHelper {
 
         } catch (error) {
             console.error('Manual lookup failed:', error);
-            this.updateStatus(`Manual lookup failed: ${error}`, 'error');
+            this.updateStatus(`Manual lookup failed: ${errorMessage(error)}`, 'error');
         }
     }
 }
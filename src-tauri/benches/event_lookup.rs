@@ -0,0 +1,61 @@
+// Compares indexed event lookup against the full scan over every event.
+// Run with: cargo bench --bench event_lookup
+
+use std::time::{Duration, Instant};
+use uma_helper_lib::events::parse_events;
+use uma_helper_lib::matcher::{match_events_with_index, match_events_with_text};
+use uma_helper_lib::search_index::SearchIndex;
+
+const ITERATIONS: u32 = 50;
+
+// Event names and choice texts as OCR tends to return them
+const QUERIES: &[&str] = &[
+    "A Gift From the Dark Sky",
+    "At Tachyon's Pace",
+    "Hamburger Helper!",
+    "What if you try thanking it?",
+    "Beach running, please.",
+    "At Summer Camp (Year 2)",
+    "Can't let them go to waste, right?",
+    "Medicine That Makes You Faster?",
+    "Body Modificatlon!",
+    "At Tachy0n's Pace\nWait a sec, hang in there!\nI'll prepare something right away",
+];
+
+fn time_lookups(mut lookup: impl FnMut(&str) -> usize) -> (Duration, usize) {
+    let mut total_matches = 0;
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        for query in QUERIES {
+            total_matches += lookup(query);
+        }
+    }
+    (start.elapsed() / (ITERATIONS * QUERIES.len() as u32), total_matches)
+}
+
+fn main() {
+    // The bundled events only, so user layer files do not change the numbers
+    let events =
+        parse_events(include_str!("../../events.json"), "events.json", true).expect("Failed to parse events.json");
+
+    let build_start = Instant::now();
+    let index = SearchIndex::build(&events);
+    println!("Indexed {} events in {:?}", index.event_count(), build_start.elapsed());
+
+    let (full_scan, _) = time_lookups(|query| match_events_with_text(query, &events).len());
+    let (indexed, _) = time_lookups(|query| match_events_with_index(query, &events, &index).len());
+
+    println!("Full scan: {:?} per lookup", full_scan);
+    println!("Indexed:   {:?} per lookup", indexed);
+    println!("Speedup:   {:.1}x", full_scan.as_secs_f64() / indexed.as_secs_f64());
+
+    // The index only prunes candidates, so the best match should not change
+    for query in QUERIES {
+        let expected = match_events_with_text(query, &events);
+        let actual = match_events_with_index(query, &events, &index);
+        let expected_top = expected.first().map(|m| m.event.name.as_str());
+        let actual_top = actual.first().map(|m| m.event.name.as_str());
+        let marker = if expected_top == actual_top { "ok" } else { "DIFF" };
+        println!("[{}] {:?}: {:?} / {:?}", marker, query, expected_top, actual_top);
+    }
+}
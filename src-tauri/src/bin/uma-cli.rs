@@ -0,0 +1,888 @@
+// Headless command line front end: runs the same preprocessing, OCR and
+// event matching as the app, without a window.
+//
+//   uma-cli ocr <image> [--area x,y,width,height] [--lang codes] [--pipeline name] [--color-key name] [--variants] [--min-word-conf n] [--words] [--stages dir] [--metric name] [settings] [--json]
+//   uma-cli lookup [text...] [--metric name] [settings] [--json]      (reads stdin when no text is given)
+//   uma-cli dump-events [--user-settings] [--json]
+//   uma-cli lint-events [file] [--severity info|warning|error] [--json]
+//   uma-cli import <file> [--format js|csv|tsv|yaml|json] [--output file] [--json]
+//   uma-cli learn-confusions <corpus.tsv> [--min-count n] [--output file] [--json]
+//   uma-cli tune <labelled.tsv> [--metric name] [settings] [--json]
+
+use serde::Serialize;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use uma_helper_lib::capture::{crop_image, CaptureArea};
+use uma_helper_lib::color_key;
+use uma_helper_lib::error::{self, AppError};
+use uma_helper_lib::event_import::{import_events, ImportFormat, ImportReport};
+use uma_helper_lib::event_lint::{self, EventsReport, Severity};
+use uma_helper_lib::events::{
+    lint_events_json, load_events_json, load_events_without_layers, JsonEvent, Provenance, BUNDLED_SOURCE,
+};
+use uma_helper_lib::matcher::{
+    match_events_in_context, parse_choice_outcomes, score_event_matches, EventMatch, MatchSignal, SimilarityMetric,
+};
+use uma_helper_lib::matcher_config::{MatcherConfig, MatcherConfigStore, MATCHER_CONFIG_FILE};
+use uma_helper_lib::matcher_tune::{self, TuneReport};
+use uma_helper_lib::ocr_confusion::{self, Confusion, ConfusionTable, LearnReport, OCR_CONFUSIONS_FILE};
+use uma_helper_lib::ocr_engine::{self, OcrConfig, OcrEngine};
+use uma_helper_lib::ocr_layout::OcrLine;
+use uma_helper_lib::ocr_variants::{self, VariantCandidate, VariantConfig, VariantScore};
+use uma_helper_lib::outcome::ChoiceOutcome;
+use uma_helper_lib::paths::get_config_dir;
+use uma_helper_lib::preprocess::{self, PreprocessConfig, PreprocessStage};
+use uma_helper_lib::run_context::{RunContext, RunContextStore, RUN_CONTEXT_FILE};
+use uma_helper_lib::scoring::{ProfileStore, WeightProfile, WEIGHT_PROFILES_FILE};
+use uma_helper_lib::search_index::SearchIndex;
+use uma_helper_lib::settings_file;
+
+const USAGE: &str = "Usage:
+  uma-cli ocr <image> [--area x,y,width,height] [--lang codes] [--pipeline name] [--color-key name] [--variants] [--min-word-conf n] [--words] [--stages dir] [--metric name] [settings] [--json]
+  uma-cli lookup [text...] [--metric name] [settings] [--json]
+  uma-cli dump-events [--user-settings] [--json]
+  uma-cli lint-events [file] [--severity info|warning|error] [--json]
+  uma-cli import <file> [--format js|csv|tsv|yaml|json] [--output file] [--json]
+  uma-cli learn-confusions <corpus.tsv> [--min-count n] [--output file] [--json]
+  uma-cli tune <labelled.tsv> [--metric name] [settings] [--json]
+
+Options:
+  --lang           Tesseract languages, e.g. eng, jpn, jpn_vert or eng+jpn
+  --pipeline       Preprocessing preset: default, otsu, sauvola, cleanup or niblack
+  --color-key      Keep only known text colours: event_title or choice_button
+  --variants       Also OCR the built-in preprocessing variants and keep the best result
+  --min-word-conf  Leave words below this confidence (0-100) out of matching
+  --words          Print every recognised word with its confidence and box
+  --stages         Save every preprocessing step's image to this directory
+  --severity       Lowest lint severity to list (default: warning)
+  --format         Format of the imported file (default: from its extension)
+  --output         Write the imported events or learned confusions here instead of to stdout
+  --metric         Text similarity: jaro_winkler, confusion or combined (default: from the matcher config)
+  --config         Matcher config file (default: the built-in settings)
+  --context        Run context file to filter or boost matches with (default: none)
+  --profile        Weight profile file to score choices with (default: the built-in profile)
+  --confusions     OCR confusions file to use on top of the built-in ones
+  --user-settings  Use the event layers, run context, profile, matcher config and confusions saved by the app
+  --min-count      Times a confusion must occur in the corpus to be learned (default: 2)
+  --json           Print machine-readable JSON instead of text
+  --verbose        Log processing details to stderr
+
+Settings: --config file, --context file, --profile file, --confusions file or --user-settings";
+
+struct Options {
+    command: String,
+    args: Vec<String>,
+    area: Option<CaptureArea>,
+    language: Option<String>,
+    pipeline: Option<String>,
+    color_key: Option<String>,
+    stages_dir: Option<PathBuf>,
+    variants: bool,
+    min_word_confidence: Option<f32>,
+    words: bool,
+    severity: Severity,
+    format: Option<String>,
+    output: Option<PathBuf>,
+    metric: Option<SimilarityMetric>,
+    config: Option<PathBuf>,
+    context: Option<PathBuf>,
+    profile: Option<PathBuf>,
+    confusions: Option<PathBuf>,
+    user_settings: bool,
+    min_count: usize,
+    json: bool,
+    verbose: bool,
+}
+
+#[derive(Serialize)]
+struct CliOcrResult {
+    text: String,
+    confidence: f32,
+    lines: Vec<OcrLine>,
+    matched_events: Vec<EventMatch>,
+    chosen_variant: String,
+    variant_scores: Vec<VariantScore>,
+}
+
+#[derive(Serialize)]
+struct ParsedEvent<'a> {
+    #[serde(flatten)]
+    event: &'a JsonEvent,
+    provenance: &'a Provenance,
+    choice_outcomes: Vec<ChoiceOutcome>,
+}
+
+fn main() -> ExitCode {
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    if options.verbose {
+        tracing_subscriber::fmt().with_writer(std::io::stderr).init();
+    }
+
+    let result = match options.command.as_str() {
+        "ocr" => run_ocr(&options),
+        "lookup" => run_lookup(&options),
+        "dump-events" => run_dump_events(&options),
+        "lint-events" => run_lint_events(&options),
+        "import" => run_import(&options),
+        "learn-confusions" => run_learn_confusions(&options),
+        "tune" => run_tune(&options),
+        "help" => {
+            let _ = writeln!(std::io::stdout().lock(), "{}", USAGE);
+            Ok(())
+        }
+        other => Err(AppError::invalid_input(format!("Unknown command: {}\n\n{}", other, USAGE))),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) if options.json => {
+            eprintln!("{}", serde_json::to_string_pretty(&e).unwrap_or_else(|_| e.to_string()));
+            ExitCode::FAILURE
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            if let AppError::TessdataMissing { .. } = e {
+                eprintln!("\n{}", error::tessdata_hint());
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn parse_args(args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut command = None;
+    let mut positional = Vec::new();
+    let mut area = None;
+    let mut language = None;
+    let mut pipeline = None;
+    let mut color_key = None;
+    let mut stages_dir = None;
+    let mut variants = false;
+    let mut min_word_confidence = None;
+    let mut words = false;
+    let mut severity = Severity::Warning;
+    let mut format = None;
+    let mut output = None;
+    let mut metric = None;
+    let mut config = None;
+    let mut context = None;
+    let mut profile = None;
+    let mut confusions = None;
+    let mut user_settings = false;
+    let mut min_count = 2;
+    let mut json = false;
+    let mut verbose = false;
+
+    let mut args = args;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--json" => json = true,
+            "--variants" => variants = true,
+            "--words" => words = true,
+            "--verbose" | "-v" => verbose = true,
+            "--help" | "-h" => command = Some("help".to_string()),
+            "--area" => {
+                let value = args.next().ok_or("--area needs a value")?;
+                area = Some(parse_area(&value)?);
+            }
+            "--lang" => language = Some(args.next().ok_or("--lang needs a language code")?),
+            "--pipeline" => pipeline = Some(args.next().ok_or("--pipeline needs a preset name")?),
+            "--color-key" => color_key = Some(args.next().ok_or("--color-key needs a preset name")?),
+            "--min-word-conf" => {
+                let value = args.next().ok_or("--min-word-conf needs a value")?;
+                let floor = value.parse().map_err(|_| format!("Invalid confidence '{}'", value))?;
+                min_word_confidence = Some(floor);
+            }
+            "--severity" => {
+                severity = match args.next().as_deref() {
+                    Some("info") => Severity::Info,
+                    Some("warning") => Severity::Warning,
+                    Some("error") => Severity::Error,
+                    _ => return Err("--severity needs info, warning or error".to_string()),
+                }
+            }
+            "--format" => format = Some(args.next().ok_or("--format needs js, csv, tsv, yaml or json")?),
+            "--output" => output = Some(PathBuf::from(args.next().ok_or("--output needs a file")?)),
+            "--metric" => {
+                let value = args.next().ok_or("--metric needs a name")?;
+                metric = Some(
+                    SimilarityMetric::from_name(&value).ok_or("--metric needs jaro_winkler, confusion or combined")?,
+                );
+            }
+            "--user-settings" => user_settings = true,
+            "--config" => config = Some(PathBuf::from(args.next().ok_or("--config needs a file")?)),
+            "--context" => context = Some(PathBuf::from(args.next().ok_or("--context needs a file")?)),
+            "--profile" => profile = Some(PathBuf::from(args.next().ok_or("--profile needs a file")?)),
+            "--confusions" => confusions = Some(PathBuf::from(args.next().ok_or("--confusions needs a file")?)),
+            "--min-count" => {
+                let value = args.next().ok_or("--min-count needs a value")?;
+                min_count = value.parse().map_err(|_| format!("Invalid --min-count '{}'", value))?;
+            }
+            "--stages" => stages_dir = Some(PathBuf::from(args.next().ok_or("--stages needs a directory")?)),
+            flag if flag.starts_with("--") => return Err(format!("Unknown option: {}", flag)),
+            _ if command.is_none() => command = Some(arg),
+            _ => positional.push(arg),
+        }
+    }
+
+    Ok(Options {
+        command: command.ok_or("No command given")?,
+        args: positional,
+        area,
+        language,
+        pipeline,
+        color_key,
+        stages_dir,
+        variants,
+        min_word_confidence,
+        words,
+        severity,
+        format,
+        output,
+        metric,
+        config,
+        context,
+        profile,
+        confusions,
+        user_settings,
+        min_count,
+        json,
+        verbose,
+    })
+}
+
+fn parse_area(value: &str) -> Result<CaptureArea, String> {
+    let parts: Vec<&str> = value.split(',').map(str::trim).collect();
+    let invalid = || format!("Invalid area '{}', expected x,y,width,height", value);
+    if parts.len() != 4 {
+        return Err(invalid());
+    }
+
+    Ok(CaptureArea {
+        x: parts[0].parse().map_err(|_| invalid())?,
+        y: parts[1].parse().map_err(|_| invalid())?,
+        width: parts[2].parse().map_err(|_| invalid())?,
+        height: parts[3].parse().map_err(|_| invalid())?,
+    })
+}
+
+// The user layer files only apply with --user-settings
+fn load_events(options: &Options) -> Result<Vec<JsonEvent>, AppError> {
+    if options.user_settings {
+        load_events_json()
+    } else {
+        load_events_without_layers()
+    }
+}
+
+// What lookups match with: the bundled events and built-in settings, files
+// given with --context, --profile, --config and --confusions, or with
+// --user-settings the event layers and settings the app uses. Output then only depends on what was asked for,
+// not on what was last picked in the app.
+struct Matching {
+    events: Vec<JsonEvent>,
+    index: SearchIndex,
+    context: RunContext,
+    profile: WeightProfile,
+    config: MatcherConfig,
+    confusions: ConfusionTable,
+}
+
+impl Matching {
+    fn new(options: &Options) -> Result<Self, AppError> {
+        let events = load_events(options)?;
+        let index = SearchIndex::build(&events);
+        let saved = |file: &str| if options.user_settings { get_config_dir().map(|dir| dir.join(file)) } else { None };
+
+        let context = match &options.context {
+            Some(path) => {
+                let context: RunContext = settings_file::read(path, "run context")?;
+                context.validate(&events)?;
+                context
+            }
+            None => RunContextStore::load(saved(RUN_CONTEXT_FILE)).context().clone(),
+        };
+        let profile = match &options.profile {
+            Some(path) => settings_file::read(path, "weight profile")?,
+            None => ProfileStore::load(saved(WEIGHT_PROFILES_FILE)).active(),
+        };
+        let mut config = match &options.config {
+            Some(path) => MatcherConfig::read(path)?,
+            None => MatcherConfigStore::load(saved(MATCHER_CONFIG_FILE)).config().clone(),
+        };
+        if let Some(metric) = options.metric {
+            config.metric = metric;
+        }
+        let confusions = match &options.confusions {
+            Some(path) => {
+                let mut confusions = ConfusionTable::default();
+                confusions.merge(settings_file::read::<Vec<Confusion>>(path, "OCR confusions")?);
+                confusions
+            }
+            None => ConfusionTable::load(saved(OCR_CONFUSIONS_FILE)),
+        };
+
+        Ok(Matching { events, index, context, profile, config, confusions })
+    }
+
+    fn find(&self, text: &str) -> Vec<EventMatch> {
+        let mut matches =
+            match_events_in_context(text, &self.events, &self.index, &self.context, &self.config, &self.confusions);
+        score_event_matches(&mut matches, &self.profile);
+        matches
+    }
+}
+
+fn run_ocr(options: &Options) -> Result<(), AppError> {
+    let [image_path] = options.args.as_slice() else {
+        return Err(AppError::invalid_input("ocr expects exactly one image path"));
+    };
+
+    let image = image::open(Path::new(image_path))
+        .map_err(|e| AppError::ImageFailed { reason: format!("could not open {}: {}", image_path, e) })?;
+    let image = match &options.area {
+        Some(area) => crop_image(&image, area)?,
+        None => image,
+    };
+
+    let mut preprocess_config = match &options.pipeline {
+        Some(name) => preprocess::preset(name)
+            .ok_or_else(|| AppError::invalid_input(format!("Unknown pipeline preset: {}", name)))?,
+        None => PreprocessConfig::default(),
+    };
+    if let Some(name) = &options.color_key {
+        preprocess_config.color_key = Some(
+            color_key::preset(name)
+                .ok_or_else(|| AppError::invalid_input(format!("Unknown colour key preset: {}", name)))?,
+        );
+    }
+    preprocess_config.debug_stages = options.stages_dir.is_some();
+
+    let variant_config = VariantConfig { enabled: options.variants, ..VariantConfig::default() };
+    let ocr_config = OcrConfig {
+        language: options.language.clone().unwrap_or_else(|| OcrConfig::default().language),
+        ..OcrConfig::default()
+    };
+    ocr_config.validate()?;
+    let engine = OcrEngine::new(ocr_config);
+    engine.set_tessdata_path(ocr_engine::find_tessdata_path(None, &engine.config().language));
+    // A failing variant is skipped; the vote is over the ones that ran
+    let mut runs = Vec::new();
+    let mut first_error = None;
+    for (name, config) in variant_config.pipelines(&preprocess_config) {
+        match ocr_variants::run_variant(&engine, &image, name.clone(), &config) {
+            Ok(run) => runs.push(run),
+            Err(e) => {
+                eprintln!("Warning: OCR variant {} failed, skipping it: {}", name, e);
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+    if runs.is_empty() {
+        return Err(first_error.unwrap_or_else(|| AppError::internal("No OCR variant was run")));
+    }
+
+    let matching = Matching::new(options)?;
+    let word_confidence_floor = options.min_word_confidence;
+    let mut texts: Vec<String> = runs.iter().map(|run| run.ocr.text.trim().to_string()).collect();
+    let mut variant_matches: Vec<Vec<EventMatch>> = runs
+        .iter()
+        .map(|run| matching.find(&run.match_text(word_confidence_floor)))
+        .collect();
+    let (chosen, variant_scores) = {
+        let candidates: Vec<VariantCandidate> = runs
+            .iter()
+            .zip(&texts)
+            .zip(&variant_matches)
+            .map(|((run, text), matches)| VariantCandidate {
+                name: &run.name,
+                text,
+                ocr_confidence: run.ocr.confidence,
+                matches,
+            })
+            .collect();
+        ocr_variants::vote(&candidates)
+    };
+
+    let run = runs.swap_remove(chosen);
+    let text = texts.swap_remove(chosen);
+    let matched_events = variant_matches.swap_remove(chosen);
+    if let Some(stages_dir) = &options.stages_dir {
+        save_stages(&run.preprocessed.stages, stages_dir)?;
+    }
+
+    if options.json {
+        return print_json(&CliOcrResult {
+            text,
+            confidence: run.ocr.confidence,
+            lines: run.ocr.lines,
+            matched_events,
+            chosen_variant: run.name,
+            variant_scores,
+        });
+    }
+
+    let mut stdout = std::io::stdout().lock();
+    if variant_scores.len() > 1 {
+        let _ = writeln!(stdout, "Variants:");
+        for score in &variant_scores {
+            let _ = writeln!(
+                stdout,
+                "  {} {:<14} score {:.2}  ocr {:>3.0}%  match {:>3.0}%  votes {}  {}",
+                if score.name == run.name { "*" } else { " " },
+                score.name,
+                score.score,
+                score.ocr_confidence,
+                score.match_confidence * 100.0,
+                score.votes,
+                score.top_event.as_deref().unwrap_or("-")
+            );
+        }
+        let _ = writeln!(stdout);
+    }
+
+    let _ = writeln!(stdout, "Text ({:.0}% confidence):", run.ocr.confidence);
+    for line in text.lines() {
+        let _ = writeln!(stdout, "  {}", line);
+    }
+    let _ = writeln!(stdout);
+
+    if options.words {
+        let _ = writeln!(stdout, "Words:");
+        for (i, line) in run.ocr.lines.iter().enumerate() {
+            let _ = writeln!(stdout, "  line {} ({:.0}%)", i + 1, line.confidence);
+            for word in &line.words {
+                let skipped = word_confidence_floor.is_some_and(|floor| word.confidence < floor);
+                let _ = writeln!(
+                    stdout,
+                    "    {:>3.0}%  {:>4},{:<4} {:>4}x{:<4} {}{}",
+                    word.confidence,
+                    word.bbox.x,
+                    word.bbox.y,
+                    word.bbox.width,
+                    word.bbox.height,
+                    word.text,
+                    if skipped { "  (ignored)" } else { "" }
+                );
+            }
+        }
+        let _ = writeln!(stdout);
+    }
+    print_matches(&matched_events);
+    Ok(())
+}
+
+fn save_stages(stages: &[PreprocessStage], dir: &Path) -> Result<(), AppError> {
+    let save_failed = |path: &Path, e: &dyn std::fmt::Display| AppError::SaveFailed {
+        path: path.display().to_string(),
+        reason: e.to_string(),
+    };
+
+    std::fs::create_dir_all(dir).map_err(|e| save_failed(dir, &e))?;
+    for (i, stage) in stages.iter().enumerate() {
+        let path = dir.join(format!("stage_{:02}_{}.png", i, stage.name));
+        stage.image.save(&path).map_err(|e| save_failed(&path, &e))?;
+        eprintln!("Saved {}", path.display());
+    }
+    Ok(())
+}
+
+fn run_lookup(options: &Options) -> Result<(), AppError> {
+    let text = if options.args.is_empty() {
+        let mut input = String::new();
+        std::io::stdin()
+            .read_to_string(&mut input)
+            .map_err(|e| AppError::invalid_input(format!("Failed to read stdin: {}", e)))?;
+        input
+    } else {
+        options.args.join(" ")
+    };
+
+    let text = text.trim();
+    if text.is_empty() {
+        return Err(AppError::invalid_input("Nothing to look up"));
+    }
+
+    let matched_events = Matching::new(options)?.find(text);
+
+    if options.json {
+        return print_json(&matched_events);
+    }
+    print_matches(&matched_events);
+    Ok(())
+}
+
+fn run_lint_events(options: &Options) -> Result<(), AppError> {
+    let path = match options.args.as_slice() {
+        [] => None,
+        [path] => Some(Path::new(path)),
+        _ => return Err(AppError::invalid_input("lint-events takes at most one file")),
+    };
+
+    let mut report = lint_events_json(path)?;
+    let summary = format!(
+        "{} loaded, {} skipped: {} error(s), {} warning(s), {} info",
+        report.loaded,
+        report.skipped,
+        report.count(Severity::Error),
+        report.count(Severity::Warning),
+        report.count(Severity::Info)
+    );
+    report.issues.retain(|issue| issue.severity >= options.severity);
+
+    if options.json {
+        print_json(&report)?;
+    } else {
+        print_lint_report(&report);
+        eprintln!("{}", summary);
+    }
+
+    // A failing exit status lets dataset checks run in CI
+    if report.skipped > 0 {
+        return Err(AppError::EventsLoadFailed {
+            reason: format!("{} has {} invalid event(s)", report.source, report.skipped),
+        });
+    }
+    Ok(())
+}
+
+fn run_import(options: &Options) -> Result<(), AppError> {
+    let [input] = options.args.as_slice() else {
+        return Err(AppError::invalid_input("import expects exactly one file"));
+    };
+    let path = Path::new(input);
+    let format = match &options.format {
+        Some(name) => ImportFormat::from_name(name)
+            .ok_or_else(|| AppError::invalid_input(format!("Unknown import format: {}", name)))?,
+        None => ImportFormat::from_path(path)
+            .ok_or_else(|| AppError::invalid_input(format!("Cannot tell the format of {}, pass --format", input)))?,
+    };
+
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| AppError::EventsLoadFailed { reason: format!("could not read {}: {}", input, e) })?;
+    let (events, report) = import_events(&content, format)?;
+    let json = serde_json::to_string_pretty(&events)
+        .map_err(|e| AppError::internal(format!("Failed to serialize events: {}", e)))?;
+
+    // The result goes through the same checks as events.json
+    let (_, issues) = event_lint::validate_events(&json)?;
+    let lint = EventsReport { source: input.clone(), loaded: events.len(), skipped: 0, issues };
+
+    match &options.output {
+        Some(output) => std::fs::write(output, format!("{}\n", json)).map_err(|e| AppError::SaveFailed {
+            path: output.display().to_string(),
+            reason: e.to_string(),
+        })?,
+        None => {
+            let _ = writeln!(std::io::stdout().lock(), "{}", json);
+        }
+    }
+
+    // Events may be on stdout, so the report always goes to stderr
+    if options.json {
+        let report = serde_json::to_string_pretty(&serde_json::json!({ "import": report, "lint": lint }))
+            .map_err(|e| AppError::internal(format!("Failed to serialize output: {}", e)))?;
+        eprintln!("{}", report);
+    } else {
+        print_import_report(&report, &lint);
+    }
+    Ok(())
+}
+
+fn run_learn_confusions(options: &Options) -> Result<(), AppError> {
+    let [input] = options.args.as_slice() else {
+        return Err(AppError::invalid_input("learn-confusions expects exactly one corpus file"));
+    };
+    let content = std::fs::read_to_string(input)
+        .map_err(|e| AppError::invalid_input(format!("Could not read {}: {}", input, e)))?;
+
+    // One pair per line: OCR output, a tab, then the correct text
+    let mut pairs = Vec::new();
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((ocr, expected)) = line.split_once('\t') else {
+            return Err(AppError::invalid_input(format!("{}:{}: expected OCR text, a tab, then the correct text", input, number + 1)));
+        };
+        pairs.push((ocr.to_string(), expected.to_string()));
+    }
+
+    let report = ocr_confusion::learn_confusions(&pairs, options.min_count);
+    let table = report.table();
+    match &options.output {
+        Some(output) => table.save(output)?,
+        None => {
+            let json = serde_json::to_string_pretty(table.entries())
+                .map_err(|e| AppError::internal(format!("Failed to serialize confusions: {}", e)))?;
+            let _ = writeln!(std::io::stdout().lock(), "{}", json);
+        }
+    }
+
+    // The table may be on stdout, so the report always goes to stderr
+    if options.json {
+        let report = serde_json::to_string_pretty(&report)
+            .map_err(|e| AppError::internal(format!("Failed to serialize output: {}", e)))?;
+        eprintln!("{}", report);
+    } else {
+        print_learn_report(&report);
+    }
+    Ok(())
+}
+
+fn run_tune(options: &Options) -> Result<(), AppError> {
+    let [input] = options.args.as_slice() else {
+        return Err(AppError::invalid_input("tune expects exactly one labelled file"));
+    };
+    let content = std::fs::read_to_string(input)
+        .map_err(|e| AppError::invalid_input(format!("Could not read {}: {}", input, e)))?;
+    let samples = matcher_tune::parse_labelled(&content)
+        .map_err(|e| AppError::invalid_input(format!("{}: {}", input, e)))?;
+    if samples.is_empty() {
+        return Err(AppError::invalid_input(format!("{} has no samples", input)));
+    }
+
+    // Run context filters are left out: the labels say which event is right
+    let matching = Matching::new(options)?;
+    let report = matcher_tune::tune(
+        &samples,
+        &matching.events,
+        &matching.index,
+        &matching.config,
+        &matching.confusions,
+        matcher_tune::DEFAULT_THRESHOLDS,
+    );
+
+    if options.json {
+        return print_json(&report);
+    }
+    print_tune_report(&report);
+    Ok(())
+}
+
+fn print_tune_report(report: &TuneReport) {
+    let mut stdout = std::io::stdout().lock();
+    let _ = writeln!(stdout, "threshold  precision  recall     f1  correct  wrong  missed");
+    for result in &report.results {
+        let best = report.best_threshold == Some(result.threshold);
+        let precision = result.precision.map_or_else(|| "-".to_string(), |precision| format!("{:.3}", precision));
+        let _ = writeln!(
+            stdout,
+            "{}{:>8.2}  {:>9}  {:>6.3}  {:>5.3}  {:>7}  {:>5}  {:>6}",
+            if best { "*" } else { " " },
+            result.threshold,
+            precision,
+            result.recall,
+            result.f1,
+            result.correct,
+            result.wrong,
+            result.missed
+        );
+    }
+
+    if let Some(threshold) = report.best_threshold {
+        let _ = writeln!(stdout, "\nMisses at {:.2}:", threshold);
+        for miss in &report.misses {
+            let matched = match (&miss.matched, miss.confidence) {
+                (Some(name), Some(confidence)) => format!("{} ({:.0}%)", name, confidence * 100.0),
+                _ => "nothing".to_string(),
+            };
+            let _ = writeln!(
+                stdout,
+                "  {:?}: expected {}, got {}",
+                miss.text,
+                miss.expected.as_deref().unwrap_or("nothing"),
+                matched
+            );
+        }
+    }
+    eprintln!(
+        "{} sample(s), {} labelled with an event; * marks the best F1",
+        report.samples, report.positives
+    );
+}
+
+fn print_learn_report(report: &LearnReport) {
+    for learned in &report.confusions {
+        eprintln!(
+            "{:>6} -> {:<6} {:>4} of {:<5} cost {:.2}",
+            format!("{:?}", learned.ocr),
+            format!("{:?}", learned.expected),
+            learned.count,
+            learned.opportunities,
+            learned.cost
+        );
+    }
+    eprintln!(
+        "{} pair(s): {} exact, {} skipped as too different; {} confusion(s) learned",
+        report.pairs,
+        report.exact,
+        report.skipped,
+        report.confusions.len()
+    );
+}
+
+fn print_import_report(report: &ImportReport, lint: &EventsReport) {
+    for skipped in &report.skipped {
+        eprintln!("skipped: {}", skipped);
+    }
+    for (field, count) in &report.unmapped_fields {
+        eprintln!("unmapped: {} (in {} entr{})", field, count, if *count == 1 { "y" } else { "ies" });
+    }
+    eprintln!(
+        "{} event(s) imported, {} skipped, {} unmapped field(s); lint: {} warning(s), {} info",
+        report.imported,
+        report.skipped.len(),
+        report.unmapped_fields.len(),
+        lint.count(Severity::Warning),
+        lint.count(Severity::Info)
+    );
+}
+
+fn print_lint_report(report: &EventsReport) {
+    let mut stdout = std::io::stdout().lock();
+    for issue in &report.issues {
+        let severity = match issue.severity {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        let field = if issue.field.is_empty() { String::new() } else { format!(" {}:", issue.field) };
+        let written = writeln!(
+            stdout,
+            "{}:{}: {}: [{}]{} {}",
+            report.source,
+            issue.line,
+            severity,
+            issue.event.as_deref().unwrap_or("?"),
+            field,
+            issue.message
+        );
+        if written.is_err() {
+            return; // Output closed early, e.g. piped into `head`
+        }
+    }
+}
+
+fn run_dump_events(options: &Options) -> Result<(), AppError> {
+    let events = load_events(options)?;
+
+    if options.json {
+        let parsed: Vec<ParsedEvent> = events
+            .iter()
+            .map(|event| ParsedEvent {
+                event,
+                provenance: &event.provenance,
+                choice_outcomes: parse_choice_outcomes(event),
+            })
+            .collect();
+        return print_json(&parsed);
+    }
+
+    let mut stdout = std::io::stdout().lock();
+    for event in &events {
+        let _ = writeln!(stdout, "{} [{} / {}]{}", event.name, event.character_name, event.relation_type, provenance_note(&event.provenance));
+        for choice in parse_choice_outcomes(event) {
+            let _ = writeln!(stdout, "  {}: {}", choice.number, choice.outcome.replace('\n', " / "));
+            for segment in &choice.unparsed {
+                let _ = writeln!(stdout, "     unrecognized: {}", segment);
+            }
+        }
+    }
+    eprintln!("{} events", events.len());
+    Ok(())
+}
+
+fn print_matches(matches: &[EventMatch]) {
+    // `head` may close stdout early; that is not an error
+    let mut stdout = std::io::stdout().lock();
+    if matches.is_empty() {
+        let _ = writeln!(stdout, "No matching events");
+        return;
+    }
+
+    for event_match in matches {
+        let event = &event_match.event;
+        let _ = writeln!(
+            stdout,
+            "{} ({}) - {:.0}% via {}{}",
+            event.name,
+            event.character_name,
+            event_match.match_confidence * 100.0,
+            event_match.match_type,
+            provenance_note(&event_match.provenance)
+        );
+        let _ = writeln!(stdout, "    {}", signals_note(&event_match.signals));
+
+        for choice in &event.choices {
+            let recommended = event_match.recommended_choice.as_deref() == Some(choice.number.as_str());
+            let score = event_match
+                .choice_scores
+                .iter()
+                .find(|score| score.number == choice.number)
+                .map(|score| format!(" [score {:.1}{}]", score.score, if score.partial { "*" } else { "" }))
+                .unwrap_or_default();
+            let _ = writeln!(
+                stdout,
+                "  {} {}: {}{}",
+                if recommended { "*" } else { " " },
+                choice.number,
+                choice.text,
+                score
+            );
+            for line in choice.outcome.lines() {
+                let _ = writeln!(stdout, "       {}", line);
+            }
+        }
+        let _ = writeln!(stdout);
+    }
+}
+
+// Why an event matched: every signal with its score and the event text
+fn signals_note(signals: &[MatchSignal]) -> String {
+    let notes: Vec<String> = signals
+        .iter()
+        .map(|signal| {
+            let field = match &signal.choice {
+                Some(number) => format!("choice {}", number),
+                None => signal.field.replace('_', " "),
+            };
+            format!("{} {:.0}% {:?}", field, signal.score * 100.0, signal.text)
+        })
+        .collect();
+    format!("matched {}", notes.join(", "))
+}
+
+// Where an event came from, when that is not just the bundled database
+fn provenance_note(provenance: &Provenance) -> String {
+    let mut notes = Vec::new();
+    if provenance.source != BUNDLED_SOURCE {
+        notes.push(format!("from {}", provenance.source));
+    }
+    if !provenance.patched_by.is_empty() {
+        notes.push(format!("patched by {}", provenance.patched_by.join(", ")));
+    }
+
+    if notes.is_empty() {
+        String::new()
+    } else {
+        format!(" ({})", notes.join("; "))
+    }
+}
+
+fn print_json<T: Serialize + ?Sized>(value: &T) -> Result<(), AppError> {
+    let json = serde_json::to_string_pretty(value)
+        .map_err(|e| AppError::internal(format!("Failed to serialize output: {}", e)))?;
+    // Piping a large dump into `head` closes stdout early; that is not an error
+    let _ = writeln!(std::io::stdout().lock(), "{}", json);
+    Ok(())
+}
@@ -0,0 +1,342 @@
+// Where captured frames come from: the live screen, or recorded screenshots
+// played back from disk (for headless runs and replaying sessions).
+
+use crate::error::AppError;
+use image::GenericImageView;
+use screenshots::{DisplayInfo, Screen};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CaptureArea {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+pub trait CaptureSource: Send {
+    fn config(&self) -> CaptureSourceConfig;
+
+    fn capture(&mut self, area: &CaptureArea) -> Result<image::DynamicImage, AppError>;
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CaptureSourceConfig {
+    Screen,
+    // A single image or a directory of frames played back in file name order
+    Replay {
+        path: String,
+        #[serde(default)]
+        loop_frames: bool,
+        #[serde(default = "default_crop_to_area")]
+        crop_to_area: bool, // false for frames that were recorded already cropped
+    },
+}
+
+fn default_crop_to_area() -> bool {
+    true
+}
+
+impl CaptureSourceConfig {
+    pub fn open(&self) -> Result<Box<dyn CaptureSource>, AppError> {
+        match self {
+            CaptureSourceConfig::Screen => Ok(Box::new(ScreenCaptureSource)),
+            CaptureSourceConfig::Replay { path, loop_frames, crop_to_area } => {
+                let source = FileCaptureSource::open(Path::new(path), *loop_frames, *crop_to_area)?;
+                Ok(Box::new(source))
+            }
+        }
+    }
+}
+
+pub struct ScreenCaptureSource;
+
+impl CaptureSource for ScreenCaptureSource {
+    fn config(&self) -> CaptureSourceConfig {
+        CaptureSourceConfig::Screen
+    }
+
+    // `area` is in desktop coordinates (logical pixels, origin at the primary
+    // display), so it may lie on any monitor, including ones left of or above
+    // the primary one. The frontend sends CSS pixels, which are logical
+    // pixels, and so are screenshots' `DisplayInfo` geometry and
+    // `capture_area`: it multiplies by the display's scale factor itself, so
+    // on a HiDPI display the image is larger than the area.
+    fn capture(&mut self, area: &CaptureArea) -> Result<image::DynamicImage, AppError> {
+        // Get all screens
+        let screens = all_screens()?;
+
+        if screens.is_empty() {
+            return Err(AppError::NoScreens);
+        }
+
+        // Use the screen that contains most of the area
+        let screen = screens
+            .iter()
+            .max_by_key(|screen| overlap_area(area, &screen.display_info))
+            .filter(|screen| overlap_area(area, &screen.display_info) > 0)
+            .ok_or_else(|| AppError::InvalidArea {
+                area: Some(area.clone()),
+                reason: "the area is outside all displays".to_string(),
+            })?;
+        let display_info = screen.display_info;
+
+        // Clamp to that screen and make the area relative to its origin; i64
+        // so that huge areas cannot overflow. The overlap is non-empty, so the
+        // result lies within the display.
+        let (left, top, right, bottom) = overlap_bounds(area, &display_info);
+        let x = (left - display_info.x as i64) as i32;
+        let y = (top - display_info.y as i64) as i32;
+        let (width, height) = ((right - left) as u32, (bottom - top) as u32);
+
+        info!(
+            "Capturing {}x{} at ({}, {}) on display {} (scale {})",
+            width, height, x, y, display_info.id, display_info.scale_factor
+        );
+
+        // Only capture the needed region instead of the full screen
+        let screen_image = screen
+            .capture_area(x, y, width, height)
+            .map_err(|e| AppError::CaptureFailed { reason: e.to_string() })?;
+
+        let scaled = |length: u32| (length as f32 * display_info.scale_factor).round() as u32;
+        let size = (screen_image.width(), screen_image.height());
+        if size != (width, height) && size != (scaled(width), scaled(height)) {
+            info!(
+                "Warning: Captured {}x{} for a {}x{} area at scale {}, the region may be off",
+                size.0, size.1, width, height, display_info.scale_factor
+            );
+        }
+
+        // Convert screenshot to DynamicImage
+        let (width, height) = (screen_image.width(), screen_image.height());
+        let rgba_data = screen_image.rgba().to_vec();
+        let image_buffer = image::ImageBuffer::from_raw(width, height, rgba_data)
+            .ok_or_else(|| AppError::CaptureFailed { reason: "invalid image buffer".to_string() })?;
+        Ok(image::DynamicImage::ImageRgba8(image_buffer))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DisplayGeometry {
+    pub id: u32,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub scale_factor: f32,
+    pub rotation: f32,
+    pub is_primary: bool,
+}
+
+pub fn list_displays() -> Result<Vec<DisplayGeometry>, AppError> {
+    let screens = all_screens()?;
+
+    Ok(screens
+        .iter()
+        .map(|screen| {
+            let display = screen.display_info;
+            DisplayGeometry {
+                id: display.id,
+                x: display.x,
+                y: display.y,
+                width: display.width,
+                height: display.height,
+                scale_factor: display.scale_factor,
+                rotation: display.rotation,
+                is_primary: display.is_primary,
+            }
+        })
+        .collect())
+}
+
+fn all_screens() -> Result<Vec<Screen>, AppError> {
+    Screen::all().map_err(|e| AppError::CaptureFailed {
+        reason: format!("could not list screens: {}", e),
+    })
+}
+
+// Left, top, right and bottom of the part of `area` on `display`
+fn overlap_bounds(area: &CaptureArea, display: &DisplayInfo) -> (i64, i64, i64, i64) {
+    let left = area.x.max(display.x) as i64;
+    let top = area.y.max(display.y) as i64;
+    let right = (area.x as i64 + area.width as i64).min(display.x as i64 + display.width as i64);
+    let bottom = (area.y as i64 + area.height as i64).min(display.y as i64 + display.height as i64);
+    (left, top, right, bottom)
+}
+
+fn overlap_area(area: &CaptureArea, display: &DisplayInfo) -> i64 {
+    let (left, top, right, bottom) = overlap_bounds(area, display);
+    (right - left).max(0) * (bottom - top).max(0)
+}
+
+pub struct FileCaptureSource {
+    path: PathBuf,
+    frames: Vec<PathBuf>,
+    next_frame: usize,
+    loop_frames: bool,
+    crop_to_area: bool,
+}
+
+impl FileCaptureSource {
+    pub fn open(path: &Path, loop_frames: bool, crop_to_area: bool) -> Result<Self, AppError> {
+        let replay_failed = |reason: String| AppError::ReplayFailed {
+            path: path.display().to_string(),
+            reason,
+        };
+
+        let frames = if path.is_dir() {
+            let mut frames: Vec<PathBuf> = std::fs::read_dir(path)
+                .map_err(|e| replay_failed(format!("cannot read directory: {}", e)))?
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|frame| is_image_file(frame))
+                .collect();
+            frames.sort();
+            frames
+        } else if path.is_file() {
+            vec![path.to_path_buf()]
+        } else {
+            return Err(replay_failed("not found".to_string()));
+        };
+
+        if frames.is_empty() {
+            return Err(replay_failed("no image frames found".to_string()));
+        }
+
+        info!("Replaying {} frame(s) from: {}", frames.len(), path.display());
+        Ok(FileCaptureSource {
+            path: path.to_path_buf(),
+            frames,
+            next_frame: 0,
+            loop_frames,
+            crop_to_area,
+        })
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+}
+
+impl CaptureSource for FileCaptureSource {
+    fn config(&self) -> CaptureSourceConfig {
+        CaptureSourceConfig::Replay {
+            path: self.path.to_string_lossy().to_string(),
+            loop_frames: self.loop_frames,
+            crop_to_area: self.crop_to_area,
+        }
+    }
+
+    fn capture(&mut self, area: &CaptureArea) -> Result<image::DynamicImage, AppError> {
+        if self.next_frame >= self.frames.len() {
+            if !self.loop_frames {
+                return Err(AppError::ReplayFinished { frames: self.frames.len() });
+            }
+            self.next_frame = 0;
+        }
+
+        let frame_path = &self.frames[self.next_frame];
+        self.next_frame += 1;
+
+        let frame = image::open(frame_path)
+            .map_err(|e| AppError::ReplayFailed {
+                path: frame_path.display().to_string(),
+                reason: e.to_string(),
+            })?;
+
+        if self.crop_to_area {
+            crop_image(&frame, area)
+        } else {
+            Ok(frame)
+        }
+    }
+}
+
+fn is_image_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| matches!(extension.to_lowercase().as_str(), "png" | "jpg" | "jpeg"))
+        .unwrap_or(false)
+}
+
+pub fn crop_image(image: &image::DynamicImage, area: &CaptureArea) -> Result<image::DynamicImage, AppError> {
+    let (img_width, img_height) = image.dimensions();
+
+    // Ensure crop area is within bounds; the part of the area left of or
+    // above the image is not part of the crop
+    let clip = |start: i32, length: u32, limit: u32| {
+        let end = (start as i64 + length as i64).min(limit as i64);
+        let start = (start as i64).clamp(0, limit as i64);
+        (start as u32, (end - start).max(0) as u32)
+    };
+    let (x, width) = clip(area.x, area.width, img_width);
+    let (y, height) = clip(area.y, area.height, img_height);
+
+    if width == 0 || height == 0 {
+        return Err(AppError::InvalidArea {
+            area: Some(area.clone()),
+            reason: format!("nothing to crop from a {}x{} image", img_width, img_height),
+        });
+    }
+
+    let cropped = image.crop_imm(x, y, width, height);
+    Ok(cropped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn display(x: i32, y: i32, width: u32, height: u32) -> DisplayInfo {
+        DisplayInfo { id: 1, x, y, width, height, rotation: 0.0, scale_factor: 2.0, frequency: 60.0, is_primary: true }
+    }
+
+    #[test]
+    fn overlap_of_huge_areas_does_not_overflow() {
+        let display = display(-1920, 0, 1920, 1080);
+        let area = CaptureArea { x: i32::MAX, y: i32::MAX, width: u32::MAX, height: u32::MAX };
+        assert_eq!(overlap_area(&area, &display), 0);
+
+        let area = CaptureArea { x: i32::MIN, y: -100, width: u32::MAX, height: u32::MAX };
+        assert_eq!(overlap_bounds(&area, &display), (-1920, 0, 0, 1080));
+        assert_eq!(overlap_area(&area, &display), 1920 * 1080);
+    }
+
+    #[test]
+    fn crops_within_the_image() {
+        let image = image::DynamicImage::new_rgba8(100, 50);
+        let cropped = crop_image(&image, &CaptureArea { x: 10, y: 5, width: 20, height: 30 }).unwrap();
+        assert_eq!(cropped.dimensions(), (20, 30));
+
+        // Parts of the area off the image are cut off, on either side
+        let cropped = crop_image(&image, &CaptureArea { x: -10, y: 40, width: 20, height: 500 }).unwrap();
+        assert_eq!(cropped.dimensions(), (10, 10));
+        let cropped = crop_image(&image, &CaptureArea { x: 90, y: -45, width: 20, height: 50 }).unwrap();
+        assert_eq!(cropped.dimensions(), (10, 5));
+
+        assert!(crop_image(&image, &CaptureArea { x: 100, y: 0, width: 10, height: 10 }).is_err());
+        assert!(crop_image(&image, &CaptureArea { x: -20, y: 0, width: 10, height: 10 }).is_err());
+    }
+
+    #[test]
+    fn single_file_replays_finish_unless_looping() {
+        let dir = std::env::temp_dir().join(format!("uma-helper-replay-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("frame.png");
+        image::DynamicImage::new_rgba8(20, 10).save(&path).unwrap();
+        let area = CaptureArea { x: 0, y: 0, width: 20, height: 10 };
+
+        let mut source = FileCaptureSource::open(&path, false, true).unwrap();
+        assert!(source.capture(&area).is_ok());
+        assert!(matches!(source.capture(&area), Err(AppError::ReplayFinished { frames: 1 })));
+
+        let mut source = FileCaptureSource::open(&path, true, true).unwrap();
+        assert!(source.capture(&area).is_ok());
+        assert!(source.capture(&area).is_ok());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,327 @@
+// Geometric cleanup of hand-dragged captures: trims uniform borders, drops
+// connected components that cannot be text and straightens small rotations.
+// Like morphology, these expect dark text over a light background.
+
+use image::{imageops, GrayImage, Luma};
+use tracing::info;
+
+// Pixels darker than this count as text (foreground)
+const FOREGROUND_THRESHOLD: u8 = 128;
+
+// Fraction of a row/column that must match its median for it to be a border
+const UNIFORM_FRACTION: f32 = 0.98;
+
+// Value used in the debug overlays for pixels a step threw away
+const OVERLAY_REMOVED: u8 = 170;
+
+pub struct CleanupOutput {
+    pub image: GrayImage,
+    pub overlay: Option<GrayImage>, // What the step removed, for the debug stages
+    pub trimmed: (u32, u32), // Pixels cut from the left and top
+}
+
+impl CleanupOutput {
+    pub fn image(image: GrayImage) -> Self {
+        CleanupOutput { image, overlay: None, trimmed: (0, 0) }
+    }
+}
+
+// Crops rows and columns at the edges that are (nearly) a single colour,
+// keeping `margin` pixels of them around the content
+pub fn trim_borders(gray_image: GrayImage, tolerance: u8, margin: u32, debug: bool) -> CleanupOutput {
+    let (width, height) = gray_image.dimensions();
+    let is_uniform = |pixels: &mut dyn Iterator<Item = u8>| {
+        let mut histogram = [0u32; 256];
+        let mut count = 0;
+        for value in pixels {
+            histogram[value as usize] += 1;
+            count += 1;
+        }
+
+        let mut seen = 0;
+        let median = histogram
+            .iter()
+            .position(|&bucket| {
+                seen += bucket;
+                seen * 2 >= count
+            })
+            .unwrap_or(0) as i32;
+        let close: u32 = histogram
+            .iter()
+            .enumerate()
+            .filter(|(value, _)| (*value as i32 - median).abs() <= tolerance as i32)
+            .map(|(_, &bucket)| bucket)
+            .sum();
+        close as f32 >= count as f32 * UNIFORM_FRACTION
+    };
+    let row_uniform = |y: u32| is_uniform(&mut (0..width).map(|x| gray_image.get_pixel(x, y)[0]));
+
+    let Some(top) = (0..height).find(|&y| !row_uniform(y)) else {
+        return CleanupOutput::image(gray_image); // Nothing but border
+    };
+    let bottom = (0..height).rev().find(|&y| !row_uniform(y)).unwrap_or(top);
+
+    let column_uniform = |x: u32| is_uniform(&mut (top..=bottom).map(|y| gray_image.get_pixel(x, y)[0]));
+    let left = (0..width).find(|&x| !column_uniform(x)).unwrap_or(0);
+    let right = (0..width).rev().find(|&x| !column_uniform(x)).unwrap_or(width - 1).max(left);
+
+    let left = left.saturating_sub(margin);
+    let top = top.saturating_sub(margin);
+    let right = (right + margin).min(width - 1);
+    let bottom = (bottom + margin).min(height - 1);
+    if left == 0 && top == 0 && right == width - 1 && bottom == height - 1 {
+        return CleanupOutput::image(gray_image);
+    }
+    info!("Trimmed borders to {}x{} at ({}, {})", right - left + 1, bottom - top + 1, left, top);
+
+    let overlay = debug.then(|| {
+        let mut overlay = gray_image.clone();
+        for (x, y, pixel) in overlay.enumerate_pixels_mut() {
+            if x < left || x > right || y < top || y > bottom {
+                *pixel = Luma([OVERLAY_REMOVED]);
+            }
+        }
+        overlay
+    });
+    let image = imageops::crop_imm(&gray_image, left, top, right - left + 1, bottom - top + 1).to_image();
+
+    CleanupOutput { image, overlay, trimmed: (left, top) }
+}
+
+struct Component {
+    pixels: Vec<(u32, u32)>,
+    min: (u32, u32),
+    max: (u32, u32),
+}
+
+// 8-connected regions of foreground pixels
+fn find_components(gray_image: &GrayImage) -> Vec<Component> {
+    let (width, height) = gray_image.dimensions();
+    let is_foreground = |x: u32, y: u32| gray_image.get_pixel(x, y)[0] < FOREGROUND_THRESHOLD;
+    let mut visited = vec![false; (width * height) as usize];
+    let mut components = Vec::new();
+
+    for start_y in 0..height {
+        for start_x in 0..width {
+            if visited[(start_y * width + start_x) as usize] || !is_foreground(start_x, start_y) {
+                continue;
+            }
+
+            let mut component = Component { pixels: Vec::new(), min: (start_x, start_y), max: (start_x, start_y) };
+            let mut stack = vec![(start_x, start_y)];
+            visited[(start_y * width + start_x) as usize] = true;
+            while let Some((x, y)) = stack.pop() {
+                component.pixels.push((x, y));
+                component.min = (component.min.0.min(x), component.min.1.min(y));
+                component.max = (component.max.0.max(x), component.max.1.max(y));
+
+                for ny in y.saturating_sub(1)..=(y + 1).min(height - 1) {
+                    for nx in x.saturating_sub(1)..=(x + 1).min(width - 1) {
+                        let index = (ny * width + nx) as usize;
+                        if !visited[index] && is_foreground(nx, ny) {
+                            visited[index] = true;
+                            stack.push((nx, ny));
+                        }
+                    }
+                }
+            }
+            components.push(component);
+        }
+    }
+    components
+}
+
+// Whites out specks smaller than `min_area`, shapes taller than
+// `max_height_fraction` of the image (icons, frame edges) and thin rules
+// whose aspect ratio exceeds `max_aspect`
+pub fn remove_components(
+    gray_image: GrayImage,
+    min_area: u32,
+    max_height_fraction: f32,
+    max_aspect: f32,
+    debug: bool,
+) -> CleanupOutput {
+    let max_height = gray_image.height() as f32 * max_height_fraction;
+    let mut image = gray_image;
+    let mut overlay = debug.then(|| image.clone());
+    let mut removed = 0;
+
+    for component in find_components(&image) {
+        let width = (component.max.0 - component.min.0 + 1) as f32;
+        let height = (component.max.1 - component.min.1 + 1) as f32;
+        let aspect = (width / height).max(height / width);
+        let is_text = component.pixels.len() as u32 >= min_area && height <= max_height && aspect <= max_aspect;
+        if is_text {
+            continue;
+        }
+
+        removed += 1;
+        for &(x, y) in &component.pixels {
+            image.put_pixel(x, y, Luma([255]));
+            if let Some(overlay) = &mut overlay {
+                overlay.put_pixel(x, y, Luma([OVERLAY_REMOVED]));
+            }
+        }
+    }
+    info!("Removed {} non-text component(s)", removed);
+
+    CleanupOutput { image, overlay, trimmed: (0, 0) }
+}
+
+// Sharpness of the horizontal projection when text lines are assumed to run
+// at `angle`: lines of text give a few tall peaks instead of a smeared profile
+fn projection_score(points: &[(f32, f32)], angle: f32, width: u32, height: u32) -> f64 {
+    let slope = angle.to_radians().tan();
+    let offset = (width as f32 * slope.abs()).ceil();
+    let mut bins = vec![0u32; height as usize + 2 * offset as usize + 1];
+    for &(x, y) in points {
+        let bin = (y - x * slope + offset).round();
+        if bin >= 0.0 && (bin as usize) < bins.len() {
+            bins[bin as usize] += 1;
+        }
+    }
+    bins.iter().map(|&count| (count as f64).powi(2)).sum()
+}
+
+// Finds the text angle within ±`max_angle` degrees from the projection
+// profile and rotates it level. Word boxes are not rotated back; at these
+// angles the error is a few pixels.
+pub fn deskew(gray_image: GrayImage, max_angle: f32) -> CleanupOutput {
+    let (width, height) = gray_image.dimensions();
+    let points: Vec<(f32, f32)> = gray_image
+        .enumerate_pixels()
+        .filter(|(_, _, pixel)| pixel[0] < FOREGROUND_THRESHOLD)
+        .map(|(x, y, _)| (x as f32, y as f32))
+        .collect();
+    if points.is_empty() || width < 2 || height < 2 {
+        return CleanupOutput::image(gray_image);
+    }
+
+    // Coarse search in half degrees, then refine around the best in tenths
+    let best_of = |angles: Vec<f32>| {
+        angles
+            .into_iter()
+            .map(|angle| (angle, projection_score(&points, angle, width, height)))
+            .fold((0.0, f64::MIN), |best, candidate| if candidate.1 > best.1 { candidate } else { best })
+            .0
+    };
+    let steps = (max_angle / 0.5).ceil() as i32;
+    let coarse = best_of((-steps..=steps).map(|i| (i as f32 * 0.5).clamp(-max_angle, max_angle)).collect());
+    let angle = best_of((-5..=5).map(|i| (coarse + i as f32 * 0.1).clamp(-max_angle, max_angle)).collect());
+
+    if angle.abs() < 0.1 {
+        return CleanupOutput::image(gray_image);
+    }
+    info!("Deskewing by {:.1} degrees", angle);
+
+    // Each output pixel samples the input rotated by the detected angle (bilinear)
+    let (sin, cos) = angle.to_radians().sin_cos();
+    let (center_x, center_y) = (width as f32 / 2.0, height as f32 / 2.0);
+    let sample = |x: f32, y: f32| -> f32 {
+        if x < 0.0 || y < 0.0 || x > (width - 1) as f32 || y > (height - 1) as f32 {
+            return 255.0; // Background
+        }
+        let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+        let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+        let (fx, fy) = (x - x0 as f32, y - y0 as f32);
+        let value = |x: u32, y: u32| gray_image.get_pixel(x, y)[0] as f32;
+        let top = value(x0, y0) * (1.0 - fx) + value(x1, y0) * fx;
+        let bottom = value(x0, y1) * (1.0 - fx) + value(x1, y1) * fx;
+        top * (1.0 - fy) + bottom * fy
+    };
+
+    let mut image = GrayImage::new(width, height);
+    for (x, y, pixel) in image.enumerate_pixels_mut() {
+        let (u, v) = (x as f32 - center_x, y as f32 - center_y);
+        let value = sample(u * cos - v * sin + center_x, u * sin + v * cos + center_y);
+        *pixel = Luma([value.round().clamp(0.0, 255.0) as u8]);
+    }
+
+    CleanupOutput::image(image)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A 3 pixel thick bar across the image, rising by `angle` degrees to the right
+    fn rotated_bar(angle: f32) -> GrayImage {
+        let slope = angle.to_radians().tan();
+        GrayImage::from_fn(200, 60, |x, y| {
+            let centre = 30.0 + (x as f32 - 100.0) * slope;
+            Luma([if (y as f32 - centre).abs() <= 1.0 { 0 } else { 255 }])
+        })
+    }
+
+    // Rows holding foreground pixels, from the first to the last
+    fn foreground_rows(image: &GrayImage) -> u32 {
+        let rows: Vec<u32> = image
+            .enumerate_pixels()
+            .filter(|(_, _, pixel)| pixel[0] < FOREGROUND_THRESHOLD)
+            .map(|(_, y, _)| y)
+            .collect();
+        rows.iter().max().unwrap() - rows.iter().min().unwrap() + 1
+    }
+
+    #[test]
+    fn deskew_levels_both_rotations() {
+        for angle in [3.0, -3.0] {
+            let skewed = rotated_bar(angle);
+            assert!(foreground_rows(&skewed) > 10);
+            let level = deskew(skewed, 5.0).image;
+            assert!(foreground_rows(&level) <= 4, "{} degrees left {} rows", angle, foreground_rows(&level));
+        }
+
+        // Level text stays level, and blank images are left alone
+        assert_eq!(foreground_rows(&deskew(rotated_bar(0.0), 5.0).image), 3);
+        let blank = GrayImage::from_pixel(20, 20, Luma([255]));
+        assert_eq!(deskew(blank.clone(), 5.0).image, blank);
+    }
+
+    #[test]
+    fn trims_a_border_frame() {
+        // A dark 5 pixel frame around a light panel with a word in it
+        let image = GrayImage::from_fn(60, 40, |x, y| {
+            let frame = x < 5 || y < 5 || x >= 55 || y >= 35;
+            let word = (20..30).contains(&x) && (15..20).contains(&y);
+            Luma([if frame { 40 } else if word { 0 } else { 230 }])
+        });
+
+        // The frame rows are uniform; between them the frame's sides are part of
+        // every row, while the columns beside the word are uniform
+        let output = trim_borders(image.clone(), 12, 2, true);
+        assert_eq!(output.trimmed, (18, 3));
+        assert_eq!(output.image.dimensions(), (14, 34));
+        let overlay = output.overlay.unwrap();
+        assert_eq!(overlay.get_pixel(0, 0)[0], OVERLAY_REMOVED);
+        assert_eq!(overlay.get_pixel(25, 17)[0], 0);
+
+        // Nothing to trim when the content reaches the edges
+        let output = trim_borders(image, 12, 64, false);
+        assert_eq!((output.trimmed, output.image.dimensions()), ((0, 0), (60, 40)));
+    }
+
+    #[test]
+    fn removes_specks_and_keeps_strokes() {
+        let mut image = GrayImage::from_pixel(40, 20, Luma([255]));
+        for (x, y) in [(2, 2), (3, 3), (30, 15)] {
+            image.put_pixel(x, y, Luma([0])); // Specks of one or two pixels
+        }
+        for y in 5..12 {
+            for x in 10..14 {
+                image.put_pixel(x, y, Luma([0])); // A stroke of 28 pixels
+            }
+        }
+
+        let output = remove_components(image, 6, 0.8, 12.0, false);
+        let remaining: Vec<(u32, u32)> = output
+            .image
+            .enumerate_pixels()
+            .filter(|(_, _, pixel)| pixel[0] == 0)
+            .map(|(x, y, _)| (x, y))
+            .collect();
+        assert_eq!(remaining.len(), 28);
+        assert!(remaining.iter().all(|&(x, y)| (10..14).contains(&x) && (5..12).contains(&y)));
+        assert!(output.overlay.is_none());
+    }
+}
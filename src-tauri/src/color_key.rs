@@ -0,0 +1,231 @@
+// Colour-keyed text extraction: keeps only pixels close to the known text
+// colours of the game UI, which separates text from coloured banners and
+// textured buttons far better than plain luminance.
+
+use crate::error::AppError;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorSpace {
+    Hsv, // Tolerance is a 0.0-1.0 distance over hue, saturation and value
+    #[default]
+    Lab, // Tolerance is a CIE76 delta E
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct ColorKey {
+    pub colors: Vec<String>, // "#RRGGBB"
+    pub space: ColorSpace,
+    pub tolerance: f32,
+}
+
+impl Default for ColorKey {
+    fn default() -> Self {
+        ColorKey {
+            colors: vec!["#FFFFFF".to_string()],
+            space: ColorSpace::Lab,
+            tolerance: 20.0,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ColorKeyPreset {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub key: ColorKey,
+}
+
+pub fn presets() -> Vec<ColorKeyPreset> {
+    let key = |colors: &[&str], space: ColorSpace, tolerance: f32| ColorKey {
+        colors: colors.iter().map(|color| color.to_string()).collect(),
+        space,
+        tolerance,
+    };
+
+    vec![
+        ColorKeyPreset {
+            name: "event_title",
+            description: "White event title text on the coloured banner (outline dropped)",
+            key: key(&["#FFFFFF"], ColorSpace::Hsv, 0.22),
+        },
+        ColorKeyPreset {
+            name: "choice_button",
+            description: "Dark brown choice text on the light choice buttons",
+            key: key(&["#5B3A1E", "#7A4E2A"], ColorSpace::Lab, 22.0),
+        },
+    ]
+}
+
+pub fn preset(name: &str) -> Option<ColorKey> {
+    presets().into_iter().find(|preset| preset.name == name).map(|preset| preset.key)
+}
+
+impl ColorKey {
+    pub fn validate(&self) -> Result<(), AppError> {
+        if self.colors.is_empty() {
+            return Err(AppError::invalid_input("A colour key needs at least one colour"));
+        }
+        for color in &self.colors {
+            parse_hex_color(color)?;
+        }
+        if !self.tolerance.is_finite() || self.tolerance <= 0.0 {
+            return Err(AppError::invalid_input("Colour key tolerance must be positive"));
+        }
+        Ok(())
+    }
+}
+
+fn parse_hex_color(color: &str) -> Result<[u8; 3], AppError> {
+    let hex = color.trim().trim_start_matches('#');
+    let invalid = || AppError::invalid_input(format!("Invalid colour '{}', expected #RRGGBB", color));
+    if hex.len() != 6 || !hex.is_ascii() {
+        return Err(invalid());
+    }
+
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| invalid());
+    Ok([channel(0)?, channel(2)?, channel(4)?])
+}
+
+// Dark text on white: pixels within the tolerance of a key colour become
+// black, pixels beyond twice the tolerance white, with a ramp in between to
+// keep the anti-aliased edges Tesseract relies on. Invalid colours are skipped
+// (configs are validated when they are set).
+pub fn extract(image: &image::DynamicImage, key: &ColorKey) -> image::GrayImage {
+    let targets: Vec<[f32; 3]> = key
+        .colors
+        .iter()
+        .filter_map(|color| parse_hex_color(color).ok())
+        .map(|rgb| to_space(rgb, key.space))
+        .collect();
+
+    let rgb_image = image.to_rgb8();
+    let mut mask = image::GrayImage::new(rgb_image.width(), rgb_image.height());
+    for (x, y, pixel) in rgb_image.enumerate_pixels() {
+        let color = to_space(pixel.0, key.space);
+        let distance = targets
+            .iter()
+            .map(|target| color_distance(&color, target, key.space))
+            .fold(f32::INFINITY, f32::min);
+
+        let ramp = ((distance - key.tolerance) / key.tolerance).clamp(0.0, 1.0);
+        mask.put_pixel(x, y, image::Luma([(ramp * 255.0).round() as u8]));
+    }
+    mask
+}
+
+fn to_space(rgb: [u8; 3], space: ColorSpace) -> [f32; 3] {
+    match space {
+        ColorSpace::Hsv => rgb_to_hsv(rgb),
+        ColorSpace::Lab => rgb_to_lab(rgb),
+    }
+}
+
+fn color_distance(a: &[f32; 3], b: &[f32; 3], space: ColorSpace) -> f32 {
+    match space {
+        ColorSpace::Hsv => {
+            // Hue is circular and meaningless for greys, so weight it by saturation
+            let hue_difference = (a[0] - b[0]).abs();
+            let hue_difference = hue_difference.min(360.0 - hue_difference) / 180.0;
+            let hue_term = hue_difference * a[1].min(b[1]);
+            (hue_term.powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)).sqrt()
+        }
+        ColorSpace::Lab => ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)).sqrt(),
+    }
+}
+
+// Hue in degrees, saturation and value in 0.0-1.0
+fn rgb_to_hsv(rgb: [u8; 3]) -> [f32; 3] {
+    let [r, g, b] = rgb.map(|channel| channel as f32 / 255.0);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let delta = max - min;
+
+    let hue = if delta == 0.0 {
+        0.0
+    } else if max == r {
+        60.0 * (((g - b) / delta).rem_euclid(6.0))
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+    let saturation = if max == 0.0 { 0.0 } else { delta / max };
+
+    [hue, saturation, max]
+}
+
+// sRGB to CIE L*a*b* under D65
+fn rgb_to_lab(rgb: [u8; 3]) -> [f32; 3] {
+    let linear = rgb.map(|channel| {
+        let c = channel as f32 / 255.0;
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    });
+
+    let x = (0.4124 * linear[0] + 0.3576 * linear[1] + 0.1805 * linear[2]) / 0.95047;
+    let y = 0.2126 * linear[0] + 0.7152 * linear[1] + 0.0722 * linear[2];
+    let z = (0.0193 * linear[0] + 0.1192 * linear[1] + 0.9505 * linear[2]) / 1.08883;
+
+    let f = |t: f32| {
+        if t > 0.008856 {
+            t.cbrt()
+        } else {
+            7.787 * t + 16.0 / 116.0
+        }
+    };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: [f32; 3], expected: [f32; 3], tolerance: f32) {
+        for (actual, expected) in actual.iter().zip(expected) {
+            assert!((actual - expected).abs() <= tolerance, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn parses_hex_colors() {
+        assert_eq!(parse_hex_color("#5B3A1E").unwrap(), [0x5B, 0x3A, 0x1E]);
+        assert_eq!(parse_hex_color(" ffffff ").unwrap(), [255, 255, 255]);
+        for bad in ["", "#FFF", "#FFFFFFF", "#GGGGGG", "#FFFFé"] {
+            assert!(parse_hex_color(bad).is_err(), "{:?}", bad);
+        }
+        assert!(presets().iter().all(|preset| preset.key.validate().is_ok()));
+    }
+
+    #[test]
+    fn converts_known_colors() {
+        assert_close(rgb_to_hsv([255, 0, 0]), [0.0, 1.0, 1.0], 1e-4);
+        assert_close(rgb_to_hsv([0, 128, 255]), [209.9, 1.0, 1.0], 0.1);
+        assert_close(rgb_to_hsv([128, 128, 128]), [0.0, 0.0, 0.502], 1e-3);
+
+        assert_close(rgb_to_lab([255, 255, 255]), [100.0, 0.0, 0.0], 0.05);
+        assert_close(rgb_to_lab([0, 0, 0]), [0.0, 0.0, 0.0], 0.05);
+        assert_close(rgb_to_lab([255, 0, 0]), [53.24, 80.09, 67.20], 0.1);
+    }
+
+    #[test]
+    fn extract_keeps_key_colored_pixels() {
+        // White key in Lab with a tolerance of 20: grey 171 has L* 70, so it is
+        // halfway up the ramp between 20 and 40
+        let pixels = [[255, 255, 255], [230, 230, 230], [171, 171, 171], [0, 0, 0], [255, 0, 0]];
+        let image = image::RgbImage::from_fn(pixels.len() as u32, 1, |x, _| image::Rgb(pixels[x as usize]));
+        let mask = extract(&image::DynamicImage::ImageRgb8(image), &ColorKey::default());
+        let values: Vec<u8> = mask.pixels().map(|pixel| pixel[0]).collect();
+
+        assert_eq!(values[..2], [0, 0]);
+        assert!((125..=131).contains(&values[2]), "{}", values[2]);
+        assert_eq!(values[3..], [255, 255]);
+    }
+}
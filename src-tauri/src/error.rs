@@ -0,0 +1,160 @@
+// Errors returned to the frontend. They serialise as
+// `{ "code": "...", "message": "...", "details": { ... } }` so the UI can
+// branch on the stable `code` and still show the human readable `message`.
+
+use crate::capture::CaptureArea;
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use serde_json::json;
+use std::fmt;
+
+#[derive(Debug, Clone)]
+pub enum AppError {
+    NoScreens,
+    CaptureFailed { reason: String },
+    InvalidArea { area: Option<CaptureArea>, reason: String },
+    ReplayFailed { path: String, reason: String },
+    ReplayFinished { frames: usize },
+    TessdataMissing { language: String, tessdata_path: Option<String>, reason: String },
+    OcrFailed { reason: String },
+    ImageFailed { reason: String },
+    EventsLoadFailed { reason: String },
+    InvalidInput { reason: String },
+    SaveFailed { path: String, reason: String },
+    Internal { reason: String },
+}
+
+impl AppError {
+    pub fn invalid_input(reason: impl Into<String>) -> Self {
+        AppError::InvalidInput { reason: reason.into() }
+    }
+
+    pub fn internal(reason: impl Into<String>) -> Self {
+        AppError::Internal { reason: reason.into() }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::NoScreens => "no_screens",
+            AppError::CaptureFailed { .. } => "capture_failed",
+            AppError::InvalidArea { .. } => "invalid_area",
+            AppError::ReplayFailed { .. } => "replay_failed",
+            AppError::ReplayFinished { .. } => "replay_finished",
+            AppError::TessdataMissing { .. } => "tessdata_missing",
+            AppError::OcrFailed { .. } => "ocr_failed",
+            AppError::ImageFailed { .. } => "image_failed",
+            AppError::EventsLoadFailed { .. } => "events_load_failed",
+            AppError::InvalidInput { .. } => "invalid_input",
+            AppError::SaveFailed { .. } => "save_failed",
+            AppError::Internal { .. } => "internal",
+        }
+    }
+
+    pub fn details(&self) -> serde_json::Value {
+        match self {
+            AppError::NoScreens => json!({}),
+            AppError::InvalidArea { area, reason } => json!({ "area": area, "reason": reason }),
+            AppError::ReplayFailed { path, reason } | AppError::SaveFailed { path, reason } => {
+                json!({ "path": path, "reason": reason })
+            }
+            AppError::ReplayFinished { frames } => json!({ "frames": frames }),
+            AppError::TessdataMissing { language, tessdata_path, reason } => json!({
+                "language": language,
+                "tessdata_path": tessdata_path,
+                "reason": reason,
+                "hint": tessdata_hint(),
+            }),
+            AppError::CaptureFailed { reason }
+            | AppError::OcrFailed { reason }
+            | AppError::ImageFailed { reason }
+            | AppError::EventsLoadFailed { reason }
+            | AppError::InvalidInput { reason }
+            | AppError::Internal { reason } => json!({ "reason": reason }),
+        }
+    }
+}
+
+// Platform specific setup advice shown alongside tessdata errors
+pub fn tessdata_hint() -> &'static str {
+    if cfg!(target_os = "windows") {
+        "To fix this on Windows:\n1. Install Tesseract OCR from https://github.com/UB-Mannheim/tesseract/wiki\n2. Or set TESSDATA_PREFIX environment variable to your tessdata directory\n3. Or place tessdata folder next to the application executable"
+    } else {
+        "Please ensure Tesseract is installed with language data"
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::NoScreens => write!(f, "No screens found"),
+            AppError::CaptureFailed { reason } => write!(f, "Failed to capture screen: {}", reason),
+            AppError::InvalidArea { reason, .. } => write!(f, "Invalid capture area: {}", reason),
+            AppError::ReplayFailed { path, reason } => write!(f, "Cannot replay {}: {}", path, reason),
+            AppError::ReplayFinished { .. } => write!(f, "Replay finished: no more frames"),
+            AppError::TessdataMissing { language, reason, .. } => {
+                write!(f, "Failed to initialize Tesseract for '{}': {}", language, reason)
+            }
+            AppError::OcrFailed { reason } => write!(f, "OCR failed: {}", reason),
+            AppError::ImageFailed { reason } => write!(f, "Image processing failed: {}", reason),
+            AppError::EventsLoadFailed { reason } => write!(f, "Failed to load events: {}", reason),
+            AppError::InvalidInput { reason } => write!(f, "{}", reason),
+            AppError::SaveFailed { path, reason } => write!(f, "Failed to save {}: {}", path, reason),
+            AppError::Internal { reason } => write!(f, "Internal error: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut error = serializer.serialize_struct("AppError", 3)?;
+        error.serialize_field("code", self.code())?;
+        error.serialize_field("message", &self.to_string())?;
+        error.serialize_field("details", &self.details())?;
+        error.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serializes_code_message_and_details() {
+        let error = AppError::InvalidArea {
+            area: Some(CaptureArea { x: -5, y: 10, width: 0, height: 20 }),
+            reason: "Width must be positive".to_string(),
+        };
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            json!({
+                "code": "invalid_area",
+                "message": "Invalid capture area: Width must be positive",
+                "details": {
+                    "area": { "x": -5, "y": 10, "width": 0, "height": 20 },
+                    "reason": "Width must be positive",
+                },
+            })
+        );
+
+        let error = AppError::SaveFailed { path: "/tmp/profiles.json".to_string(), reason: "read-only".to_string() };
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            json!({
+                "code": "save_failed",
+                "message": "Failed to save /tmp/profiles.json: read-only",
+                "details": { "path": "/tmp/profiles.json", "reason": "read-only" },
+            })
+        );
+
+        assert_eq!(
+            serde_json::to_value(AppError::internal("lock poisoned")).unwrap(),
+            json!({
+                "code": "internal",
+                "message": "Internal error: lock poisoned",
+                "details": { "reason": "lock poisoned" },
+            })
+        );
+    }
+}
@@ -0,0 +1,160 @@
+// Where the app's events and the user's own data live. The default keeps
+// everything in memory, with the trigram index for lookups; with the
+// `sqlite` feature the events, lookup history, notes and overrides share an
+// SQLite file with a full-text index (see `event_sqlite`).
+
+use crate::error::AppError;
+use crate::event_layers;
+use crate::events::{self, JsonEvent};
+use crate::search_index::{CandidateIndex, SearchIndex};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::info;
+
+// Lookups the in-memory backend remembers for the session
+const MEMORY_HISTORY_LIMIT: usize = 500;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LookupRecord {
+    pub at: u64, // Unix seconds
+    pub origin: String, // "capture", "watch" or "manual"
+    pub text: String,
+    pub event_name: Option<String>, // Best match, if there was one
+    pub character_name: Option<String>,
+    pub confidence: Option<f32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EventNote {
+    pub name: String,
+    pub character_name: String,
+    pub note: String,
+    pub updated_at: u64, // Unix seconds
+}
+
+pub struct LoadedEvents {
+    pub events: Vec<JsonEvent>,
+    pub index: Box<dyn CandidateIndex + Send + Sync>,
+    pub source: String, // File the events were read from
+}
+
+pub trait EventBackend: Send + Sync {
+    fn kind(&self) -> &'static str;
+
+    // Reads events.json with the user layers and indexes it. `strict` is set
+    // for reloads, which must not replace good data with a broken file.
+    fn load(&self, strict: bool) -> Result<LoadedEvents, AppError>;
+
+    fn record_lookup(&self, record: LookupRecord) -> Result<(), AppError>;
+
+    // Newest first
+    fn history(&self, limit: usize) -> Result<Vec<LookupRecord>, AppError>;
+
+    // An empty note removes it
+    fn set_note(&self, name: &str, character_name: &str, note: &str) -> Result<(), AppError>;
+
+    fn notes(&self) -> Result<Vec<EventNote>, AppError>;
+
+    // Stores an event layer (the format of the user layer files) under
+    // `name`, replacing one with the same name. Applies from the next load.
+    fn save_override(&self, name: &str, layer: &str) -> Result<(), AppError>;
+}
+
+pub fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs())
+}
+
+// Override names double as file names
+pub fn validate_override(name: &str, layer: &str) -> Result<(), AppError> {
+    let valid_name = !name.is_empty()
+        && name.len() <= 64
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if !valid_name {
+        return Err(AppError::invalid_input("Override names use 1-64 letters, digits, '_' or '-'"));
+    }
+    event_layers::parse_layer(name.to_string(), layer)?;
+    Ok(())
+}
+
+// Events, history and notes in memory; overrides are written as user layer files
+#[derive(Default)]
+pub struct MemoryBackend {
+    history: Mutex<VecDeque<LookupRecord>>,
+    notes: Mutex<BTreeMap<(String, String), EventNote>>,
+}
+
+impl EventBackend for MemoryBackend {
+    fn kind(&self) -> &'static str {
+        "memory"
+    }
+
+    fn load(&self, strict: bool) -> Result<LoadedEvents, AppError> {
+        let (events, source) = if strict {
+            events::reload_events_json()?
+        } else {
+            events::load_events_with_source()?
+        };
+        // Build the lookup index once; every search reuses it
+        let index = Box::new(SearchIndex::build(&events));
+        Ok(LoadedEvents { events, index, source })
+    }
+
+    fn record_lookup(&self, record: LookupRecord) -> Result<(), AppError> {
+        let mut history = self.history.lock().unwrap();
+        history.push_front(record);
+        history.truncate(MEMORY_HISTORY_LIMIT);
+        Ok(())
+    }
+
+    fn history(&self, limit: usize) -> Result<Vec<LookupRecord>, AppError> {
+        Ok(self.history.lock().unwrap().iter().take(limit).cloned().collect())
+    }
+
+    fn set_note(&self, name: &str, character_name: &str, note: &str) -> Result<(), AppError> {
+        let key = (name.to_string(), character_name.to_string());
+        let mut notes = self.notes.lock().unwrap();
+        if note.trim().is_empty() {
+            notes.remove(&key);
+        } else {
+            let note = EventNote {
+                name: key.0.clone(),
+                character_name: key.1.clone(),
+                note: note.trim().to_string(),
+                updated_at: now(),
+            };
+            notes.insert(key, note);
+        }
+        Ok(())
+    }
+
+    fn notes(&self) -> Result<Vec<EventNote>, AppError> {
+        Ok(self.notes.lock().unwrap().values().cloned().collect())
+    }
+
+    fn save_override(&self, name: &str, layer: &str) -> Result<(), AppError> {
+        validate_override(name, layer)?;
+        let dir = event_layers::layers_dir().ok_or_else(|| AppError::internal("No config directory for event layers"))?;
+        let path = dir.join(format!("{}.json", name));
+        let save_failed = |e: std::io::Error| AppError::SaveFailed { path: path.display().to_string(), reason: e.to_string() };
+
+        std::fs::create_dir_all(&dir).map_err(save_failed)?;
+        std::fs::write(&path, layer).map_err(save_failed)?;
+        info!("Saved event override {}", path.display());
+        Ok(())
+    }
+}
+
+// The SQLite store when the feature is built in and its file can be opened,
+// the in-memory one otherwise
+pub fn open_default() -> Box<dyn EventBackend> {
+    #[cfg(feature = "sqlite")]
+    {
+        match crate::event_sqlite::SqliteBackend::open_default() {
+            Ok(backend) => return Box::new(backend),
+            Err(e) => info!("Warning: Could not open the SQLite event store, keeping events in memory: {}", e),
+        }
+    }
+    Box::new(MemoryBackend::default())
+}
@@ -0,0 +1,671 @@
+// Importers that turn community event dumps into the events.json model: the
+// JavaScript module of umamusume-event-search, CSV/TSV spreadsheets with one
+// row per choice, YAML, and JSON with differently named fields. Every format
+// is read into plain records first, so the field mapping (and the report of
+// fields it could not map) is shared.
+
+use crate::error::AppError;
+use crate::events::{Choice, JsonEvent};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+// Accepted field names, after normalising to snake_case
+const NAME_KEYS: &[&str] = &["name", "event_name", "event", "title"];
+const CHARACTER_KEYS: &[&str] = &["character_name", "character", "chara", "card"];
+const RELATION_KEYS: &[&str] = &["relation_type", "relation", "type", "category"];
+const CHOICES_KEYS: &[&str] = &["choices", "options"];
+const TEXT_KEYS: &[&str] = &["text", "choice_text", "choice", "option", "label"];
+const NUMBER_KEYS: &[&str] = &["number", "choice_number", "num", "no"];
+const OUTCOME_KEYS: &[&str] = &["outcome", "outcomes", "effect", "effects", "result", "reward", "rewards"];
+
+// Translations: a `names`/`texts` object, or `name_<code>`/`text_<code>` fields
+const NAMES_KEY: &str = "names";
+const TEXTS_KEY: &str = "texts";
+const NAME_PREFIX: &str = "name_";
+const TEXT_PREFIX: &str = "text_";
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportFormat {
+    JsModule,
+    Csv,
+    Tsv,
+    Yaml,
+    Json,
+}
+
+impl ImportFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "js" | "mjs" | "cjs" | "js_module" => Some(ImportFormat::JsModule),
+            "csv" => Some(ImportFormat::Csv),
+            "tsv" | "tab" => Some(ImportFormat::Tsv),
+            "yaml" | "yml" => Some(ImportFormat::Yaml),
+            "json" => Some(ImportFormat::Json),
+            _ => None,
+        }
+    }
+
+    // Guessed from the file extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        path.extension().and_then(|extension| Self::from_name(&extension.to_string_lossy()))
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ImportReport {
+    pub format: ImportFormat,
+    pub imported: usize,
+    pub skipped: Vec<String>, // Entries that could not become an event, and why
+    pub unmapped_fields: BTreeMap<String, usize>, // Field path -> entries it appeared in
+}
+
+pub fn import_events(content: &str, format: ImportFormat) -> Result<(Vec<JsonEvent>, ImportReport), AppError> {
+    let records = match format {
+        ImportFormat::JsModule => records_from_value(parse_json(&js_literal_to_json(content)?)?)?,
+        ImportFormat::Json => records_from_value(parse_json(content)?)?,
+        ImportFormat::Yaml => {
+            let value = serde_yaml_ng::from_str::<Value>(content).map_err(|e| failed(format!("invalid YAML: {}", e)))?;
+            records_from_value(value)?
+        }
+        ImportFormat::Csv => records_from_rows(content, b',')?,
+        ImportFormat::Tsv => records_from_rows(content, b'\t')?,
+    };
+
+    let mut mapper = Mapper::default();
+    let events: Vec<JsonEvent> = records
+        .iter()
+        .enumerate()
+        .filter_map(|(entry, record)| mapper.map_event(entry, record))
+        .collect();
+
+    let report = ImportReport {
+        format,
+        imported: events.len(),
+        skipped: mapper.skipped,
+        unmapped_fields: mapper.unmapped,
+    };
+    Ok((events, report))
+}
+
+fn failed(reason: impl Into<String>) -> AppError {
+    AppError::EventsLoadFailed { reason: reason.into() }
+}
+
+fn parse_json(content: &str) -> Result<Value, AppError> {
+    serde_json::from_str(content).map_err(|e| failed(format!("invalid JSON: {}", e)))
+}
+
+// The event list: the document itself, its `events` field or its only list
+fn records_from_value(value: Value) -> Result<Vec<Value>, AppError> {
+    match value {
+        Value::Array(records) => Ok(records),
+        Value::Object(mut object) => {
+            if let Some(Value::Array(records)) = object.remove("events") {
+                return Ok(records);
+            }
+            let mut lists = object.into_iter().filter_map(|(_, value)| match value {
+                Value::Array(records) => Some(records),
+                _ => None,
+            });
+            match (lists.next(), lists.next()) {
+                (Some(records), None) => Ok(records),
+                _ => Err(failed("expected a list of events, or an object with an `events` list")),
+            }
+        }
+        _ => Err(failed("expected a list of events")),
+    }
+}
+
+// snake_case of a field name: "characterName", "Character Name" and
+// "character-name" all become "character_name"
+fn normalize_key(key: &str) -> String {
+    let mut normalized = String::with_capacity(key.len() + 4);
+    let mut previous_lower = false;
+    for c in key.trim().chars() {
+        if c.is_uppercase() && previous_lower {
+            normalized.push('_');
+        }
+        previous_lower = c.is_lowercase() || c.is_ascii_digit();
+        match c {
+            ' ' | '-' | '.' => normalized.push('_'),
+            c => normalized.extend(c.to_lowercase()),
+        }
+    }
+    normalized
+}
+
+// Strings, numbers and lists of them (joined as outcome segments)
+fn as_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(text) => Some(text.trim().to_string()),
+        Value::Number(number) => Some(number.to_string()),
+        Value::Bool(flag) => Some(flag.to_string()),
+        Value::Array(items) => {
+            let parts: Option<Vec<String>> = items.iter().map(as_text).collect();
+            parts.map(|parts| parts.join(";"))
+        }
+        _ => None,
+    }
+}
+
+// Language code of a `name_<code>` style field
+fn language_suffix<'a>(key: &'a str, prefix: &str) -> Option<&'a str> {
+    let code = key.strip_prefix(prefix)?;
+    let valid = !code.is_empty() && code.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+    valid.then_some(code)
+}
+
+// Fields of one record, looked up by their normalised names
+struct Fields<'a> {
+    fields: Vec<(String, &'a str, &'a Value)>, // Normalised key, original key, value
+    used: Vec<bool>,
+}
+
+impl<'a> Fields<'a> {
+    fn new(object: &'a Map<String, Value>) -> Self {
+        let fields: Vec<_> = object
+            .iter()
+            .filter(|(_, value)| !value.is_null())
+            .map(|(key, value)| (normalize_key(key), key.as_str(), value))
+            .collect();
+        let used = vec![false; fields.len()];
+        Fields { fields, used }
+    }
+
+    fn take(&mut self, aliases: &[&str]) -> Option<&'a Value> {
+        for alias in aliases {
+            if let Some(i) = self.fields.iter().position(|(key, _, _)| key == alias) {
+                self.used[i] = true;
+                return Some(self.fields[i].2);
+            }
+        }
+        None
+    }
+
+    fn take_text(&mut self, aliases: &[&str]) -> Option<String> {
+        self.take(aliases).and_then(as_text).filter(|text| !text.is_empty())
+    }
+
+    // A translation object plus `<prefix><code>` fields
+    fn take_translations(&mut self, object_key: &str, prefix: &str) -> BTreeMap<String, String> {
+        let mut translations = BTreeMap::new();
+        if let Some(Value::Object(object)) = self.take(&[object_key]) {
+            for (code, value) in object {
+                if let Some(text) = as_text(value).filter(|text| !text.is_empty()) {
+                    translations.insert(code.clone(), text);
+                }
+            }
+        }
+        for i in 0..self.fields.len() {
+            let (key, _, value) = &self.fields[i];
+            if let Some(code) = language_suffix(key, prefix) {
+                if let Some(text) = as_text(value).filter(|text| !text.is_empty()) {
+                    translations.insert(code.to_string(), text);
+                    self.used[i] = true;
+                }
+            }
+        }
+        translations
+    }
+
+    fn unused(&self) -> impl Iterator<Item = &'a str> + '_ {
+        self.fields.iter().zip(&self.used).filter(|(_, used)| !**used).map(|((_, key, _), _)| *key)
+    }
+}
+
+#[derive(Default)]
+struct Mapper {
+    skipped: Vec<String>,
+    unmapped: BTreeMap<String, usize>,
+}
+
+impl Mapper {
+    fn map_event(&mut self, entry: usize, record: &Value) -> Option<JsonEvent> {
+        let Value::Object(object) = record else {
+            self.skipped.push(format!("entry {}: not an object", entry));
+            return None;
+        };
+        let mut fields = Fields::new(object);
+
+        let Some(name) = fields.take_text(NAME_KEYS) else {
+            self.skipped.push(format!("entry {}: no event name", entry));
+            return None;
+        };
+        let character_name = fields.take_text(CHARACTER_KEYS).unwrap_or_default();
+        let relation_type = fields.take_text(RELATION_KEYS).unwrap_or_default();
+        let names = fields.take_translations(NAMES_KEY, NAME_PREFIX);
+
+        let mut choices = Vec::new();
+        match fields.take(CHOICES_KEYS) {
+            Some(Value::Array(items)) => {
+                for (i, item) in items.iter().enumerate() {
+                    match self.map_choice(i, item) {
+                        Some(choice) => choices.push(choice),
+                        None => self.skipped.push(format!("entry {} ({}): choice {} has no text or outcome", entry, name, i)),
+                    }
+                }
+            }
+            Some(_) => self.skipped.push(format!("entry {} ({}): choices is not a list", entry, name)),
+            None => {}
+        }
+
+        for key in fields.unused() {
+            *self.unmapped.entry(key.to_string()).or_default() += 1;
+        }
+
+        Some(JsonEvent { name, names, character_name, relation_type, choices, provenance: Default::default() })
+    }
+
+    fn map_choice(&mut self, position: usize, item: &Value) -> Option<Choice> {
+        // A bare string is the choice text
+        let Value::Object(object) = item else {
+            return as_text(item).filter(|text| !text.is_empty()).map(|text| Choice {
+                text,
+                texts: BTreeMap::new(),
+                number: (position + 1).to_string(),
+                outcome: String::new(),
+            });
+        };
+
+        let mut fields = Fields::new(object);
+        let text = fields.take_text(TEXT_KEYS).unwrap_or_default();
+        let number = fields.take_text(NUMBER_KEYS).unwrap_or_else(|| (position + 1).to_string());
+        let outcome = fields.take_text(OUTCOME_KEYS).unwrap_or_default();
+        let texts = fields.take_translations(TEXTS_KEY, TEXT_PREFIX);
+
+        for key in fields.unused() {
+            *self.unmapped.entry(format!("choices[].{}", key)).or_default() += 1;
+        }
+
+        if text.is_empty() && outcome.is_empty() {
+            return None;
+        }
+        Some(Choice { text, texts, number, outcome })
+    }
+}
+
+// Spreadsheet rows, one per choice, grouped into one record per event. A row
+// with an empty event name continues the event above it (merged cells).
+fn records_from_rows(content: &str, delimiter: u8) -> Result<Vec<Value>, AppError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        // Tab separated exports rarely quote, and choice texts contain quotes
+        .quoting(delimiter != b'\t')
+        .from_reader(content.as_bytes());
+    let headers = reader.headers().map_err(|e| failed(format!("could not read the header row: {}", e)))?.clone();
+    let is_choice_column = |header: &str| {
+        let key = normalize_key(header);
+        TEXT_KEYS.contains(&key.as_str())
+            || NUMBER_KEYS.contains(&key.as_str())
+            || OUTCOME_KEYS.contains(&key.as_str())
+            || language_suffix(&key, TEXT_PREFIX).is_some()
+    };
+    let Some(name_column) = headers.iter().position(|header| NAME_KEYS.contains(&normalize_key(header).as_str())) else {
+        return Err(failed(format!("no event name column, expected one of: {}", NAME_KEYS.join(", "))));
+    };
+    let character_column = headers.iter().position(|header| CHARACTER_KEYS.contains(&normalize_key(header).as_str()));
+
+    let mut events: Vec<Map<String, Value>> = Vec::new();
+    let mut by_key: HashMap<(String, String), usize> = HashMap::new();
+    let mut current: Option<usize> = None;
+    for (row, record) in reader.records().enumerate() {
+        let record = record.map_err(|e| failed(format!("row {}: {}", row + 2, e)))?;
+        let cell = |column: usize| record.get(column).unwrap_or("");
+
+        let name = cell(name_column);
+        let target = if name.is_empty() {
+            let Some(current) = current else {
+                continue; // Nothing to continue yet
+            };
+            current
+        } else {
+            let key = (name.to_string(), character_column.map(cell).unwrap_or("").to_string());
+            *by_key.entry(key).or_insert_with(|| {
+                events.push(Map::new());
+                events.len() - 1
+            })
+        };
+        current = Some(target);
+
+        let event = &mut events[target];
+        let mut choice = Map::new();
+        for (header, value) in headers.iter().zip(record.iter()) {
+            if value.is_empty() {
+                continue;
+            }
+            if is_choice_column(header) {
+                choice.insert(header.to_string(), Value::String(value.to_string()));
+            } else {
+                event.entry(header.to_string()).or_insert_with(|| Value::String(value.to_string()));
+            }
+        }
+        if !choice.is_empty() {
+            let choices = event.entry("choices").or_insert_with(|| Value::Array(Vec::new()));
+            if let Value::Array(choices) = choices {
+                choices.push(Value::Object(choice));
+            }
+        }
+    }
+
+    Ok(events.into_iter().map(Value::Object).collect())
+}
+
+// Turns the array or object literal of a JavaScript module into JSON. Keys
+// may be unquoted, strings single, double or backtick quoted, and comments
+// and trailing commas are allowed; anything computed is rejected. Whatever
+// comes before the first literal (`export default`, `const events =`) and
+// after it is ignored.
+fn js_literal_to_json(source: &str) -> Result<String, AppError> {
+    let chars: Vec<char> = source.chars().collect();
+    let line_at = |i: usize| chars[..i.min(chars.len())].iter().filter(|&&c| c == '\n').count() + 1;
+    let mut json = String::with_capacity(source.len());
+    let mut depth = 0usize;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+
+        // Comments
+        if c == '/' && next == Some('/') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+            continue;
+        }
+        if c == '/' && next == Some('*') {
+            i += 2;
+            while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                i += 1;
+            }
+            i += 2;
+            continue;
+        }
+
+        // Skip the statement around the literal
+        if depth == 0 && c != '[' && c != '{' {
+            if matches!(c, '"' | '\'' | '`') {
+                i = read_js_string(&chars, i).map_err(|e| failed(format!("line {}: {}", line_at(i), e)))?.1;
+            } else {
+                i += 1;
+            }
+            continue;
+        }
+
+        match c {
+            '"' | '\'' | '`' => {
+                let (text, end) = read_js_string(&chars, i).map_err(|e| failed(format!("line {}: {}", line_at(i), e)))?;
+                json.push_str(&Value::String(text).to_string());
+                i = end;
+            }
+            '[' | '{' => {
+                depth += 1;
+                json.push(c);
+                i += 1;
+            }
+            ']' | '}' => {
+                // Drop a trailing comma
+                let trimmed = json.trim_end().len();
+                json.truncate(trimmed);
+                if json.ends_with(',') {
+                    json.pop();
+                }
+                json.push(c);
+                depth -= 1;
+                i += 1;
+                if depth == 0 {
+                    return Ok(json);
+                }
+            }
+            c if c.is_alphabetic() || c == '_' || c == '$' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '$') {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                let is_key = chars[i..].iter().find(|c| !c.is_whitespace()) == Some(&':');
+                match word.as_str() {
+                    _ if is_key => json.push_str(&Value::String(word).to_string()),
+                    "true" | "false" | "null" => json.push_str(&word),
+                    "undefined" => json.push_str("null"),
+                    _ => return Err(failed(format!("line {}: unsupported value `{}`, only literals can be imported", line_at(start), word))),
+                }
+            }
+            c if c.is_ascii_digit() || c == '-' || c == '+' || c == '.' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_ascii_alphanumeric() || matches!(chars[i], '.' | '-' | '+' | '_')) {
+                    i += 1;
+                }
+                let number: String = chars[start..i].iter().filter(|&&c| c != '_').collect();
+                let number = number.strip_prefix('+').unwrap_or(&number);
+                if number.parse::<f64>().is_err() {
+                    return Err(failed(format!("line {}: unsupported number `{}`", line_at(start), number)));
+                }
+                json.push_str(number);
+            }
+            ',' | ':' => {
+                json.push(c);
+                i += 1;
+            }
+            c if c.is_whitespace() => {
+                json.push(c);
+                i += 1;
+            }
+            _ => return Err(failed(format!("line {}: unexpected `{}`, only literals can be imported", line_at(i), c))),
+        }
+    }
+
+    if depth == 0 {
+        Err(failed("no array or object literal found"))
+    } else {
+        Err(failed("the literal is not closed"))
+    }
+}
+
+// Reads the string literal starting at `start` (its opening quote); returns
+// the text and the index after the closing quote
+fn read_js_string(chars: &[char], start: usize) -> Result<(String, usize), String> {
+    let quote = chars[start];
+    let mut text = String::new();
+    let mut i = start + 1;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c == quote {
+            return Ok((text, i + 1));
+        }
+        if quote == '`' && c == '$' && chars.get(i + 1) == Some(&'{') {
+            return Err("template literals with ${...} cannot be imported".to_string());
+        }
+        if c == '\n' && quote != '`' {
+            return Err("unterminated string".to_string());
+        }
+        if c != '\\' {
+            text.push(c);
+            i += 1;
+            continue;
+        }
+
+        let escaped = *chars.get(i + 1).ok_or("unterminated string")?;
+        i += 2;
+        match escaped {
+            'n' => text.push('\n'),
+            't' => text.push('\t'),
+            'r' => text.push('\r'),
+            'b' => text.push('\u{8}'),
+            'f' => text.push('\u{c}'),
+            'v' => text.push('\u{b}'),
+            '0' => text.push('\0'),
+            '\n' => {} // Line continuation
+            'x' => {
+                let code = hex_value(chars, i, 2)?;
+                text.push(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+                i += 2;
+            }
+            'u' => {
+                let (code, end) = read_unicode_escape(chars, i)?;
+                i = end;
+                // Astral characters are written as a surrogate pair of escapes
+                let code = if (0xD800..0xDC00).contains(&code) && chars.get(i) == Some(&'\\') && chars.get(i + 1) == Some(&'u') {
+                    let (low, end) = read_unicode_escape(chars, i + 2)?;
+                    i = end;
+                    0x10000 + ((code - 0xD800) << 10) + (low.wrapping_sub(0xDC00) & 0x3FF)
+                } else {
+                    code
+                };
+                text.push(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+            }
+            other => text.push(other),
+        }
+    }
+
+    Err("unterminated string".to_string())
+}
+
+// `XXXX` or `{X...}` after a `\u`
+fn read_unicode_escape(chars: &[char], start: usize) -> Result<(u32, usize), String> {
+    if chars.get(start) == Some(&'{') {
+        let length = chars[start + 1..].iter().position(|&c| c == '}').ok_or("unterminated \\u{...} escape")?;
+        Ok((hex_value(chars, start + 1, length)?, start + length + 2))
+    } else {
+        Ok((hex_value(chars, start, 4)?, start + 4))
+    }
+}
+
+fn hex_value(chars: &[char], start: usize, length: usize) -> Result<u32, String> {
+    let digits: String = chars.get(start..start + length).ok_or("truncated escape")?.iter().collect();
+    u32::from_str_radix(&digits, 16).map_err(|_| format!("invalid escape digits `{}`", digits))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn import(content: &str, format: ImportFormat) -> (Vec<JsonEvent>, ImportReport) {
+        import_events(content, format).unwrap()
+    }
+
+    fn import_error(content: &str, format: ImportFormat) -> String {
+        import_events(content, format).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn js_module_literals() {
+        let source = r#"
+// Scraped from umamusume-event-search
+export default [
+  {
+    name: 'Hamburger Helper!', /* title */
+    characterName: "Agnes Tachyon",
+    choices: [
+      { text: `Can't let them go to waste, right?`, effect: ["10 Energy", "5 Skill Pts"], },
+      "Let's share",
+    ],
+  },
+];
+"#;
+        let (events, report) = import(source, ImportFormat::JsModule);
+        assert_eq!(report.imported, 1);
+        assert!(report.skipped.is_empty() && report.unmapped_fields.is_empty());
+
+        let event = &events[0];
+        assert_eq!(event.name, "Hamburger Helper!");
+        assert_eq!(event.character_name, "Agnes Tachyon");
+        assert_eq!(event.choices[0].text, "Can't let them go to waste, right?");
+        assert_eq!(event.choices[0].outcome, "10 Energy;5 Skill Pts");
+        assert_eq!((event.choices[1].text.as_str(), event.choices[1].number.as_str()), ("Let's share", "2"));
+    }
+
+    #[test]
+    fn js_string_escapes() {
+        let source = r#"[{ name: "Café \u{1F955} 🍔 \x41\n", id: 1_000, ok: true, gone: undefined }]"#;
+        let json: Value = serde_json::from_str(&js_literal_to_json(source).unwrap()).unwrap();
+        assert_eq!(json[0]["name"], "Café 🥕 🍔 A\n");
+        assert_eq!(json[0]["id"], 1000);
+        assert_eq!(json[0]["ok"], true);
+        assert!(json[0]["gone"].is_null());
+    }
+
+    #[test]
+    fn js_module_errors() {
+        let format = ImportFormat::JsModule;
+        assert!(import_error("export default events;", format).contains("no array or object literal found"));
+        assert!(import_error("[{ name: 'A' }", format).contains("the literal is not closed"));
+        assert!(import_error("[\n{ name: title }]", format).contains("line 2: unsupported value `title`"));
+        assert!(import_error("[{ name: `A ${b}` }]", format).contains("template literals"));
+        assert!(import_error("[{ name: 'A\n' }]", format).contains("line 1: unterminated string"));
+        assert!(import_error("[{ name: '\\u12' }]", format).contains("invalid escape digits"));
+        assert!(import_error("[{ n: 0x1z }]", format).contains("unsupported number `0x1z`"));
+        assert!(import_error("[{ n: #a }]", format).contains("unexpected `#`"));
+    }
+
+    #[test]
+    fn csv_rows_are_grouped_per_event() {
+        let content = "\
+Event Name,Character,Choice,Outcome,Notes
+At Tachyon's Pace,Agnes Tachyon,Wait a sec,5 Speed,first
+,,\"Go, go!\",5 Power,
+Hamburger Helper!,,Eat,10 Energy,
+At Tachyon's Pace,Agnes Tachyon,Third,5 Guts,
+";
+        let (events, report) = import(content, ImportFormat::Csv);
+        assert_eq!(report.imported, 2);
+        assert_eq!(report.unmapped_fields.get("Notes"), Some(&1));
+
+        let texts: Vec<&str> = events[0].choices.iter().map(|choice| choice.text.as_str()).collect();
+        assert_eq!(texts, ["Wait a sec", "Go, go!", "Third"]);
+        assert_eq!(events[0].choices[1].number, "2");
+        assert_eq!(events[1].choices[0].outcome, "10 Energy");
+    }
+
+    #[test]
+    fn tsv_rows_keep_quotes_and_translations() {
+        let content = "name\tname_ja\ttext\ttext_ja\toutcome\nA \"Gift\"\t贈り物\tThank it\tありがとう\t5 Wit\n";
+        let (events, _) = import(content, ImportFormat::Tsv);
+        assert_eq!(events[0].name, "A \"Gift\"");
+        assert_eq!(events[0].names.get("ja").map(String::as_str), Some("贈り物"));
+        assert_eq!(events[0].choices[0].texts.get("ja").map(String::as_str), Some("ありがとう"));
+    }
+
+    #[test]
+    fn row_errors() {
+        assert!(import_error("Character,Choice\nA,B\n", ImportFormat::Csv).contains("no event name column"));
+    }
+
+    #[test]
+    fn yaml_and_json_records() {
+        let yaml = "events:\n  - title: Summer Camp\n    options:\n      - label: Rest\n        rewards: [10 Energy]\n";
+        let (events, _) = import(yaml, ImportFormat::Yaml);
+        assert_eq!(events[0].name, "Summer Camp");
+        assert_eq!(events[0].choices[0].outcome, "10 Energy");
+        assert!(import_error("events: [", ImportFormat::Yaml).contains("invalid YAML"));
+
+        let json = r#"{"data": [{"Event": "A", "names": {"ja": "エー"}, "choices": [{"choice": "B", "choice_number": 2}, {}]}, 5, {"chara": "C"}]}"#;
+        let (events, report) = import(json, ImportFormat::Json);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].names.get("ja").map(String::as_str), Some("エー"));
+        assert_eq!(events[0].choices[0].number, "2");
+        assert_eq!(
+            report.skipped,
+            ["entry 0 (A): choice 1 has no text or outcome", "entry 1: not an object", "entry 2: no event name"]
+        );
+
+        assert!(import_error("{", ImportFormat::Json).contains("invalid JSON"));
+        assert!(import_error(r#"{"a": [], "b": []}"#, ImportFormat::Json).contains("expected a list of events"));
+        assert!(import_error("5", ImportFormat::Json).contains("expected a list of events"));
+    }
+
+    #[test]
+    fn field_names_are_normalised() {
+        assert_eq!(normalize_key("characterName"), "character_name");
+        assert_eq!(normalize_key("Character Name"), "character_name");
+        assert_eq!(normalize_key("character-name"), "character_name");
+        assert_eq!(ImportFormat::from_path(Path::new("events.mjs")), Some(ImportFormat::JsModule));
+        assert_eq!(ImportFormat::from_name("xml"), None);
+    }
+}
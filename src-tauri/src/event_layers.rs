@@ -0,0 +1,296 @@
+// User event files layered over the bundled database. Each file in the
+// `events` folder of the config directory can add events, patch single
+// fields of existing ones (found by name + character) or hide them, so a
+// wrong outcome can be fixed without replacing the whole events.json.
+//
+//   {
+//     "add":   [ { full event, as in events.json } ],
+//     "patch": [ { "name": "...", "character_name": "...",
+//                  "choices": [ { "number": "1", "outcome": "..." } ] } ],
+//     "hide":  [ { "name": "...", "character_name": "..." } ]
+//   }
+
+use crate::error::AppError;
+use crate::events::{Choice, JsonEvent};
+use crate::paths::get_config_dir;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct EventKey {
+    pub name: String,
+    #[serde(default)]
+    pub character_name: String,
+}
+
+impl EventKey {
+    fn matches(&self, event: &JsonEvent) -> bool {
+        event.name.trim() == self.name.trim() && comparable(&event.character_name) == comparable(&self.character_name)
+    }
+}
+
+// Many character names in events.json end in a stray comma; keys should not need it
+fn comparable(character_name: &str) -> &str {
+    character_name.trim().trim_matches(',').trim()
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ChoicePatch {
+    pub number: String, // Choice to change; added when the event has no such choice
+    pub text: Option<String>,
+    #[serde(default)]
+    pub texts: BTreeMap<String, String>, // Merged into the existing translations
+    pub outcome: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct EventPatch {
+    pub name: String,
+    #[serde(default)]
+    pub character_name: String,
+    #[serde(default)]
+    pub names: BTreeMap<String, String>, // Merged into the existing translations
+    pub relation_type: Option<String>,
+    #[serde(default)]
+    pub choices: Vec<ChoicePatch>,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct EventLayer {
+    #[serde(skip)]
+    pub name: String, // File name, recorded in the provenance of what it touches
+    pub add: Vec<JsonEvent>,
+    pub patch: Vec<EventPatch>,
+    pub hide: Vec<EventKey>,
+}
+
+// Created on demand by the user; None without a config directory
+pub fn layers_dir() -> Option<PathBuf> {
+    get_config_dir().map(|dir| dir.join("events"))
+}
+
+// The `.json` files of the layer folder, in the order they apply (by file name)
+pub fn layer_paths() -> Vec<PathBuf> {
+    let Some(entries) = layers_dir().and_then(|dir| std::fs::read_dir(dir).ok()) else {
+        return Vec::new();
+    };
+
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file() && path.extension().is_some_and(|extension| extension == "json"))
+        .collect();
+    paths.sort();
+    paths
+}
+
+pub fn read_layer(path: &Path) -> Result<EventLayer, AppError> {
+    let name = path.file_name().map_or_else(|| path.display().to_string(), |name| name.to_string_lossy().to_string());
+    let content = std::fs::read_to_string(path).map_err(|e| AppError::EventsLoadFailed {
+        reason: format!("event layer {}: could not read: {}", name, e),
+    })?;
+    parse_layer(name, &content)
+}
+
+// A layer from its JSON, e.g. one stored in the SQLite database
+pub fn parse_layer(name: String, content: &str) -> Result<EventLayer, AppError> {
+    let mut layer: EventLayer = serde_json::from_str(content).map_err(|e| AppError::EventsLoadFailed {
+        reason: format!("event layer {}: {}", name, e),
+    })?;
+    layer.name = name;
+    Ok(layer)
+}
+
+// Applies `layer` to `events`: hides first, then patches, then additions
+// (which replace an event with the same name + character). Returns what
+// could not be applied.
+pub fn apply_layer(events: &mut Vec<JsonEvent>, layer: &EventLayer) -> Vec<String> {
+    let mut warnings = Vec::new();
+
+    for key in &layer.hide {
+        let before = events.len();
+        events.retain(|event| !key.matches(event));
+        if events.len() == before {
+            warnings.push(format!("hide: no event {:?} for {:?}", key.name, key.character_name));
+        }
+    }
+
+    for patch in &layer.patch {
+        let key = EventKey { name: patch.name.clone(), character_name: patch.character_name.clone() };
+        let mut found = false;
+        for event in events.iter_mut().filter(|event| key.matches(event)) {
+            found = true;
+            apply_patch(event, patch, &mut warnings);
+            event.provenance.patched_by.push(layer.name.clone());
+        }
+        if !found {
+            warnings.push(format!("patch: no event {:?} for {:?}", patch.name, patch.character_name));
+        }
+    }
+
+    for event in &layer.add {
+        if event.name.trim().is_empty() {
+            warnings.push("add: event without a name".to_string());
+            continue;
+        }
+
+        let mut event = event.clone();
+        event.provenance.source = layer.name.clone();
+        let key = EventKey { name: event.name.clone(), character_name: event.character_name.clone() };
+        match events.iter_mut().find(|existing| key.matches(existing)) {
+            Some(existing) => *existing = event,
+            None => events.push(event),
+        }
+    }
+
+    warnings
+}
+
+fn apply_patch(event: &mut JsonEvent, patch: &EventPatch, warnings: &mut Vec<String>) {
+    event.names.extend(patch.names.clone());
+    if let Some(relation_type) = &patch.relation_type {
+        event.relation_type = relation_type.clone();
+    }
+
+    for choice_patch in &patch.choices {
+        let number = choice_patch.number.trim();
+        if let Some(choice) = event.choices.iter_mut().find(|choice| choice.number.trim() == number) {
+            if let Some(text) = &choice_patch.text {
+                choice.text = text.clone();
+            }
+            if let Some(outcome) = &choice_patch.outcome {
+                choice.outcome = outcome.clone();
+            }
+            choice.texts.extend(choice_patch.texts.clone());
+            continue;
+        }
+
+        // A new choice needs both its text and its outcome
+        match (&choice_patch.text, &choice_patch.outcome) {
+            (Some(text), Some(outcome)) => event.choices.push(Choice {
+                text: text.clone(),
+                texts: choice_patch.texts.clone(),
+                number: number.to_string(),
+                outcome: outcome.clone(),
+            }),
+            _ => warnings.push(format!(
+                "patch: {:?} has no choice {}, and adding one needs text and outcome",
+                event.name, number
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(name: &str, character_name: &str, outcome: &str) -> JsonEvent {
+        JsonEvent {
+            name: name.to_string(),
+            names: Default::default(),
+            character_name: character_name.to_string(),
+            relation_type: "Umamusume".to_string(),
+            choices: vec![Choice {
+                text: "Rest, please.".to_string(),
+                texts: Default::default(),
+                number: "1".to_string(),
+                outcome: outcome.to_string(),
+            }],
+            provenance: Default::default(),
+        }
+    }
+
+    fn layer(name: &str, json: &str) -> EventLayer {
+        parse_layer(name.to_string(), json).unwrap()
+    }
+
+    #[test]
+    fn hides_before_patching_and_adding() {
+        let mut events = vec![event("At Summer Camp", "Vodka,", "10 Energy"), event("New Year", "Vodka", "5 Energy")];
+        let layer = layer(
+            "fixes.json",
+            r#"{
+                "hide": [{ "name": "At Summer Camp", "character_name": "Vodka" }],
+                "patch": [{ "name": "At Summer Camp", "character_name": "Vodka",
+                            "choices": [{ "number": "1", "outcome": "20 Energy" }] }],
+                "add": [{ "name": "At Summer Camp", "character_name": "Vodka", "relation_type": "Umamusume",
+                          "choices": [{ "number": "1", "text": "Swim", "outcome": "30 Energy" }] }]
+            }"#,
+        );
+
+        // The patch finds nothing once the event is hidden; the addition brings it back
+        let warnings = apply_layer(&mut events, &layer);
+        assert_eq!(warnings, [r#"patch: no event "At Summer Camp" for "Vodka""#]);
+        assert_eq!(events.len(), 2);
+        let added = events.iter().find(|event| event.name == "At Summer Camp").unwrap();
+        assert_eq!(added.choices[0].outcome, "30 Energy");
+        assert_eq!(added.provenance.source, "fixes.json");
+        assert!(added.provenance.patched_by.is_empty());
+    }
+
+    #[test]
+    fn additions_replace_the_same_event() {
+        let mut events = vec![event("New Year", " Vodka, ", "5 Energy"), event("New Year", "Agnes Tachyon", "5 Energy")];
+        let layer = layer(
+            "more.json",
+            r#"{ "add": [
+                { "name": "New Year", "character_name": "Vodka", "relation_type": "Umamusume",
+                  "choices": [{ "number": "1", "text": "Rest", "outcome": "15 Energy" }] },
+                { "name": " ", "character_name": "", "relation_type": "Umamusume", "choices": [] }
+            ] }"#,
+        );
+
+        let warnings = apply_layer(&mut events, &layer);
+        assert_eq!(warnings, ["add: event without a name"]);
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].character_name, "Vodka");
+        assert_eq!(events[0].choices[0].outcome, "15 Energy");
+        assert_eq!(events[1].choices[0].outcome, "5 Energy");
+    }
+
+    #[test]
+    fn warns_about_what_it_cannot_apply() {
+        let mut events = vec![event("New Year", "Vodka", "5 Energy")];
+        let layer = layer(
+            "typos.json",
+            r#"{
+                "hide": [{ "name": "New Yaer", "character_name": "Vodka" }],
+                "patch": [
+                    { "name": "New Yaer", "character_name": "Vodka" },
+                    { "name": "New Year", "character_name": "Vodka", "choices": [{ "number": "2", "outcome": "Rest" }] }
+                ]
+            }"#,
+        );
+
+        let warnings = apply_layer(&mut events, &layer);
+        assert_eq!(warnings.len(), 3);
+        assert!(warnings[0].starts_with("hide: no event \"New Yaer\""));
+        assert!(warnings[1].starts_with("patch: no event \"New Yaer\""));
+        assert!(warnings[2].starts_with("patch: \"New Year\" has no choice 2"));
+        assert_eq!(events.len(), 1);
+        assert!(parse_layer("bad.json".to_string(), r#"{ "remove": [] }"#).is_err());
+    }
+
+    #[test]
+    fn records_provenance_across_layers() {
+        let mut events = vec![event("New Year", "Vodka", "5 Energy")];
+        let patch = |outcome: &str| {
+            format!(
+                r#"{{ "patch": [{{ "name": "New Year", "character_name": "Vodka",
+                                 "choices": [{{ "number": "1", "outcome": "{}" }}] }}] }}"#,
+                outcome
+            )
+        };
+
+        assert!(apply_layer(&mut events, &layer("01-outcomes.json", &patch("10 Energy"))).is_empty());
+        assert!(apply_layer(&mut events, &layer("02-fixes.json", &patch("20 Energy"))).is_empty());
+        assert_eq!(events[0].choices[0].outcome, "20 Energy");
+        assert_eq!(events[0].provenance.source, "bundled");
+        assert_eq!(events[0].provenance.patched_by, ["01-outcomes.json", "02-fixes.json"]);
+    }
+}
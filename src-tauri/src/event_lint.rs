@@ -0,0 +1,254 @@
+// Validation pass over events.json. Every entry is checked on its own, so a
+// malformed event is reported and skipped instead of failing the whole load,
+// and data problems serde accepts (stray commas, duplicates, empty outcomes)
+// are reported for dataset maintainers.
+
+use crate::error::AppError;
+use crate::events::JsonEvent;
+use crate::outcome;
+use crate::run_context;
+use serde::Serialize;
+use serde_json::value::RawValue;
+use std::collections::HashMap;
+
+const RELATION_TYPES: &[&str] = &["Umamusume", "Support Card", "Scenario"];
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Info,    // Worth a look, but the data is usable as is
+    Warning, // Loaded, but probably wrong
+    Error,   // The entry was skipped
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct EventIssue {
+    pub severity: Severity,
+    pub line: usize, // 1-based line of the entry, or of the parse error for skipped ones
+    pub entry: usize, // 0-based position in the array
+    pub event: Option<String>, // Event name, when it could be read
+    pub field: String, // e.g. "choices[1].number"
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct EventsReport {
+    pub source: String,
+    pub loaded: usize,
+    pub skipped: usize,
+    pub issues: Vec<EventIssue>,
+}
+
+impl EventsReport {
+    pub fn count(&self, severity: Severity) -> usize {
+        self.issues.iter().filter(|issue| issue.severity == severity).count()
+    }
+}
+
+// Parses and checks `content`, returning the usable events and every issue
+// found. Only a file that is not a JSON array at all is an error.
+pub fn validate_events(content: &str) -> Result<(Vec<JsonEvent>, Vec<EventIssue>), AppError> {
+    let entries: Vec<&RawValue> = serde_json::from_str(content).map_err(|e| AppError::EventsLoadFailed {
+        reason: format!("not a JSON array of events: {}", e),
+    })?;
+
+    let mut events = Vec::with_capacity(entries.len());
+    let mut issues = Vec::new();
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    let mut names: HashMap<String, usize> = HashMap::new();
+    let mut lines = LineCounter::new(content);
+
+    for (entry, raw) in entries.into_iter().enumerate() {
+        let line = lines.line_of(raw.get());
+        let mut report = |severity, event: Option<&str>, field: &str, message: String| {
+            issues.push(EventIssue {
+                severity,
+                line,
+                entry,
+                event: event.map(str::to_string),
+                field: field.to_string(),
+                message,
+            });
+        };
+
+        let event: JsonEvent = match serde_json::from_str(raw.get()) {
+            Ok(event) => event,
+            Err(e) => {
+                let name = serde_json::from_str::<serde_json::Value>(raw.get())
+                    .ok()
+                    .and_then(|value| value.get("name").and_then(|name| name.as_str()).map(str::to_string));
+                // serde reports the position within the entry; point at the file line instead
+                let message = e.to_string();
+                let message = message.rsplit_once(" at line ").map_or(message.as_str(), |(message, _)| message);
+                issues.push(EventIssue {
+                    severity: Severity::Error,
+                    line: line + e.line().saturating_sub(1),
+                    entry,
+                    event: name,
+                    field: String::new(),
+                    message: format!("Not a valid event: {}", message),
+                });
+                continue;
+            }
+        };
+        let name = Some(event.name.as_str());
+
+        if event.name.trim().is_empty() {
+            report(Severity::Error, None, "name", "Event name is empty".to_string());
+            continue;
+        }
+        if event.name != event.name.trim() {
+            report(Severity::Warning, name, "name", "Event name has leading or trailing whitespace".to_string());
+        }
+
+        let character_name = event.character_name.trim();
+        if character_name.ends_with(',') || character_name.starts_with(',') || character_name.contains(",,") {
+            report(Severity::Warning, name, "character_name", format!("Stray comma in {:?}", event.character_name));
+        }
+        if run_context::character_names(&event).next().is_none() && event.relation_type != "Scenario" {
+            report(Severity::Warning, name, "character_name", "No character name".to_string());
+        }
+        if !RELATION_TYPES.contains(&event.relation_type.as_str()) {
+            report(
+                Severity::Warning,
+                name,
+                "relation_type",
+                format!("Unknown relation type {:?}, expected one of {}", event.relation_type, RELATION_TYPES.join(", ")),
+            );
+        }
+
+        // Same key as the event id, so entries differing only in whitespace or
+        // stray commas count as duplicates
+        let key = event.id();
+        if let Some(first) = seen.get(&key) {
+            report(Severity::Warning, name, "name", format!("Duplicate of entry {} (same name and character)", first));
+        } else if let Some(first) = names.get(event.name.trim()) {
+            report(Severity::Info, name, "name", format!("Name shared with entry {} of another character", first));
+        }
+        seen.entry(key).or_insert(entry);
+        names.entry(event.name.trim().to_string()).or_insert(entry);
+
+        if event.choices.is_empty() {
+            report(Severity::Warning, name, "choices", "Event has no choices".to_string());
+        }
+        let mut numbers: HashMap<&str, usize> = HashMap::new();
+        for (i, choice) in event.choices.iter().enumerate() {
+            let field = |field: &str| format!("choices[{}].{}", i, field);
+
+            if choice.number.trim().parse::<u32>().is_err() {
+                report(Severity::Warning, name, &field("number"), format!("Choice number {:?} is not numeric", choice.number));
+            }
+            if let Some(first) = numbers.insert(choice.number.as_str(), i) {
+                report(Severity::Warning, name, &field("number"), format!("Same choice number as choices[{}]", first));
+            }
+            if choice.text.trim().is_empty() {
+                report(Severity::Warning, name, &field("text"), "Choice text is empty".to_string());
+            }
+            if choice.outcome.trim().is_empty() {
+                report(Severity::Warning, name, &field("outcome"), "Outcome is empty".to_string());
+            } else {
+                for segment in outcome::parse_choice_outcome(&choice.number, &choice.outcome).unparsed {
+                    report(Severity::Info, name, &field("outcome"), format!("Effect not understood: {:?}", segment));
+                }
+            }
+        }
+
+        events.push(event);
+    }
+
+    Ok((events, issues))
+}
+
+// Line numbers of slices borrowed from `content`, asked for in file order, so
+// each newline is only counted once
+struct LineCounter<'a> {
+    content: &'a str,
+    offset: usize,
+    line: usize,
+}
+
+impl<'a> LineCounter<'a> {
+    fn new(content: &'a str) -> Self {
+        LineCounter { content, offset: 0, line: 1 }
+    }
+
+    fn line_of(&mut self, slice: &str) -> usize {
+        let offset = (slice.as_ptr() as usize).saturating_sub(self.content.as_ptr() as usize).min(self.content.len());
+        if offset > self.offset {
+            self.line += self.content.as_bytes()[self.offset..offset].iter().filter(|&&byte| byte == b'\n').count();
+            self.offset = offset;
+        }
+        self.line
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EVENTS: &str = r#"[
+  {
+    "name": "Hamburger Helper!",
+    "character_name": "Kitasan Black",
+    "relation_type": "Support Card",
+    "choices": [{ "number": "1", "text": "Eat up", "outcome": "10 Energy" }]
+  },
+  { "name": "Broken", "choices": "not a list" },
+  {
+    "name": "Hamburger Helper! ",
+    "character_name": " Kitasan Black,",
+    "relation_type": "Support Card",
+    "choices": [{ "number": "1", "text": "Eat up", "outcome": "10 Energy" }]
+  },
+  {
+    "name": "Hamburger Helper!",
+    "character_name": "Vodka",
+    "relation_type": "Umamusume",
+    "choices": [{ "number": "1", "text": "Share", "outcome": "5 Energy" }]
+  }
+]"#;
+
+    fn issues_for(issues: &[EventIssue], entry: usize) -> Vec<&EventIssue> {
+        issues.iter().filter(|issue| issue.entry == entry).collect()
+    }
+
+    #[test]
+    fn skips_bad_entries_and_keeps_good_ones() {
+        let (events, issues) = validate_events(EVENTS).unwrap();
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].character_name, "Kitasan Black");
+        assert_eq!(events[2].character_name, "Vodka");
+
+        let broken = issues_for(&issues, 1);
+        assert_eq!(broken.len(), 1);
+        assert_eq!(broken[0].severity, Severity::Error);
+        assert_eq!(broken[0].event.as_deref(), Some("Broken"));
+        assert_eq!(broken[0].line, 8);
+
+        assert!(validate_events("{}").is_err());
+    }
+
+    #[test]
+    fn reports_entry_lines() {
+        let (_, issues) = validate_events(EVENTS).unwrap();
+        assert!(issues_for(&issues, 2).iter().all(|issue| issue.line == 9));
+        assert!(issues_for(&issues, 3).iter().all(|issue| issue.line == 15));
+    }
+
+    #[test]
+    fn detects_duplicates_by_event_id() {
+        let (_, issues) = validate_events(EVENTS).unwrap();
+
+        // Whitespace and a stray comma do not make a different event
+        let duplicate = issues_for(&issues, 2);
+        assert!(duplicate
+            .iter()
+            .any(|issue| issue.severity == Severity::Warning && issue.message.starts_with("Duplicate of entry 0")));
+        assert!(duplicate.iter().any(|issue| issue.field == "character_name" && issue.message.contains("Stray comma")));
+
+        let shared = issues_for(&issues, 3);
+        assert_eq!(shared.len(), 1);
+        assert_eq!(shared[0].severity, Severity::Info);
+        assert!(shared[0].message.contains("Name shared with entry 0"));
+    }
+}
@@ -0,0 +1,394 @@
+// SQLite event store, built with the `sqlite` feature. `uma_events.db` in
+// the config directory holds the imported events.json (re-imported when its
+// contents change), an FTS5 trigram index for lookups, and the user's lookup
+// history, notes and overrides.
+
+use crate::error::AppError;
+use crate::event_backend::{self, EventBackend, EventNote, LoadedEvents, LookupRecord};
+use crate::event_layers;
+use crate::events::{self, JsonEvent};
+use crate::matcher::normalize_text;
+use crate::paths::get_config_dir;
+use crate::search_index::CandidateIndex;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tracing::info;
+
+const DATABASE_FILE: &str = "uma_events.db";
+
+// Lookups kept in the history table
+const HISTORY_LIMIT: i64 = 10_000;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS meta (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS events (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    character_name TEXT NOT NULL,
+    relation_type TEXT NOT NULL,
+    data TEXT NOT NULL -- The whole event as JSON
+);
+CREATE VIRTUAL TABLE IF NOT EXISTS event_search USING fts5(names, choices, tokenize = 'trigram');
+CREATE TABLE IF NOT EXISTS history (
+    id INTEGER PRIMARY KEY,
+    at INTEGER NOT NULL,
+    origin TEXT NOT NULL,
+    text TEXT NOT NULL,
+    event_name TEXT,
+    character_name TEXT,
+    confidence REAL
+);
+CREATE TABLE IF NOT EXISTS notes (
+    name TEXT NOT NULL,
+    character_name TEXT NOT NULL,
+    note TEXT NOT NULL,
+    updated_at INTEGER NOT NULL,
+    PRIMARY KEY (name, character_name)
+);
+CREATE TABLE IF NOT EXISTS overrides (
+    name TEXT PRIMARY KEY,
+    layer TEXT NOT NULL, -- Same JSON as a user layer file
+    updated_at INTEGER NOT NULL
+);
+";
+
+fn database_error(e: rusqlite::Error) -> AppError {
+    AppError::internal(format!("Event database: {}", e))
+}
+
+pub struct SqliteBackend {
+    connection: Arc<Mutex<Connection>>,
+    generation: Arc<AtomicU64>, // Bumped whenever event_search is rebuilt
+}
+
+impl SqliteBackend {
+    pub fn open_default() -> Result<Self, AppError> {
+        let dir = get_config_dir().ok_or_else(|| AppError::internal("No config directory for the event database"))?;
+        Self::open(&dir.join(DATABASE_FILE))
+    }
+
+    pub fn open(path: &Path) -> Result<Self, AppError> {
+        let connection = Connection::open(path).map_err(database_error)?;
+        // The app and the CLI may have the file open at the same time
+        connection.query_row("PRAGMA journal_mode = WAL", [], |_| Ok(())).map_err(database_error)?;
+        connection.execute_batch(SCHEMA).map_err(database_error)?;
+        info!("Opened event database {}", path.display());
+
+        Ok(SqliteBackend { connection: Arc::new(Mutex::new(connection)), generation: Arc::new(AtomicU64::new(0)) })
+    }
+}
+
+impl EventBackend for SqliteBackend {
+    fn kind(&self) -> &'static str {
+        "sqlite"
+    }
+
+    fn load(&self, strict: bool) -> Result<LoadedEvents, AppError> {
+        let (content, source) = events::read_events_source();
+        let hash = content_hash(&content);
+        let mut connection = self.connection.lock().unwrap();
+
+        // Import only when events.json (or the app version) changed since last time
+        let imported: Option<String> = connection
+            .query_row("SELECT value FROM meta WHERE key = 'source_hash'", [], |row| row.get(0))
+            .optional()
+            .map_err(database_error)?;
+        // A changed file is parsed and layered before anything is written, so
+        // a strict reload that fails leaves the previous import current
+        let import = if imported.as_deref() == Some(hash.as_str()) {
+            None
+        } else {
+            Some(events::parse_events(&content, &source, strict)?)
+        };
+        let mut events = match &import {
+            Some(imported) => imported.clone(),
+            None => read_events(&connection)?,
+        };
+        events::apply_user_layers(&mut events, strict)?;
+        apply_overrides(&connection, &mut events, strict)?;
+
+        write_events(&mut connection, import.as_deref().map(|imported| (imported, hash.as_str())), &events)
+            .map_err(database_error)?;
+        if let Some(imported) = &import {
+            info!("Imported {} events from {} into the event database", imported.len(), source);
+        }
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+
+        let index = Box::new(SqliteIndex {
+            connection: Arc::clone(&self.connection),
+            generation,
+            current: Arc::clone(&self.generation),
+        });
+        Ok(LoadedEvents { events, index, source })
+    }
+
+    fn record_lookup(&self, record: LookupRecord) -> Result<(), AppError> {
+        let connection = self.connection.lock().unwrap();
+        connection
+            .execute(
+                "INSERT INTO history (at, origin, text, event_name, character_name, confidence) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![record.at as i64, record.origin, record.text, record.event_name, record.character_name, record.confidence],
+            )
+            .map_err(database_error)?;
+        connection
+            .execute("DELETE FROM history WHERE id <= (SELECT MAX(id) FROM history) - ?1", params![HISTORY_LIMIT])
+            .map_err(database_error)?;
+        Ok(())
+    }
+
+    fn history(&self, limit: usize) -> Result<Vec<LookupRecord>, AppError> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection
+            .prepare("SELECT at, origin, text, event_name, character_name, confidence FROM history ORDER BY id DESC LIMIT ?1")
+            .map_err(database_error)?;
+        let records = statement
+            .query_map(params![limit as i64], |row| {
+                Ok(LookupRecord {
+                    at: row.get::<_, i64>(0)? as u64,
+                    origin: row.get(1)?,
+                    text: row.get(2)?,
+                    event_name: row.get(3)?,
+                    character_name: row.get(4)?,
+                    confidence: row.get(5)?,
+                })
+            })
+            .map_err(database_error)?;
+        records.collect::<Result<_, _>>().map_err(database_error)
+    }
+
+    fn set_note(&self, name: &str, character_name: &str, note: &str) -> Result<(), AppError> {
+        let connection = self.connection.lock().unwrap();
+        let result = if note.trim().is_empty() {
+            connection.execute("DELETE FROM notes WHERE name = ?1 AND character_name = ?2", params![name, character_name])
+        } else {
+            connection.execute(
+                "INSERT OR REPLACE INTO notes (name, character_name, note, updated_at) VALUES (?1, ?2, ?3, ?4)",
+                params![name, character_name, note.trim(), event_backend::now() as i64],
+            )
+        };
+        result.map(|_| ()).map_err(database_error)
+    }
+
+    fn notes(&self) -> Result<Vec<EventNote>, AppError> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection
+            .prepare("SELECT name, character_name, note, updated_at FROM notes ORDER BY name, character_name")
+            .map_err(database_error)?;
+        let notes = statement
+            .query_map([], |row| {
+                Ok(EventNote {
+                    name: row.get(0)?,
+                    character_name: row.get(1)?,
+                    note: row.get(2)?,
+                    updated_at: row.get::<_, i64>(3)? as u64,
+                })
+            })
+            .map_err(database_error)?;
+        notes.collect::<Result<_, _>>().map_err(database_error)
+    }
+
+    fn save_override(&self, name: &str, layer: &str) -> Result<(), AppError> {
+        event_backend::validate_override(name, layer)?;
+        let connection = self.connection.lock().unwrap();
+        connection
+            .execute(
+                "INSERT OR REPLACE INTO overrides (name, layer, updated_at) VALUES (?1, ?2, ?3)",
+                params![name, layer, event_backend::now() as i64],
+            )
+            .map_err(database_error)?;
+        info!("Saved event override {} in the event database", name);
+        Ok(())
+    }
+}
+
+// Hash of the file plus the app version, so an upgrade re-imports too
+fn content_hash(content: &str) -> String {
+    format!("{:016x}-{}", events::stable_hash(content), env!("CARGO_PKG_VERSION"))
+}
+
+// The imported events and their hash (when events.json changed) and the
+// search index of the layered events, in one transaction
+fn write_events(connection: &mut Connection, import: Option<(&[JsonEvent], &str)>, layered: &[JsonEvent]) -> rusqlite::Result<()> {
+    let transaction = connection.transaction()?;
+    if let Some((events, hash)) = import {
+        import_events(&transaction, events, hash)?;
+    }
+    rebuild_search(&transaction, layered)?;
+    transaction.commit()
+}
+
+fn import_events(connection: &Connection, events: &[JsonEvent], hash: &str) -> rusqlite::Result<()> {
+    connection.execute("DELETE FROM events", [])?;
+    let mut insert = connection.prepare(
+        "INSERT INTO events (id, name, character_name, relation_type, data) VALUES (?1, ?2, ?3, ?4, ?5)",
+    )?;
+    for (i, event) in events.iter().enumerate() {
+        let data = serde_json::to_string(event).unwrap_or_default();
+        insert.execute(params![i as i64, event.name, event.character_name, event.relation_type, data])?;
+    }
+    connection.execute("INSERT OR REPLACE INTO meta (key, value) VALUES ('source_hash', ?1)", params![hash])?;
+    Ok(())
+}
+
+fn read_events(connection: &Connection) -> Result<Vec<JsonEvent>, AppError> {
+    let mut statement = connection.prepare("SELECT data FROM events ORDER BY id").map_err(database_error)?;
+    let rows = statement.query_map([], |row| row.get::<_, String>(0)).map_err(database_error)?;
+
+    let mut events = Vec::new();
+    for data in rows {
+        let data = data.map_err(database_error)?;
+        let event = serde_json::from_str(&data).map_err(|e| AppError::EventsLoadFailed {
+            reason: format!("corrupt event in the event database: {}", e),
+        })?;
+        events.push(event);
+    }
+    Ok(events)
+}
+
+// Overrides apply after the layer files, in name order
+fn apply_overrides(connection: &Connection, events: &mut Vec<JsonEvent>, strict: bool) -> Result<(), AppError> {
+    let mut statement = connection.prepare("SELECT name, layer FROM overrides ORDER BY name").map_err(database_error)?;
+    let rows = statement
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+        .map_err(database_error)?;
+
+    for row in rows {
+        let (name, layer) = row.map_err(database_error)?;
+        let layer = match event_layers::parse_layer(format!("db:{}", name), &layer) {
+            Ok(layer) => layer,
+            Err(e) if strict => return Err(e),
+            Err(e) => {
+                info!("Warning: {}, skipping it", e);
+                continue;
+            }
+        };
+        for warning in event_layers::apply_layer(events, &layer) {
+            info!("Warning: Event override {}: {}", layer.name, warning);
+        }
+    }
+    Ok(())
+}
+
+// The index covers the layered events, row N + 1 being events[N]
+fn rebuild_search(connection: &Connection, events: &[JsonEvent]) -> rusqlite::Result<()> {
+    connection.execute("DELETE FROM event_search", [])?;
+    let mut insert = connection.prepare("INSERT INTO event_search (rowid, names, choices) VALUES (?1, ?2, ?3)")?;
+    for (i, event) in events.iter().enumerate() {
+        let names: Vec<String> = event.all_names().map(normalize_text).collect();
+        let choices: Vec<String> = event
+            .choices
+            .iter()
+            .flat_map(|choice| choice.all_texts().map(normalize_text))
+            .collect();
+        insert.execute(params![i as i64 + 1, names.join("\n"), choices.join("\n")])?;
+    }
+    Ok(())
+}
+
+struct SqliteIndex {
+    connection: Arc<Mutex<Connection>>,
+    generation: u64, // Rebuild of event_search these positions refer to
+    current: Arc<AtomicU64>,
+}
+
+impl CandidateIndex for SqliteIndex {
+    // Events ranked by how many of the text's trigrams they contain (bm25)
+    fn candidates(&self, text: &str, limit: usize) -> Option<Vec<usize>> {
+        let query = search_query(text)?;
+        let connection = self.connection.lock().unwrap();
+        if self.current.load(Ordering::SeqCst) != self.generation {
+            return None; // Reloaded since; the rows no longer match these events
+        }
+
+        let rows = connection
+            .prepare_cached("SELECT rowid FROM event_search WHERE event_search MATCH ?1 ORDER BY rank LIMIT ?2")
+            .and_then(|mut statement| {
+                statement
+                    .query_map(params![query, limit as i64], |row| row.get::<_, i64>(0))?
+                    .collect::<rusqlite::Result<Vec<i64>>>()
+            });
+        match rows {
+            Ok(rows) => {
+                let mut candidates: Vec<usize> = rows.into_iter().map(|rowid| rowid as usize - 1).collect();
+                candidates.sort_unstable();
+                Some(candidates)
+            }
+            Err(e) => {
+                info!("Warning: Full-text lookup failed, scanning all events: {}", e);
+                None
+            }
+        }
+    }
+}
+
+// The text's trigrams OR'ed together, so OCR mistakes only cost the
+// trigrams they touch. None when too short to prune safely.
+fn search_query(text: &str) -> Option<String> {
+    let mut trigrams: Vec<String> = normalize_text(text)
+        .split_whitespace()
+        .flat_map(|word| {
+            let chars: Vec<char> = word.chars().collect();
+            chars.windows(3).map(|window| window.iter().collect::<String>()).collect::<Vec<_>>()
+        })
+        .collect();
+    trigrams.sort_unstable();
+    trigrams.dedup();
+    if trigrams.len() < 2 {
+        return None;
+    }
+
+    let phrases: Vec<String> = trigrams.iter().map(|trigram| format!("\"{}\"", trigram.replace('"', "\"\""))).collect();
+    Some(phrases.join(" OR "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event_backend::MemoryBackend;
+    use crate::matcher::match_events_with_index;
+
+    #[test]
+    fn index_finds_the_same_top_event_as_memory() {
+        let sqlite = SqliteBackend::open(Path::new(":memory:")).unwrap().load(false).unwrap();
+        let memory = MemoryBackend::default().load(false).unwrap();
+        assert_eq!(sqlite.events.len(), memory.events.len());
+
+        let event = &memory.events[memory.events.len() / 2];
+        let text = format!("{}\n{}", event.name, event.choices.first().map_or("", |choice| choice.text.as_str()));
+        let from_sqlite = match_events_with_index(&text, &sqlite.events, sqlite.index.as_ref());
+        let from_memory = match_events_with_index(&text, &memory.events, memory.index.as_ref());
+        assert_eq!(from_sqlite[0].event_id, from_memory[0].event_id);
+        assert_eq!(from_sqlite[0].match_confidence, from_memory[0].match_confidence);
+    }
+
+    #[test]
+    fn reopening_with_the_same_hash_skips_the_import() {
+        let dir = std::env::temp_dir().join(format!("uma-helper-sqlite-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(DATABASE_FILE);
+
+        let first = SqliteBackend::open(&path).unwrap();
+        first.load(false).unwrap();
+        {
+            // Mark the stored copy; a re-import would overwrite it
+            let connection = first.connection.lock().unwrap();
+            let data: String = connection.query_row("SELECT data FROM events WHERE id = 0", [], |row| row.get(0)).unwrap();
+            let mut event: JsonEvent = serde_json::from_str(&data).unwrap();
+            event.name = "Imported once".to_string();
+            let data = serde_json::to_string(&event).unwrap();
+            connection.execute("UPDATE events SET data = ?1 WHERE id = 0", params![data]).unwrap();
+        }
+        drop(first);
+
+        let reopened = SqliteBackend::open(&path).unwrap().load(false).unwrap();
+        assert!(reopened.events.iter().any(|event| event.name == "Imported once"));
+
+        drop(reopened);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
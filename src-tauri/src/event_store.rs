@@ -0,0 +1,238 @@
+// The event database of a running app, behind a handle that can be swapped
+// when events.json changes. Lookups take a snapshot, so a reload never
+// changes the events under a search that is already running.
+
+use crate::error::AppError;
+use crate::event_backend::{EventBackend, LoadedEvents};
+use crate::event_layers;
+use crate::events::{self, JsonEvent};
+use crate::search_index::CandidateIndex;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime};
+use tracing::info;
+
+// How often the watcher checks the events file for changes
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+pub struct EventDatabase {
+    pub events: Vec<JsonEvent>,
+    pub index: Box<dyn CandidateIndex + Send + Sync>,
+    pub source: String, // File the events were read from
+}
+
+impl From<LoadedEvents> for EventDatabase {
+    fn from(loaded: LoadedEvents) -> Self {
+        EventDatabase { events: loaded.events, index: loaded.index, source: loaded.source }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct EventsReloaded {
+    pub source: String,
+    pub count: usize,
+    pub previous_count: usize,
+}
+
+pub struct EventStore {
+    backend: Box<dyn EventBackend>,
+    current: RwLock<Arc<EventDatabase>>,
+    reloading: Mutex<()>, // One reload at a time, so counts are reported in order
+}
+
+impl EventStore {
+    // Loads the events through `backend`, which also keeps the user's data
+    pub fn open(backend: Box<dyn EventBackend>) -> Result<Self, AppError> {
+        let database = EventDatabase::from(backend.load(false)?);
+        Ok(EventStore { backend, current: RwLock::new(Arc::new(database)), reloading: Mutex::new(()) })
+    }
+
+    pub fn backend(&self) -> &dyn EventBackend {
+        self.backend.as_ref()
+    }
+
+    pub fn snapshot(&self) -> Arc<EventDatabase> {
+        Arc::clone(&self.current.read().unwrap())
+    }
+
+    // Loads and indexes the events file, then swaps it in. On any error the
+    // events in use are kept.
+    pub fn reload(&self) -> Result<EventsReloaded, AppError> {
+        let _reloading = self.reloading.lock().unwrap();
+
+        let database = Arc::new(EventDatabase::from(self.backend.load(true)?));
+        let reloaded = EventsReloaded {
+            source: database.source.clone(),
+            count: database.events.len(),
+            previous_count: self.snapshot().events.len(),
+        };
+
+        *self.current.write().unwrap() = database;
+        info!("Reloaded {} events from {} (was {})", reloaded.count, reloaded.source, reloaded.previous_count);
+        Ok(reloaded)
+    }
+}
+
+// What identifies a version of a file: where it is, when it was last
+// written and how long it is
+type FileSignature = (PathBuf, Option<SystemTime>, u64);
+
+fn file_signature(path: PathBuf) -> Option<FileSignature> {
+    let metadata = std::fs::metadata(&path).ok()?;
+    Some((path, metadata.modified().ok(), metadata.len()))
+}
+
+// Signatures of events.json (None when it is missing) and of every user layer file
+fn files_signature() -> (Option<FileSignature>, Vec<FileSignature>) {
+    let events_file = events::find_events_path().and_then(file_signature);
+    let layers = event_layers::layer_paths().into_iter().filter_map(file_signature).collect();
+    (events_file, layers)
+}
+
+// Polls events.json and the user layer files, and reloads the store once a
+// change has settled, passing every outcome to `on_reload`. Runs for the
+// rest of the process.
+pub fn spawn_events_watcher<F>(store: Arc<EventStore>, on_reload: F)
+where
+    F: Fn(Result<EventsReloaded, AppError>) + Send + 'static,
+{
+    let spawned = std::thread::Builder::new().name("events-watcher".to_string()).spawn(move || {
+        let mut loaded = files_signature();
+        let mut pending = None;
+        match &loaded.0 {
+            Some((path, _, _)) => info!("Watching {} for changes", path.display()),
+            None => info!("No events.json on disk yet, watching for one to appear"),
+        }
+        if let Some(dir) = event_layers::layers_dir() {
+            info!("Watching {} for user event layers ({} now)", dir.display(), loaded.1.len());
+        }
+
+        loop {
+            std::thread::sleep(POLL_INTERVAL);
+            let current = files_signature();
+            if current == loaded {
+                pending = None;
+                continue;
+            }
+
+            // Editors often save in several writes; wait one more poll for the file to settle
+            if pending.as_ref() != Some(&current) {
+                pending = Some(current);
+                continue;
+            }
+            pending = None;
+            let removed = loaded.0.is_some() && current.0.is_none();
+            loaded = current;
+
+            // A removed events.json keeps the events in use until one shows up again,
+            // rather than falling back to the embedded copy
+            if removed {
+                info!("Warning: events.json was removed, keeping the loaded events");
+                continue;
+            }
+            info!("Event files changed, reloading");
+            on_reload(store.reload());
+        }
+    });
+
+    if let Err(e) = spawned {
+        info!("Warning: Could not start the events watcher: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event_backend::{EventNote, LookupRecord, MemoryBackend};
+    use crate::search_index::SearchIndex;
+    use std::path::Path;
+
+    // Events from one file, parsed the way the real backends parse them
+    struct FileBackend {
+        path: PathBuf,
+        user_data: MemoryBackend,
+    }
+
+    impl EventBackend for FileBackend {
+        fn kind(&self) -> &'static str {
+            "file"
+        }
+
+        fn load(&self, strict: bool) -> Result<LoadedEvents, AppError> {
+            let source = self.path.display().to_string();
+            let content = std::fs::read_to_string(&self.path)
+                .map_err(|e| AppError::EventsLoadFailed { reason: e.to_string() })?;
+            let events = events::parse_events(&content, &source, strict)?;
+            let index = Box::new(SearchIndex::build(&events));
+            Ok(LoadedEvents { events, index, source })
+        }
+
+        fn record_lookup(&self, record: LookupRecord) -> Result<(), AppError> {
+            self.user_data.record_lookup(record)
+        }
+
+        fn history(&self, limit: usize) -> Result<Vec<LookupRecord>, AppError> {
+            self.user_data.history(limit)
+        }
+
+        fn set_note(&self, name: &str, character_name: &str, note: &str) -> Result<(), AppError> {
+            self.user_data.set_note(name, character_name, note)
+        }
+
+        fn notes(&self) -> Result<Vec<EventNote>, AppError> {
+            self.user_data.notes()
+        }
+
+        fn save_override(&self, name: &str, layer: &str) -> Result<(), AppError> {
+            self.user_data.save_override(name, layer)
+        }
+    }
+
+    fn write_events(path: &Path, names: &[&str]) {
+        let events: Vec<String> = names
+            .iter()
+            .map(|name| {
+                format!(
+                    r#"{{ "name": "{}", "character_name": "Vodka", "relation_type": "Umamusume",
+                         "choices": [{{ "number": "1", "text": "Rest", "outcome": "10 Energy" }}] }}"#,
+                    name
+                )
+            })
+            .collect();
+        std::fs::write(path, format!("[{}]", events.join(",\n"))).unwrap();
+    }
+
+    #[test]
+    fn reload_swaps_valid_files_and_keeps_the_rest() {
+        let dir = std::env::temp_dir().join(format!("uma-helper-event-store-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("events.json");
+        write_events(&path, &["New Year"]);
+
+        let backend = FileBackend { path: path.clone(), user_data: MemoryBackend::default() };
+        let store = EventStore::open(Box::new(backend)).unwrap();
+        let before = store.snapshot();
+
+        // A valid rewrite replaces the events; snapshots taken earlier keep theirs
+        write_events(&path, &["New Year", "At Summer Camp"]);
+        let reloaded = store.reload().unwrap();
+        assert_eq!((reloaded.count, reloaded.previous_count), (2, 1));
+        assert_eq!(store.snapshot().events[1].name, "At Summer Camp");
+        assert_eq!(before.events.len(), 1);
+
+        // A rewrite with a broken entry is rejected and the loaded events stay
+        let loaded = store.snapshot();
+        std::fs::write(&path, r#"[{ "name": "New Year", "choices": "oops" }]"#).unwrap();
+        let error = store.reload().unwrap_err();
+        assert!(matches!(&error, AppError::EventsLoadFailed { reason } if reason.contains("1 invalid event")));
+        assert!(Arc::ptr_eq(&loaded, &store.snapshot()));
+
+        // So is one that is no longer an array of events
+        std::fs::write(&path, "{").unwrap();
+        assert!(store.reload().is_err());
+        assert!(Arc::ptr_eq(&loaded, &store.snapshot()));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
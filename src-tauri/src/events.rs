@@ -0,0 +1,209 @@
+use crate::error::AppError;
+use crate::event_layers;
+use crate::event_lint::{self, EventsReport, Severity};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+// Provenance of events from events.json itself
+pub const BUNDLED_SOURCE: &str = "bundled";
+
+// JSON Event structures (matching events.json format)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct JsonEvent {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub names: BTreeMap<String, String>, // Localised names by language code, e.g. "jpn"
+    pub character_name: String,
+    pub relation_type: String,
+    pub choices: Vec<Choice>,
+    #[serde(skip)]
+    pub provenance: Provenance, // Set by the loader, never read from a file
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Provenance {
+    pub source: String, // "bundled", or the user layer file that added the event
+    pub patched_by: Vec<String>, // User layer files that changed it, in the order applied
+}
+
+impl Default for Provenance {
+    fn default() -> Self {
+        Provenance { source: BUNDLED_SOURCE.to_string(), patched_by: Vec::new() }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Choice {
+    pub text: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub texts: BTreeMap<String, String>, // Localised choice texts by language code
+    pub number: String,
+    pub outcome: String,
+}
+
+impl JsonEvent {
+    // The English name followed by every localised one
+    pub fn all_names(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.name.as_str()).chain(self.names.values().map(String::as_str))
+    }
+
+    // Stays the same across reloads and data updates as long as the name and
+    // character do (ignoring stray whitespace and trailing commas)
+    pub fn id(&self) -> String {
+        let character_name = self.character_name.trim().trim_matches(',').trim();
+        format!("{:016x}", stable_hash(&format!("{}\u{1f}{}", self.name.trim(), character_name)))
+    }
+}
+
+// FNV-1a: unlike the std hashers, the same on every build and platform
+pub fn stable_hash(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+impl Choice {
+    pub fn all_texts(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.text.as_str()).chain(self.texts.values().map(String::as_str))
+    }
+}
+
+pub fn load_events_json() -> Result<Vec<JsonEvent>, AppError> {
+    load_events_with_source().map(|(events, _)| events)
+}
+
+// The events, with the user layers applied, and the file they were read from
+pub fn load_events_with_source() -> Result<(Vec<JsonEvent>, String), AppError> {
+    let (content, source) = read_events_source();
+    info!("Loading events from: {}", source);
+    
+    let mut events = parse_events(&content, &source, false)?;
+    apply_user_layers(&mut events, false)?;
+    Ok((events, source))
+}
+
+// The events file on its own, without the user layers, for output that must
+// not depend on the user's files (CLI defaults, benchmarks)
+pub fn load_events_without_layers() -> Result<Vec<JsonEvent>, AppError> {
+    let (content, source) = read_events_source();
+    parse_events(&content, &source, false)
+}
+
+// Reads the events file again for a running app. Unlike the first load, a
+// skipped entry fails the reload, so a half-finished edit keeps the data in use.
+pub fn reload_events_json() -> Result<(Vec<JsonEvent>, String), AppError> {
+    let (content, source) = read_events_source();
+    
+    let mut events = parse_events(&content, &source, true)?;
+    apply_user_layers(&mut events, true)?;
+    Ok((events, source))
+}
+
+// Validates an events file. Bad entries are skipped rather than failing the
+// whole database, unless `strict`, where they (or an empty file) are an error.
+pub fn parse_events(content: &str, source: &str, strict: bool) -> Result<Vec<JsonEvent>, AppError> {
+    let (events, issues) = event_lint::validate_events(content)?;
+    let skipped = issues.iter().filter(|issue| issue.severity == Severity::Error).count();
+    if skipped > 0 && strict {
+        return Err(AppError::EventsLoadFailed {
+            reason: format!("{} invalid event(s) in {}, run `uma-cli lint-events` for details", skipped, source),
+        });
+    }
+    if skipped > 0 {
+        info!("Warning: Skipped {} invalid event(s) in {}, run `uma-cli lint-events` for details", skipped, source);
+    }
+    if events.is_empty() && strict {
+        return Err(AppError::EventsLoadFailed { reason: format!("no events in {}", source) });
+    }
+    
+    Ok(events)
+}
+
+// Applies the user layer files over `events`. A layer that cannot be read
+// is skipped on the first load and fails a reload (`strict`).
+pub fn apply_user_layers(events: &mut Vec<JsonEvent>, strict: bool) -> Result<(), AppError> {
+    for path in event_layers::layer_paths() {
+        let layer = match event_layers::read_layer(&path) {
+            Ok(layer) => layer,
+            Err(e) if strict => return Err(e),
+            Err(e) => {
+                info!("Warning: {}, skipping it", e);
+                continue;
+            }
+        };
+        
+        for warning in event_layers::apply_layer(events, &layer) {
+            info!("Warning: Event layer {}: {}", layer.name, warning);
+        }
+        info!(
+            "Applied event layer {}: {} addition(s), {} patch(es), {} hide rule(s)",
+            layer.name,
+            layer.add.len(),
+            layer.patch.len(),
+            layer.hide.len()
+        );
+    }
+    Ok(())
+}
+
+// Lints the events file the app would load, or `path` when given
+pub fn lint_events_json(path: Option<&Path>) -> Result<EventsReport, AppError> {
+    let (content, source) = match path {
+        Some(path) => {
+            let content = std::fs::read_to_string(path).map_err(|e| AppError::EventsLoadFailed {
+                reason: format!("could not read {}: {}", path.display(), e),
+            })?;
+            (content, path.display().to_string())
+        }
+        None => read_events_source(),
+    };
+    
+    let (events, issues) = event_lint::validate_events(&content)?;
+    let skipped = issues.iter().filter(|issue| issue.severity == Severity::Error).count();
+    Ok(EventsReport { source, loaded: events.len(), skipped, issues })
+}
+
+// First events.json found on disk, or None when the embedded copy is used
+pub fn find_events_path() -> Option<PathBuf> {
+    // Try multiple possible locations for events.json
+    let mut possible_paths = vec![
+        PathBuf::from("events.json"),    // Current directory
+        PathBuf::from("../events.json"), // Parent directory (from src-tauri)
+    ];
+    
+    // For bundled app, try various resource locations
+    if let Ok(exe_path) = std::env::current_exe() {
+        if let Some(exe_dir) = exe_path.parent() {
+            // Try in same directory as executable
+            possible_paths.push(exe_dir.join("events.json"));
+            
+            // Try in share directory (Linux package structure)
+            possible_paths.push(exe_dir.join("../share/uma-helper/events.json"));
+            
+            // Try in resources subdirectory
+            possible_paths.push(exe_dir.join("resources/events.json"));
+        }
+    }
+    
+    // Absolute, so the path stays valid if the working directory changes
+    possible_paths
+        .into_iter()
+        .find(|path| path.is_file())
+        .map(|path| path.canonicalize().unwrap_or(path))
+}
+
+// Contents of the first events.json found, and where it came from
+pub fn read_events_source() -> (String, String) {
+    if let Some(path) = find_events_path() {
+        match std::fs::read_to_string(&path) {
+            Ok(content) => return (content, path.display().to_string()),
+            Err(e) => info!("Warning: Could not read {}: {}", path.display(), e),
+        }
+    }
+    
+    // Fallback to embedded events.json
+    info!("Using embedded events.json as fallback");
+    (include_str!("../../events.json").to_string(), "embedded events.json".to_string())
+}
@@ -0,0 +1,80 @@
+// Perceptual difference hash (dHash) of a captured frame, used by watch mode
+// to tell whether the region content changed enough to be worth OCRing.
+
+use image::imageops::FilterType;
+use image::DynamicImage;
+
+// 16x16 comparisons: coarse enough to ignore noise, fine enough for text
+const HASH_SIZE: u32 = 16;
+const HASH_BITS: usize = (HASH_SIZE * HASH_SIZE) as usize;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameHash {
+    bits: Vec<u64>,
+}
+
+impl FrameHash {
+    pub fn new(image: &DynamicImage) -> Self {
+        let small = image
+            .resize_exact(HASH_SIZE + 1, HASH_SIZE, FilterType::Triangle)
+            .to_luma8();
+
+        let mut bits = vec![0_u64; HASH_BITS / 64];
+        let mut bit = 0;
+        for y in 0..HASH_SIZE {
+            for x in 0..HASH_SIZE {
+                if small.get_pixel(x, y)[0] > small.get_pixel(x + 1, y)[0] {
+                    bits[bit / 64] |= 1 << (bit % 64);
+                }
+                bit += 1;
+            }
+        }
+
+        FrameHash { bits }
+    }
+
+    // Fraction of differing bits: 0.0 for identical frames, ~0.5 for unrelated ones
+    pub fn distance(&self, other: &FrameHash) -> f32 {
+        let differing: u32 = self
+            .bits
+            .iter()
+            .zip(&other.bits)
+            .map(|(a, b)| (a ^ b).count_ones())
+            .sum();
+        differing as f32 / HASH_BITS as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GrayImage, Luma};
+
+    // Dark text-like bars on a light background, one per entry of `rows`
+    fn frame(rows: &[u32]) -> DynamicImage {
+        let image = GrayImage::from_fn(170, 80, |x, y| {
+            let on_bar = rows.iter().any(|&row| (row..row + 6).contains(&y)) && (x / 10) % 3 != 2;
+            Luma([if on_bar { 30 } else { 220 }])
+        });
+        DynamicImage::ImageLuma8(image)
+    }
+
+    #[test]
+    fn identical_frames_have_no_distance() {
+        let hash = FrameHash::new(&frame(&[10, 40]));
+        assert_eq!(hash.distance(&FrameHash::new(&frame(&[10, 40]))), 0.0);
+        assert_eq!(hash.distance(&hash), 0.0);
+    }
+
+    #[test]
+    fn changed_frames_are_apart() {
+        let hash = FrameHash::new(&frame(&[10, 40]));
+        let changed = FrameHash::new(&frame(&[10, 40, 60]));
+        let blank = FrameHash::new(&frame(&[]));
+
+        let distance = hash.distance(&changed);
+        assert!(distance > 0.0);
+        assert_eq!(distance, changed.distance(&hash));
+        assert!(hash.distance(&blank) > distance);
+    }
+}
@@ -0,0 +1,102 @@
+// OCR language codes and the script specific handling that goes with them:
+// which traineddata files are needed, which whitelist makes sense and how
+// CJK text (written without spaces) is told apart from Latin text.
+
+use crate::error::AppError;
+
+// Applied when every configured language uses the Latin script
+const LATIN_WHITELIST: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789 .,!?'-:()[]{}\"";
+
+// Tesseract languages written in CJK scripts (vertical variants included)
+const CJK_LANGUAGES: &[&str] = &["jpn", "chi_sim", "chi_tra", "kor"];
+
+// Individual Tesseract codes of a combined language such as "eng+jpn"
+pub fn language_codes(language: &str) -> impl Iterator<Item = &str> {
+    language.split('+').map(str::trim).filter(|code| !code.is_empty())
+}
+
+pub fn validate_language(language: &str) -> Result<(), AppError> {
+    if language_codes(language).next().is_none() {
+        return Err(AppError::invalid_input("OCR language must not be empty"));
+    }
+    for code in language_codes(language) {
+        if !code.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_') {
+            return Err(AppError::invalid_input(format!("Invalid OCR language code: {}", code)));
+        }
+    }
+    Ok(())
+}
+
+// Language of the event data a Tesseract code reads: "jpn_vert" is still "jpn"
+pub fn data_language(code: &str) -> &str {
+    code.strip_suffix("_vert").unwrap_or(code)
+}
+
+pub fn is_cjk_language(code: &str) -> bool {
+    CJK_LANGUAGES.contains(&data_language(code))
+}
+
+// Tesseract cannot whitelist the thousands of characters CJK text uses, so
+// only all-Latin language sets get one
+pub fn default_whitelist(language: &str) -> Option<String> {
+    if language_codes(language).any(is_cjk_language) {
+        None
+    } else {
+        Some(LATIN_WHITELIST.to_string())
+    }
+}
+
+// Kana, CJK ideographs and Hangul: scripts written without spaces between words
+pub fn is_cjk(c: char) -> bool {
+    matches!(
+        c,
+        '\u{3040}'..='\u{30FF}' // Hiragana and Katakana
+            | '\u{3400}'..='\u{4DBF}' // CJK Extension A
+            | '\u{4E00}'..='\u{9FFF}' // CJK Unified Ideographs
+            | '\u{F900}'..='\u{FAFF}' // CJK Compatibility Ideographs
+            | '\u{FF66}'..='\u{FF9F}' // Halfwidth Katakana
+            | '\u{AC00}'..='\u{D7AF}' // Hangul syllables
+    )
+}
+
+// Folds full-width ASCII and the ideographic space to their plain forms, as
+// the Japanese client mixes both
+pub fn fold_width(c: char) -> char {
+    match c {
+        '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+        '\u{3000}' => ' ',
+        _ => c,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn folds_full_width_ascii() {
+        let folded: String = "ＡＢＣ１２３！　ｚ".chars().map(fold_width).collect();
+        assert_eq!(folded, "ABC123! z");
+        assert_eq!(fold_width('夏'), '夏');
+        assert_eq!(fold_width('ｱ'), 'ｱ'); // Half-width katakana is not ASCII
+    }
+
+    #[test]
+    fn tells_cjk_from_latin() {
+        assert!("夏合宿かカ한ｱ".chars().all(is_cjk));
+        assert!(!"Az9 !é".chars().any(is_cjk));
+
+        assert!(is_cjk_language("jpn_vert"));
+        assert!(!is_cjk_language("eng"));
+        assert!(default_whitelist("eng").is_some());
+        assert!(default_whitelist("eng+jpn").is_none());
+    }
+
+    #[test]
+    fn validates_language_codes() {
+        assert_eq!(language_codes(" eng + jpn_vert +").collect::<Vec<_>>(), ["eng", "jpn_vert"]);
+        assert!(validate_language("eng+jpn").is_ok());
+        assert!(validate_language(" + ").is_err());
+        assert!(validate_language("eng;rm").is_err());
+    }
+}
@@ -0,0 +1,30 @@
+// Capture, event data, matching and scoring shared by the app, the CLI and the benchmarks.
+
+pub mod capture;
+pub mod cleanup;
+pub mod color_key;
+pub mod error;
+pub mod event_backend;
+pub mod event_import;
+pub mod event_layers;
+pub mod event_lint;
+#[cfg(feature = "sqlite")]
+pub mod event_sqlite;
+pub mod event_store;
+pub mod events;
+pub mod frame_hash;
+pub mod language;
+pub mod matcher;
+pub mod matcher_config;
+pub mod matcher_tune;
+pub mod ocr_confusion;
+pub mod ocr_engine;
+pub mod ocr_layout;
+pub mod ocr_variants;
+pub mod outcome;
+pub mod paths;
+pub mod preprocess;
+pub mod run_context;
+pub mod scoring;
+pub mod search_index;
+pub mod settings_file;
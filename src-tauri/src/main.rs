@@ -2,151 +2,136 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 use anyhow::Result;
-use image::GenericImageView;
-use screenshots::Screen;
 use serde::{Deserialize, Serialize};
-use tauri::{State, Manager, AppHandle};
-use tesseract::Tesseract;
+use tauri::{State, Manager, AppHandle, Emitter};
 use tracing::info;
-use strsim::jaro_winkler;
 use base64::Engine;
+use std::sync::{Arc, Mutex};
 
-// JSON Event structures (matching events.json format)
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct JsonEvent {
-    name: String,
-    character_name: String,
-    relation_type: String,
-    choices: Vec<Choice>,
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct Choice {
-    text: String,
-    number: String,
-    outcome: String,
-}
+mod watch;
 
+use uma_helper_lib::capture::{self, CaptureArea, CaptureSource, CaptureSourceConfig, DisplayGeometry};
+use uma_helper_lib::color_key::{self, ColorKey, ColorKeyPreset};
+use uma_helper_lib::error::AppError;
+use watch::{WatchConfig, WatchController, WatchStatus};
+use uma_helper_lib::event_lint::EventsReport;
+use uma_helper_lib::event_backend::{self, EventNote, LookupRecord};
+use uma_helper_lib::event_store::{self, EventStore, EventsReloaded};
+use uma_helper_lib::events::lint_events_json;
+use uma_helper_lib::matcher::{match_events_in_context, score_event_matches, EventMatch};
+use uma_helper_lib::matcher_config::{MatcherConfig, MatcherConfigStore, MATCHER_CONFIG_FILE};
+use uma_helper_lib::ocr_confusion::{ConfusionTable, OCR_CONFUSIONS_FILE};
+use uma_helper_lib::ocr_engine::{self, OcrConfig, OcrEngine};
+use uma_helper_lib::ocr_layout::OcrLine;
+use uma_helper_lib::ocr_variants::{self, VariantCandidate, VariantConfig, VariantScore};
+use uma_helper_lib::paths::get_config_dir;
+use uma_helper_lib::preprocess::{self, PreprocessConfig, PreprocessPreset};
+use uma_helper_lib::run_context::{self, CharacterOption, RunContext, RunContextStore, RUN_CONTEXT_FILE};
+use uma_helper_lib::scoring::{ProfileList, ProfileStore, WeightProfile, WEIGHT_PROFILES_FILE};
 
-// Enhanced OCR result with event matching
-#[derive(Debug, Serialize, Deserialize)]
-struct EventMatch {
-    event: JsonEvent,
-    match_confidence: f32,
-    match_type: String, // "event_name" or "choice_text"
-    matched_text: String,
-}
+const EVENTS_RELOADED_EVENT: &str = "events-reloaded";
+const EVENTS_RELOAD_FAILED_EVENT: &str = "events-reload-failed";
 
 #[derive(Debug, Serialize, Deserialize)]
 struct OcrResult {
     text: String,
     confidence: f32,
+    lines: Vec<OcrLine>, // Recognised words with boxes in capture coordinates
     matched_events: Vec<EventMatch>,
+    chosen_variant: String, // Preprocessing variant the text was taken from
+    variant_scores: Vec<VariantScore>, // One entry per variant that ran
     debug_captured_image: Option<String>, // Base64 encoded image
     debug_processed_image: Option<String>, // Base64 encoded image
+    debug_stages: Vec<DebugStage>, // Pipeline intermediates, when enabled
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-struct CaptureArea {
-    x: i32,
-    y: i32,
-    width: u32,
-    height: u32,
+struct DebugStage {
+    name: String,
+    image: String, // Base64 encoded image
 }
 
 // Application state
 struct AppState {
-    events: Vec<JsonEvent>,
+    events: Arc<EventStore>, // Swapped as a whole when events.json is reloaded
+    profiles: Mutex<ProfileStore>,
+    run_context: Mutex<RunContextStore>,
+    matcher: Mutex<MatcherConfigStore>,
+    confusions: ConfusionTable,
+    ocr: Arc<OcrEngine>,
+    preprocess: Mutex<PreprocessConfig>,
+    variants: Mutex<VariantConfig>,
+    watch: WatchController,
+    capture: Arc<Mutex<Box<dyn CaptureSource>>>,
 }
 
 impl AppState {
     fn new() -> Result<Self> {
-        // Load JSON events
-        let events = load_events_json()?;
-        info!("Loaded {} events from events.json", events.len());
+        // Load JSON events (into the SQLite store when built with it)
+        let events = Arc::new(EventStore::open(event_backend::open_default())?);
+        info!("Loaded {} events from events.json ({} store)", events.snapshot().events.len(), events.backend().kind());
+
+        // Load saved weight profiles (falls back to the built-in default)
+        let profiles_path = get_config_dir().map(|dir| dir.join(WEIGHT_PROFILES_FILE));
+        let profiles = ProfileStore::load(profiles_path);
+        info!("Active weight profile: {}", profiles.list().active);
+
+        // Restore the run being trained in the last session
+        let run_context_path = get_config_dir().map(|dir| dir.join(RUN_CONTEXT_FILE));
+        let run_context = RunContextStore::load(run_context_path);
+        info!("Run context: {:?}", run_context.context());
+        
+        // Matcher thresholds and weights, as last adjusted
+        let matcher_path = get_config_dir().map(|dir| dir.join(MATCHER_CONFIG_FILE));
+        let matcher = MatcherConfigStore::load(matcher_path);
+        info!("Matcher config: {:?}", matcher.config());
+        
+        // Built-in OCR confusions plus any learned with `uma-cli learn-confusions`
+        let confusions_path = get_config_dir().map(|dir| dir.join(OCR_CONFUSIONS_FILE));
+        let confusions = ConfusionTable::load(confusions_path);
+
+        // Capture from the screen unless a replay source is requested
+        let capture_config = match std::env::var("UMA_HELPER_REPLAY") {
+            Ok(path) => CaptureSourceConfig::Replay { path, loop_frames: true, crop_to_area: false },
+            Err(_) => CaptureSourceConfig::Screen,
+        };
+        let capture = capture_config.open()?;
 
         Ok(AppState {
             events,
+            profiles: Mutex::new(profiles),
+            run_context: Mutex::new(run_context),
+            matcher: Mutex::new(matcher),
+            confusions,
+            ocr: Arc::new(OcrEngine::new(OcrConfig::default())),
+            preprocess: Mutex::new(PreprocessConfig::default()),
+            variants: Mutex::new(VariantConfig::default()),
+            watch: WatchController::default(),
+            capture: Arc::new(Mutex::new(capture)),
         })
     }
-}
 
-fn load_events_json() -> Result<Vec<JsonEvent>> {
-    // Try multiple possible locations for events.json
-    let mut possible_paths = vec![
-        "events.json".to_string(),           // Current directory
-        "../events.json".to_string(),        // Parent directory (from src-tauri)
-    ];
-    
-    // For bundled app, try various resource locations
-    if let Ok(exe_path) = std::env::current_exe() {
-        if let Some(exe_dir) = exe_path.parent() {
-            // Try in same directory as executable
-            possible_paths.push(exe_dir.join("events.json").to_string_lossy().to_string());
-            
-            // Try in share directory (Linux package structure)
-            possible_paths.push(exe_dir.join("../share/uma-helper/events.json").to_string_lossy().to_string());
-            
-            // Try in resources subdirectory
-            possible_paths.push(exe_dir.join("resources/events.json").to_string_lossy().to_string());
-        }
+    fn active_profile(&self) -> WeightProfile {
+        self.profiles.lock().unwrap().active()
     }
-    
-    let mut events_content = String::new();
-    let mut found_path = None;
-    
-    for path in &possible_paths {
-        match std::fs::read_to_string(path) {
-            Ok(content) => {
-                events_content = content;
-                found_path = Some(path);
-                break;
-            }
-            Err(_) => continue,
-        }
+
+    fn run_context(&self) -> RunContext {
+        self.run_context.lock().unwrap().context().clone()
     }
     
-    if found_path.is_none() {
-        // Fallback to embedded events.json
-        info!("Using embedded events.json as fallback");
-        events_content = include_str!("../../events.json").to_string();
-    } else {
-        info!("Loading events from: {}", found_path.unwrap());
+    fn matcher_config(&self) -> MatcherConfig {
+        self.matcher.lock().unwrap().config().clone()
     }
-    
-    let events: Vec<JsonEvent> = serde_json::from_str(&events_content)
-        .map_err(|e| anyhow::anyhow!("Failed to parse events.json: {}", e))?;
-    
-    Ok(events)
 }
 
 // Tauri commands
 #[tauri::command]
-async fn capture_screen_area(area: CaptureArea, state: State<'_, AppState>, app_handle: AppHandle) -> Result<OcrResult, String> {
+async fn capture_screen_area(area: CaptureArea, color_key: Option<String>, state: State<'_, AppState>, app_handle: AppHandle) -> Result<OcrResult, AppError> {
     info!("Capturing screen area: {:?}", area);
     
-    // Get all screens
-    let screens = Screen::all().map_err(|e| format!("Failed to get screens: {}", e))?;
-    
-    if screens.is_empty() {
-        return Err("No screens found".to_string());
-    }
-
-    // Use primary screen
-    let screen = &screens[0];
-    
-    // Capture the screen  
-    let screen_image = screen.capture().map_err(|e| format!("Failed to capture screen: {}", e))?;
-    
-    // Convert screenshot to DynamicImage
-    let (width, height) = (screen_image.width(), screen_image.height());
-    let rgba_data = screen_image.rgba().to_vec();
-    let image_buffer = image::ImageBuffer::from_raw(width, height, rgba_data)
-        .ok_or("Failed to create image buffer")?;
-    let dynamic_image = image::DynamicImage::ImageRgba8(image_buffer);
-    
-    // Crop to the specified area
-    let cropped = crop_image(&dynamic_image, area)?;
+    // Regions with known text colours pick a colour-key preset per capture
+    let color_key = resolve_color_key(color_key.as_deref())?;
+    let cropped = capture_area_image(&state.capture, &area)?;
     
     // Save captured image for debugging (optional - don't fail OCR if this fails)
     if let Err(e) = save_debug_image(&cropped, "captured_image.png") {
@@ -154,7 +139,22 @@ async fn capture_screen_area(area: CaptureArea, state: State<'_, AppState>, app_
     }
     
     // Perform OCR
-    perform_ocr(&cropped, &state, Some(&app_handle)).await
+    let result = perform_ocr(&cropped, &state, Some(&app_handle), color_key).await?;
+    record_lookup(&state, "capture", &result.text, &result.matched_events);
+    Ok(result)
+}
+
+fn resolve_color_key(name: Option<&str>) -> Result<Option<ColorKey>, AppError> {
+    match name {
+        None | Some("") => Ok(None),
+        Some(name) => color_key::preset(name)
+            .map(Some)
+            .ok_or_else(|| AppError::invalid_input(format!("Unknown colour key preset: {}", name))),
+    }
+}
+
+fn capture_area_image(source: &Mutex<Box<dyn CaptureSource>>, area: &CaptureArea) -> Result<image::DynamicImage, AppError> {
+    source.lock().unwrap().capture(area)
 }
 
 fn get_writable_debug_dir() -> Result<std::path::PathBuf, String> {
@@ -205,329 +205,146 @@ fn image_to_base64(image: &image::DynamicImage) -> Result<String, String> {
     Ok(format!("data:image/png;base64,{}", base64_string))
 }
 
-fn crop_image(image: &image::DynamicImage, area: CaptureArea) -> Result<image::DynamicImage, String> {
-    let (img_width, img_height) = image.dimensions();
-    
-    // Ensure crop area is within bounds
-    let x = area.x.max(0) as u32;
-    let y = area.y.max(0) as u32;
-    let width = area.width.min(img_width.saturating_sub(x));
-    let height = area.height.min(img_height.saturating_sub(y));
-    
-    if width == 0 || height == 0 {
-        return Err("Invalid crop area".to_string());
-    }
-    
-    let cropped = image.crop_imm(x, y, width, height);
-    Ok(cropped)
-}
-
-fn preprocess_image_for_ocr(image: &image::DynamicImage) -> image::DynamicImage {
-    use image::imageops;
-    
-    // Convert to grayscale for better OCR
-    let gray_image = image.to_luma8();
-    
-    // Analyze image to determine if we should invert (for white text on dark background)
-    let should_invert = analyze_text_brightness(&gray_image);
-    
-    // Scale up the image for better OCR (2x scale)
-    let (width, height) = gray_image.dimensions();
-    let scaled_width = width * 2;
-    let scaled_height = height * 2;
-    
-    let scaled_gray = imageops::resize(
-        &gray_image,
-        scaled_width,
-        scaled_height,
-        imageops::FilterType::Lanczos3,
+fn find_event_matches(text: &str, state: &AppState) -> Vec<EventMatch> {
+    let database = state.events.snapshot();
+    let mut matches = match_events_in_context(
+        text,
+        &database.events,
+        &*database.index,
+        &state.run_context(),
+        &state.matcher_config(),
+        &state.confusions,
     );
-    
-    // Apply minimal processing - just inversion if needed
-    let processed_gray = if should_invert {
-        info!("Inverting image for white text detection");
-        invert_image(&scaled_gray)
-    } else {
-        scaled_gray
-    };
-    
-    // Convert back to DynamicImage with minimal contrast adjustment
-    let processed = image::DynamicImage::ImageLuma8(processed_gray);
-    
-    // Apply light contrast enhancement only
-    let contrasted = imageops::contrast(&processed, 20.0);
-    
-    // Return the contrasted image wrapped in DynamicImage
-    image::DynamicImage::ImageRgba8(contrasted)
-}
-
-fn analyze_text_brightness(gray_image: &image::ImageBuffer<image::Luma<u8>, Vec<u8>>) -> bool {
-    // Sample pixels to determine if the image has more dark background (indicating white text)
-    let (width, height) = gray_image.dimensions();
-    let mut dark_pixels = 0;
-    let mut total_pixels = 0;
-    
-    // Sample every 4th pixel to check brightness distribution
-    for y in (0..height).step_by(4) {
-        for x in (0..width).step_by(4) {
-            let pixel = gray_image.get_pixel(x, y);
-            let brightness = pixel[0];
-            
-            if brightness < 128 {
-                dark_pixels += 1;
-            }
-            total_pixels += 1;
-        }
-    }
-    
-    // If more than 60% of pixels are dark, likely white text on dark background
-    let dark_ratio = dark_pixels as f32 / total_pixels as f32;
-    info!("Dark pixel ratio: {:.2}, should_invert: {}", dark_ratio, dark_ratio > 0.6);
-    
-    dark_ratio > 0.6
-}
-
-fn invert_image(gray_image: &image::ImageBuffer<image::Luma<u8>, Vec<u8>>) -> image::ImageBuffer<image::Luma<u8>, Vec<u8>> {
-    let (width, height) = gray_image.dimensions();
-    let mut inverted = image::ImageBuffer::new(width, height);
-    
-    for (x, y, pixel) in gray_image.enumerate_pixels() {
-        let inverted_value = 255 - pixel[0];
-        inverted.put_pixel(x, y, image::Luma([inverted_value]));
-    }
-    
-    inverted
+    score_event_matches(&mut matches, &state.active_profile());
+    matches
 }
 
-fn match_events_with_text(extracted_text: &str, events: &[JsonEvent]) -> Vec<EventMatch> {
-    let mut matches = Vec::new();
-    let threshold = 0.6; // Minimum similarity threshold
-    
-    // Clean and normalize extracted text for better matching
-    let normalized_text = normalize_text(extracted_text);
-    
-    for event in events {
-        // Try matching against event name
-        let event_name_similarity = jaro_winkler(&normalize_text(&event.name), &normalized_text) as f32;
-        if event_name_similarity >= threshold {
-            matches.push(EventMatch {
-                event: event.clone(),
-                match_confidence: event_name_similarity,
-                match_type: "event_name".to_string(),
-                matched_text: event.name.clone(),
-            });
-        }
-        
-        // Try matching against choice texts
-        for choice in &event.choices {
-            let choice_similarity = jaro_winkler(&normalize_text(&choice.text), &normalized_text) as f32;
-            if choice_similarity >= threshold {
-                matches.push(EventMatch {
-                    event: event.clone(),
-                    match_confidence: choice_similarity,
-                    match_type: "choice_text".to_string(),
-                    matched_text: choice.text.clone(),
-                });
-            }
-        }
-        
-        // Try partial word matching for OCR errors
-        if event_name_similarity < threshold {
-            let partial_similarity = calculate_partial_match(&normalized_text, &normalize_text(&event.name));
-            if partial_similarity >= threshold + 0.1_f32 { // Higher threshold for partial matches
-                matches.push(EventMatch {
-                    event: event.clone(),
-                    match_confidence: partial_similarity,
-                    match_type: "partial_event_name".to_string(),
-                    matched_text: event.name.clone(),
-                });
-            }
-        }
+// Remembers a lookup and its best match; history is a convenience, so errors are only logged
+fn record_lookup(state: &AppState, origin: &str, text: &str, matches: &[EventMatch]) {
+    let best = matches.first();
+    let record = LookupRecord {
+        at: event_backend::now(),
+        origin: origin.to_string(),
+        text: text.to_string(),
+        event_name: best.map(|event_match| event_match.event.name.clone()),
+        character_name: best.map(|event_match| event_match.event.character_name.clone()),
+        confidence: best.map(|event_match| event_match.match_confidence),
+    };
+    if let Err(e) = state.events.backend().record_lookup(record) {
+        info!("Warning: Could not record lookup: {}", e);
     }
-    
-    // Sort by confidence (highest first) and limit results
-    matches.sort_by(|a, b| b.match_confidence.partial_cmp(&a.match_confidence).unwrap());
-    matches.truncate(5); // Return top 5 matches
-    
-    matches
 }
 
-fn normalize_text(text: &str) -> String {
-    text.to_lowercase()
-        .chars()
-        .filter(|c| c.is_alphanumeric() || c.is_whitespace())
-        .collect::<String>()
-        .split_whitespace()
-        .collect::<Vec<&str>>()
-        .join(" ")
+fn get_tessdata_path(app_handle: Option<&AppHandle>, language: &str) -> Option<String> {
+    // Bundled tessdata (via the Tauri resource API) takes priority
+    let resource_dir = app_handle.and_then(|handle| handle.path().resource_dir().ok());
+    ocr_engine::find_tessdata_path(resource_dir.as_deref(), language)
 }
 
-fn calculate_partial_match(ocr_text: &str, event_text: &str) -> f32 {
-    let ocr_words: Vec<&str> = ocr_text.split_whitespace().collect();
-    let event_words: Vec<&str> = event_text.split_whitespace().collect();
-    
-    if ocr_words.is_empty() || event_words.is_empty() {
-        return 0.0;
+fn ensure_tessdata_path(engine: &OcrEngine, app_handle: Option<&AppHandle>) {
+    if !engine.needs_tessdata_path() {
+        return;
     }
     
-    let mut total_score = 0.0_f32;
-    let mut matched_words = 0;
-    
-    for ocr_word in &ocr_words {
-        let mut best_match = 0.0_f32;
-        for event_word in &event_words {
-            let similarity = jaro_winkler(ocr_word, event_word) as f32;
-            if similarity > best_match {
-                best_match = similarity;
-            }
-        }
-        if best_match > 0.7 { // Word-level threshold
-            total_score += best_match;
-            matched_words += 1;
-        }
-    }
-    
-    if matched_words > 0 {
-        total_score / ocr_words.len() as f32
-    } else {
-        0.0
+    let tessdata_path = get_tessdata_path(app_handle, &engine.config().language);
+    if tessdata_path.is_none() {
+        info!("No tessdata path found, letting Tesseract use default search paths");
     }
+    engine.set_tessdata_path(tessdata_path);
 }
 
-fn get_tessdata_path(app_handle: Option<&AppHandle>) -> Option<String> {
-    let mut possible_paths = vec![];
+async fn perform_ocr(
+    image: &image::DynamicImage,
+    state: &AppState,
+    app_handle: Option<&AppHandle>,
+    color_key: Option<ColorKey>,
+) -> Result<OcrResult, AppError> {
+    info!("Performing OCR on captured image");
     
-    // First priority: Try bundled tessdata via Tauri resource API
-    if let Some(handle) = app_handle {
-        if let Ok(resource_path) = handle.path().resource_dir() {
-            let bundled_tessdata = resource_path.join("tessdata");
-            info!("Checking bundled tessdata path: {}", bundled_tessdata.display());
-            
-            let eng_data = bundled_tessdata.join("eng.traineddata");
-            if eng_data.exists() {
-                info!("Found bundled tessdata at: {}", bundled_tessdata.display());
-                return Some(bundled_tessdata.to_string_lossy().to_string());
-            }
-        }
-    }
+    // Resolve tessdata once - prioritize bundled version
+    ensure_tessdata_path(&state.ocr, app_handle);
     
-    // Fallback to environment variable
-    if let Ok(tessdata_prefix) = std::env::var("TESSDATA_PREFIX") {
-        possible_paths.push(tessdata_prefix);
+    // The configured pipeline, plus the extra variants when voting is enabled
+    let mut preprocess_config = state.preprocess.lock().unwrap().clone();
+    if color_key.is_some() {
+        preprocess_config.color_key = color_key;
     }
-    
-    // Add executable directory paths
-    if let Ok(exe_path) = std::env::current_exe() {
-        if let Some(exe_dir) = exe_path.parent() {
-            possible_paths.push(exe_dir.join("tessdata").to_string_lossy().to_string());
-            possible_paths.push(exe_dir.join("resources").join("tessdata").to_string_lossy().to_string());
+    let pipelines = state.variants.lock().unwrap().pipelines(&preprocess_config);
+    
+    // Preprocess and OCR the variants concurrently (blocking work happens off the async runtime)
+    let tasks: Vec<_> = pipelines
+        .into_iter()
+        .map(|(name, config)| {
+            let engine = Arc::clone(&state.ocr);
+            let image = image.clone();
+            tauri::async_runtime::spawn_blocking(move || ocr_variants::run_variant(&engine, &image, name, &config))
+        })
+        .collect();
+    
+    let mut runs = Vec::new();
+    let mut first_error = None;
+    for task in tasks {
+        match task.await {
+            Ok(Ok(run)) => runs.push(run),
+            Ok(Err(e)) => {
+                info!("OCR variant failed: {}", e);
+                first_error.get_or_insert(e);
+            }
+            Err(e) => {
+                first_error.get_or_insert(AppError::internal(format!("OCR task failed: {}", e)));
+            }
         }
     }
-    
-    // Add platform-specific paths as fallback
-    if cfg!(target_os = "windows") {
-        possible_paths.extend(vec![
-            "C:\\Program Files\\Tesseract-OCR\\tessdata".to_string(),
-            "C:\\Program Files (x86)\\Tesseract-OCR\\tessdata".to_string(),
-            "tessdata".to_string(), // Current directory
-        ]);
-    } else {
-        possible_paths.extend(vec![
-            "/usr/share/tesseract-ocr/4.00/tessdata".to_string(),
-            "/usr/share/tesseract-ocr/tessdata".to_string(),
-            "/usr/local/share/tessdata".to_string(),
-            "/opt/homebrew/share/tessdata".to_string(), // macOS Homebrew
-            "tessdata".to_string(),
-        ]);
+    if runs.is_empty() {
+        return Err(first_error.unwrap_or_else(|| AppError::internal("No OCR variant was run")));
     }
     
-    for path in possible_paths {
-        let tessdata_path = std::path::Path::new(&path);
-        let eng_data = tessdata_path.join("eng.traineddata");
-        
-        info!("Checking tessdata path: {}", tessdata_path.display());
-        
-        if eng_data.exists() {
-            info!("Found tessdata at: {}", tessdata_path.display());
-            return Some(path);
-        }
-    }
+    // Match every variant's text against events and keep the most trustworthy one
+    let word_confidence_floor = state.ocr.config().word_confidence_floor;
+    let mut texts: Vec<String> = runs.iter().map(|run| run.ocr.text.trim().to_string()).collect();
+    let mut variant_matches: Vec<Vec<EventMatch>> = runs
+        .iter()
+        .map(|run| find_event_matches(&run.match_text(word_confidence_floor), state))
+        .collect();
+    let (chosen, variant_scores) = {
+        let candidates: Vec<VariantCandidate> = runs
+            .iter()
+            .zip(&texts)
+            .zip(&variant_matches)
+            .map(|((run, text), matches)| VariantCandidate {
+                name: &run.name,
+                text,
+                ocr_confidence: run.ocr.confidence,
+                matches,
+            })
+            .collect();
+        ocr_variants::vote(&candidates)
+    };
     
-    info!("No tessdata found in any checked locations");
-    None
-}
-
-async fn perform_ocr(image: &image::DynamicImage, state: &AppState, app_handle: Option<&AppHandle>) -> Result<OcrResult, String> {
-    info!("Performing OCR on captured image");
+    let run = runs.swap_remove(chosen);
+    let extracted_text = texts.swap_remove(chosen);
+    let matched_events = variant_matches.swap_remove(chosen);
+    let confidence = run.ocr.confidence;
     
-    // Preprocess image for better OCR
-    let processed_image = preprocess_image_for_ocr(image);
+    info!("OCR completed with variant '{}'. Text length: {}, Confidence: {}", run.name, extracted_text.len(), confidence);
+    info!("Found {} matching events for text: '{}'", matched_events.len(), extracted_text);
     
     // Save processed image for debugging (optional - don't fail OCR if this fails)
+    let processed_image = run.preprocessed.image;
     if let Err(e) = save_debug_image(&processed_image, "processed_image.png") {
         info!("Warning: Could not save processed debug image: {}", e);
     }
     
-    // Save processed image to memory as PNG for Tesseract
-    let mut image_bytes = Vec::new();
-    let mut cursor = std::io::Cursor::new(&mut image_bytes);
-    
-    processed_image.write_to(&mut cursor, image::ImageFormat::Png)
-        .map_err(|e| format!("Failed to encode image: {}", e))?;
-    
-    info!("Image processed and encoded as PNG, size: {} bytes", image_bytes.len());
-    
-    // Get tessdata path - prioritize bundled version
-    let tessdata_path = get_tessdata_path(app_handle);
-    
-    if tessdata_path.is_none() {
-        info!("No tessdata path found, letting Tesseract use default search paths");
-    }
-    
-    // Initialize Tesseract with tessdata path (try both with and without explicit path)
-    let tesseract = if let Some(ref path) = tessdata_path {
-        info!("Initializing Tesseract with tessdata path: {}", path);
-        Tesseract::new(Some(path), Some("eng"))
-    } else {
-        info!("Initializing Tesseract with default paths");
-        Tesseract::new(None, Some("eng"))
-    }.map_err(|e| {
-        let base_msg = format!("Failed to initialize Tesseract: {}", e);
-        if cfg!(target_os = "windows") {
-            format!("{}. \n\nTo fix this on Windows:\n1. Install Tesseract OCR from https://github.com/UB-Mannheim/tesseract/wiki\n2. Or set TESSDATA_PREFIX environment variable to your tessdata directory\n3. Or place tessdata folder next to the application executable", base_msg)
-        } else {
-            format!("{}. Please ensure Tesseract is installed with language data", base_msg)
+    let mut debug_stages = Vec::new();
+    for (i, stage) in run.preprocessed.stages.iter().enumerate() {
+        let filename = format!("stage_{:02}_{}.png", i, stage.name);
+        if let Err(e) = save_debug_image(&stage.image, &filename) {
+            info!("Warning: Could not save debug stage {}: {}", stage.name, e);
         }
-    })?;
-    
-    // Configure Tesseract for better text recognition (chain the method calls)
-    let tesseract = tesseract
-        .set_variable("tessedit_char_whitelist", "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789 .,!?'-:()[]{}\"")
-        .map_err(|e| format!("Failed to set character whitelist: {}", e))?;
-    
-    // Use automatic page segmentation - let Tesseract decide
-    let tesseract = tesseract
-        .set_variable("tessedit_pageseg_mode", "3") // Fully automatic page segmentation
-        .map_err(|e| format!("Failed to set page segmentation mode: {}", e))?;
-    
-    // Set image from memory (PNG format)
-    let mut tesseract = tesseract.set_image_from_mem(&image_bytes)
-        .map_err(|e| format!("Failed to set image: {}", e))?;
-    
-    // Get text and confidence
-    let text = tesseract
-        .get_text()
-        .map_err(|e| format!("Failed to extract text: {}", e))?;
-    
-    let confidence = tesseract.mean_text_conf() as f32;
-    
-    info!("OCR completed. Text length: {}, Confidence: {}", text.len(), confidence);
-    
-    // Match OCR text against events
-    let extracted_text = text.trim().to_string();
-    let matched_events = match_events_with_text(&extracted_text, &state.events);
-    
-    info!("Found {} matching events for text: '{}'", matched_events.len(), extracted_text);
+        if let Ok(image) = image_to_base64(&stage.image) {
+            debug_stages.push(DebugStage { name: stage.name.clone(), image });
+        }
+    }
     
     // Convert images to base64 for debug panel
     let debug_captured_image = image_to_base64(image).ok();
@@ -536,17 +353,21 @@ async fn perform_ocr(image: &image::DynamicImage, state: &AppState, app_handle:
     Ok(OcrResult {
         text: extracted_text,
         confidence,
+        lines: run.ocr.lines,
         matched_events,
+        chosen_variant: run.name,
+        variant_scores,
         debug_captured_image,
         debug_processed_image,
+        debug_stages,
     })
 }
 
 #[tauri::command]
-async fn lookup_event(extracted_text: String, state: State<'_, AppState>) -> Result<Vec<EventMatch>, String> {
+async fn lookup_event(extracted_text: String, state: State<'_, AppState>) -> Result<Vec<EventMatch>, AppError> {
     info!("Looking up events for text: {}", extracted_text);
     
-    let matched_events = match_events_with_text(&extracted_text, &state.events);
+    let matched_events = find_event_matches(&extracted_text, &state);
     
     if matched_events.is_empty() {
         info!("No events found for text: {}", extracted_text);
@@ -567,16 +388,259 @@ async fn lookup_event(extracted_text: String, state: State<'_, AppState>) -> Res
 }
 
 #[tauri::command]
-async fn lookup_event_manual(input_text: String, state: State<'_, AppState>) -> Result<Vec<EventMatch>, String> {
+async fn lookup_event_manual(input_text: String, state: State<'_, AppState>) -> Result<Vec<EventMatch>, AppError> {
     info!("Manual event lookup for text: {}", input_text);
     
-    let matched_events = match_events_with_text(&input_text, &state.events);
+    let matched_events = find_event_matches(&input_text, &state);
+    record_lookup(&state, "manual", &input_text, &matched_events);
     
     info!("Manual lookup found {} matching events for text: '{}'", matched_events.len(), input_text);
     
     Ok(matched_events)
 }
 
+#[tauri::command]
+async fn start_watch(area: CaptureArea, config: Option<WatchConfig>, state: State<'_, AppState>, app_handle: AppHandle) -> Result<WatchStatus, AppError> {
+    let config = config.unwrap_or_default();
+    if !(0.0..=1.0).contains(&config.change_threshold) {
+        return Err(AppError::invalid_input("Change threshold must be between 0.0 and 1.0"));
+    }
+    resolve_color_key(config.color_key.as_deref())?;
+    
+    Ok(state.watch.start(app_handle, area, config))
+}
+
+#[tauri::command]
+async fn stop_watch(state: State<'_, AppState>) -> Result<WatchStatus, AppError> {
+    state.watch.stop();
+    Ok(state.watch.status())
+}
+
+#[tauri::command]
+async fn get_watch_status(state: State<'_, AppState>) -> Result<WatchStatus, AppError> {
+    Ok(state.watch.status())
+}
+
+#[tauri::command]
+async fn list_displays() -> Result<Vec<DisplayGeometry>, AppError> {
+    let displays = capture::list_displays()?;
+    info!("Found {} display(s): {:?}", displays.len(), displays);
+    Ok(displays)
+}
+
+#[tauri::command]
+async fn get_capture_source(state: State<'_, AppState>) -> Result<CaptureSourceConfig, AppError> {
+    Ok(state.capture.lock().unwrap().config())
+}
+
+#[tauri::command]
+async fn set_capture_source(config: CaptureSourceConfig, state: State<'_, AppState>) -> Result<CaptureSourceConfig, AppError> {
+    info!("Switching capture source to: {:?}", config);
+    
+    let source = config.open()?;
+    let mut capture = state.capture.lock().unwrap();
+    *capture = source;
+    Ok(capture.config())
+}
+
+#[tauri::command]
+async fn get_ocr_config(state: State<'_, AppState>) -> Result<OcrConfig, AppError> {
+    Ok(state.ocr.config())
+}
+
+#[tauri::command]
+async fn set_ocr_config(config: OcrConfig, state: State<'_, AppState>, app_handle: AppHandle) -> Result<OcrConfig, AppError> {
+    info!("Updating OCR config: {:?}", config);
+    
+    config.validate()?;
+    let previous = state.ocr.config();
+    state.ocr.set_config(config);
+    ensure_tessdata_path(&state.ocr, Some(&app_handle));
+    
+    // Initialise the new configuration right away so errors surface here,
+    // keeping the previous (working) one if it cannot be loaded
+    if let Err(e) = state.ocr.warm_up().await {
+        state.ocr.set_config(previous);
+        return Err(e);
+    }
+    Ok(state.ocr.config())
+}
+
+#[tauri::command]
+async fn get_preprocess_config(state: State<'_, AppState>) -> Result<PreprocessConfig, AppError> {
+    Ok(state.preprocess.lock().unwrap().clone())
+}
+
+#[tauri::command]
+async fn set_preprocess_config(config: PreprocessConfig, state: State<'_, AppState>) -> Result<PreprocessConfig, AppError> {
+    info!("Updating preprocessing pipeline: {:?}", config);
+    
+    config.validate()?;
+    *state.preprocess.lock().unwrap() = config.clone();
+    Ok(config)
+}
+
+#[tauri::command]
+async fn list_preprocess_presets() -> Result<Vec<PreprocessPreset>, AppError> {
+    Ok(preprocess::presets())
+}
+
+#[tauri::command]
+async fn list_color_key_presets() -> Result<Vec<ColorKeyPreset>, AppError> {
+    Ok(color_key::presets())
+}
+
+#[tauri::command]
+async fn get_ocr_variants(state: State<'_, AppState>) -> Result<VariantConfig, AppError> {
+    Ok(state.variants.lock().unwrap().clone())
+}
+
+#[tauri::command]
+async fn set_ocr_variants(config: VariantConfig, state: State<'_, AppState>) -> Result<VariantConfig, AppError> {
+    info!("Updating OCR variants: {:?}", config);
+    
+    config.validate()?;
+    *state.variants.lock().unwrap() = config.clone();
+    Ok(config)
+}
+
+#[tauri::command]
+async fn get_weight_profiles(state: State<'_, AppState>) -> Result<ProfileList, AppError> {
+    Ok(state.profiles.lock().unwrap().list().clone())
+}
+
+#[tauri::command]
+async fn save_weight_profile(profile: WeightProfile, state: State<'_, AppState>) -> Result<ProfileList, AppError> {
+    info!("Saving weight profile: {}", profile.name);
+    
+    let mut profiles = state.profiles.lock().unwrap();
+    profiles.save_profile(profile)?;
+    Ok(profiles.list().clone())
+}
+
+#[tauri::command]
+async fn set_active_weight_profile(name: String, state: State<'_, AppState>) -> Result<ProfileList, AppError> {
+    info!("Switching weight profile to: {}", name);
+    
+    let mut profiles = state.profiles.lock().unwrap();
+    profiles.set_active(&name)?;
+    Ok(profiles.list().clone())
+}
+
+#[tauri::command]
+async fn delete_weight_profile(name: String, state: State<'_, AppState>) -> Result<ProfileList, AppError> {
+    info!("Deleting weight profile: {}", name);
+    
+    let mut profiles = state.profiles.lock().unwrap();
+    profiles.delete_profile(&name)?;
+    Ok(profiles.list().clone())
+}
+
+#[tauri::command]
+async fn get_matcher_config(state: State<'_, AppState>) -> Result<MatcherConfig, AppError> {
+    Ok(state.matcher_config())
+}
+
+// Applies to the next lookup and is saved for the next start
+#[tauri::command]
+async fn set_matcher_config(config: MatcherConfig, state: State<'_, AppState>) -> Result<MatcherConfig, AppError> {
+    info!("Updating matcher config: {:?}", config);
+    
+    let mut store = state.matcher.lock().unwrap();
+    store.set(config)?;
+    Ok(store.config().clone())
+}
+
+// Reloads events.json now instead of waiting for the file watcher
+#[tauri::command]
+async fn reload_events(state: State<'_, AppState>, app_handle: AppHandle) -> Result<EventsReloaded, AppError> {
+    let events = Arc::clone(&state.events);
+    let result = tauri::async_runtime::spawn_blocking(move || events.reload())
+        .await
+        .map_err(|e| AppError::internal(format!("Reload task failed: {}", e)))?;
+    
+    notify_events_reload(&app_handle, &result);
+    result
+}
+
+// Tells the frontend about a reload, whether it swapped the events or not
+fn notify_events_reload(app_handle: &AppHandle, result: &Result<EventsReloaded, AppError>) {
+    let emitted = match result {
+        Ok(reloaded) => app_handle.emit(EVENTS_RELOADED_EVENT, reloaded),
+        Err(e) => {
+            info!("Warning: Events reload failed, keeping the loaded events: {}", e);
+            app_handle.emit(EVENTS_RELOAD_FAILED_EVENT, e)
+        }
+    };
+    if let Err(e) = emitted {
+        info!("Warning: Could not emit events reload: {}", e);
+    }
+}
+
+// Past lookups, newest first
+#[tauri::command]
+async fn get_lookup_history(limit: Option<usize>, state: State<'_, AppState>) -> Result<Vec<LookupRecord>, AppError> {
+    state.events.backend().history(limit.unwrap_or(100))
+}
+
+#[tauri::command]
+async fn get_event_notes(state: State<'_, AppState>) -> Result<Vec<EventNote>, AppError> {
+    state.events.backend().notes()
+}
+
+// An empty note removes the event's note
+#[tauri::command]
+async fn set_event_note(name: String, character_name: String, note: String, state: State<'_, AppState>) -> Result<Vec<EventNote>, AppError> {
+    let backend = state.events.backend();
+    backend.set_note(&name, &character_name, &note)?;
+    backend.notes()
+}
+
+// Stores an event layer (same format as the user layer files) and reloads the events with it
+#[tauri::command]
+async fn save_event_override(name: String, layer: serde_json::Value, state: State<'_, AppState>, app_handle: AppHandle) -> Result<EventsReloaded, AppError> {
+    info!("Saving event override: {}", name);
+    
+    let layer = serde_json::to_string_pretty(&layer).map_err(|e| AppError::invalid_input(format!("Invalid event layer: {}", e)))?;
+    state.events.backend().save_override(&name, &layer)?;
+    
+    let events = Arc::clone(&state.events);
+    let result = tauri::async_runtime::spawn_blocking(move || events.reload())
+        .await
+        .map_err(|e| AppError::internal(format!("Reload task failed: {}", e)))?;
+    
+    notify_events_reload(&app_handle, &result);
+    result
+}
+
+// Validation report for the loaded events file, or another one for maintainers
+#[tauri::command]
+async fn lint_events(path: Option<String>) -> Result<EventsReport, AppError> {
+    tauri::async_runtime::spawn_blocking(move || lint_events_json(path.as_deref().map(std::path::Path::new)))
+        .await
+        .map_err(|e| AppError::internal(format!("Lint task failed: {}", e)))?
+}
+
+#[tauri::command]
+async fn list_characters(state: State<'_, AppState>) -> Result<Vec<CharacterOption>, AppError> {
+    Ok(run_context::list_characters(&state.events.snapshot().events))
+}
+
+#[tauri::command]
+async fn get_run_context(state: State<'_, AppState>) -> Result<RunContext, AppError> {
+    Ok(state.run_context())
+}
+
+#[tauri::command]
+async fn set_run_context(context: RunContext, state: State<'_, AppState>) -> Result<RunContext, AppError> {
+    info!("Setting run context: {:?}", context);
+    
+    let events = state.events.snapshot();
+    let mut store = state.run_context.lock().unwrap();
+    store.set(context, &events.events)?;
+    Ok(store.context().clone())
+}
+
 
 
 // Removed window creation commands as they're not supported in current Tauri version
@@ -593,10 +657,56 @@ fn main() {
     
     tauri::Builder::default()
         .manage(app_state)
+        .setup(|app| {
+            // Initialise Tesseract in the background so the first capture is fast
+            let handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                let state = handle.state::<AppState>();
+                ensure_tessdata_path(&state.ocr, Some(&handle));
+                if let Err(e) = state.ocr.warm_up().await {
+                    info!("Warning: OCR warm-up failed: {}", e);
+                }
+            });
+            
+            // Pick up edits to events.json without a restart
+            let handle = app.handle().clone();
+            let events = Arc::clone(&app.state::<AppState>().events);
+            event_store::spawn_events_watcher(events, move |result| notify_events_reload(&handle, &result));
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             capture_screen_area,
             lookup_event,
-            lookup_event_manual
+            lookup_event_manual,
+            start_watch,
+            stop_watch,
+            get_watch_status,
+            list_displays,
+            get_capture_source,
+            set_capture_source,
+            get_ocr_config,
+            set_ocr_config,
+            get_preprocess_config,
+            set_preprocess_config,
+            list_preprocess_presets,
+            list_color_key_presets,
+            get_ocr_variants,
+            set_ocr_variants,
+            get_weight_profiles,
+            save_weight_profile,
+            set_active_weight_profile,
+            delete_weight_profile,
+            get_matcher_config,
+            set_matcher_config,
+            reload_events,
+            get_lookup_history,
+            get_event_notes,
+            set_event_note,
+            save_event_override,
+            lint_events,
+            list_characters,
+            get_run_context,
+            set_run_context
         ])
         .run(tauri::generate_context!())
         .expect("Error while running tauri application");
@@ -0,0 +1,498 @@
+use crate::events::{JsonEvent, Provenance};
+use crate::language;
+use crate::matcher_config::MatcherConfig;
+use crate::ocr_confusion::ConfusionTable;
+use crate::outcome::{self, ChoiceOutcome};
+use crate::run_context::RunContext;
+use crate::scoring::{self, ChoiceScore, WeightProfile};
+use crate::search_index::CandidateIndex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use strsim::jaro_winkler;
+
+// Upper bound on events passed from the index to the precise scoring
+const MAX_CANDIDATES: usize = 64;
+
+// OCR lines past this many are left out of segment matching
+const MAX_SEGMENT_LINES: usize = 12;
+
+// Enhanced OCR result with event matching, one per event
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EventMatch {
+    pub event_id: String, // See `JsonEvent::id`
+    pub event: JsonEvent,
+    pub match_confidence: f32, // Combined over the signals
+    pub match_type: String, // Field of the strongest signal: "event_name", "choice_text" or "partial_event_name"
+    pub matched_text: String,
+    pub signals: Vec<MatchSignal>, // What the confidence is made of, strongest first
+    pub choice_outcomes: Vec<ChoiceOutcome>, // Parsed effects, one entry per choice
+    pub choice_scores: Vec<ChoiceScore>, // Scored with the active weight profile
+    pub recommended_choice: Option<String>, // Choice number with the best score
+    pub provenance: Provenance, // Bundled data or the user layer files it came from
+}
+
+// One piece of OCR text matching one field of an event
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MatchSignal {
+    pub field: String, // "event_name", "choice_text" or "partial_event_name"
+    pub choice: Option<String>, // Choice number, for choice_text
+    pub text: String, // Event text that matched
+    pub ocr_text: String, // Normalised OCR line (or whole text) it matched
+    pub score: f32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SimilarityMetric {
+    #[default]
+    JaroWinkler,
+    Confusion, // Edit distance with cheap OCR confusions
+    Combined, // The better of the two
+}
+
+impl SimilarityMetric {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "jaro_winkler" | "jaro-winkler" => Some(SimilarityMetric::JaroWinkler),
+            "confusion" => Some(SimilarityMetric::Confusion),
+            "combined" => Some(SimilarityMetric::Combined),
+            _ => None,
+        }
+    }
+
+    // Similarity of normalised OCR text to normalised event text
+    pub fn score(self, confusions: &ConfusionTable, ocr: &str, expected: &str) -> f32 {
+        match self {
+            SimilarityMetric::JaroWinkler => jaro_winkler(expected, ocr) as f32,
+            SimilarityMetric::Confusion => confusions.similarity(ocr, expected),
+            SimilarityMetric::Combined => (jaro_winkler(expected, ocr) as f32).max(confusions.similarity(ocr, expected)),
+        }
+    }
+}
+
+// The matcher settings for one lookup
+struct Scorer<'a> {
+    config: &'a MatcherConfig,
+    confusions: &'a ConfusionTable,
+}
+
+impl Scorer<'_> {
+    fn similarity(&self, ocr: &str, expected: &str) -> f32 {
+        self.config.metric.score(self.confusions, ocr, expected)
+    }
+}
+
+pub fn match_events_with_text(extracted_text: &str, events: &[JsonEvent]) -> Vec<EventMatch> {
+    let scorer = Scorer { config: &MatcherConfig::default(), confusions: ConfusionTable::builtin() };
+    match_candidate_events(extracted_text, events.iter(), None, &scorer)
+}
+
+// Same as `match_events_with_text`, but only scores the events the index
+// considers plausible. Falls back to a full scan for very short queries.
+pub fn match_events_with_index<I: CandidateIndex + ?Sized>(extracted_text: &str, events: &[JsonEvent], index: &I) -> Vec<EventMatch> {
+    let scorer = Scorer { config: &MatcherConfig::default(), confusions: ConfusionTable::builtin() };
+    match_indexed_events(extracted_text, events, index, None, &scorer)
+}
+
+// Indexed lookup that filters or boosts events by the current run, with
+// the given matcher settings and OCR confusions
+pub fn match_events_in_context<I: CandidateIndex + ?Sized>(
+    extracted_text: &str,
+    events: &[JsonEvent],
+    index: &I,
+    context: &RunContext,
+    config: &MatcherConfig,
+    confusions: &ConfusionTable,
+) -> Vec<EventMatch> {
+    match_indexed_events(extracted_text, events, index, Some(context), &Scorer { config, confusions })
+}
+
+fn match_indexed_events<I: CandidateIndex + ?Sized>(
+    extracted_text: &str,
+    events: &[JsonEvent],
+    index: &I,
+    context: Option<&RunContext>,
+    scorer: &Scorer,
+) -> Vec<EventMatch> {
+    match index.candidates(extracted_text, MAX_CANDIDATES) {
+        Some(candidates) => match_candidate_events(extracted_text, candidates.iter().map(|&i| &events[i]), context, scorer),
+        None => match_candidate_events(extracted_text, events.iter(), context, scorer),
+    }
+}
+
+fn match_candidate_events<'a>(
+    extracted_text: &str,
+    events: impl Iterator<Item = &'a JsonEvent>,
+    context: Option<&RunContext>,
+    scorer: &Scorer,
+) -> Vec<EventMatch> {
+    // Clean and normalize extracted text for better matching; a capture of
+    // several lines (title and choices) is matched line by line instead
+    let segments = segment_text(extracted_text);
+
+    let mut matches: Vec<ScoredEvent> = events
+        .filter_map(|event| match_event(event, &segments, scorer))
+        .filter_map(|mut scored| {
+            if let Some(context) = context {
+                scored.confidence = context.adjust(scored.event, scored.confidence)?;
+            }
+            Some(scored)
+        })
+        .collect();
+
+    // Sort by confidence (highest first) and limit results; duplicate
+    // entries of one event in the data only take one slot
+    matches.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+    let mut seen = HashSet::new();
+    matches.retain(|scored| seen.insert(scored.event_id.clone()));
+    matches.truncate(scorer.config.max_results);
+
+    // Only the results are cloned and get their outcomes parsed
+    matches.into_iter().map(ScoredEvent::into_match).collect()
+}
+
+// The part of an event a piece of text matched
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field {
+    Name,
+    Choice(usize),
+}
+
+struct Evidence<'a> {
+    field: Field,
+    match_type: &'static str,
+    matched_text: &'a str,
+    segment: &'a str,
+    score: f32,
+}
+
+impl Evidence<'_> {
+    fn signal(&self, event: &JsonEvent) -> MatchSignal {
+        let choice = match self.field {
+            Field::Choice(i) => event.choices.get(i).map(|choice| choice.number.clone()),
+            Field::Name => None,
+        };
+        MatchSignal {
+            field: self.match_type.to_string(),
+            choice,
+            text: self.matched_text.to_string(),
+            ocr_text: self.segment.to_string(),
+            score: self.score,
+        }
+    }
+}
+
+// Everything in `event` that normalised `text` matches well enough, with
+// the field weights applied
+fn text_evidence<'a>(event: &'a JsonEvent, text: &'a str, scorer: &Scorer) -> Vec<Evidence<'a>> {
+    let mut evidence = Vec::new();
+    let config = scorer.config;
+    let threshold = config.threshold;
+
+    // Try matching against the event name in every language the data has
+    let (event_name_similarity, matched_name) = event
+        .all_names()
+        .map(|name| ((scorer.similarity(text, &normalize_text(name)) * config.name_weight).min(1.0), name))
+        .fold((0.0, event.name.as_str()), |best, candidate| if candidate.0 > best.0 { candidate } else { best });
+    if event_name_similarity >= threshold {
+        evidence.push(Evidence { field: Field::Name, match_type: "event_name", matched_text: matched_name, segment: text, score: event_name_similarity });
+    }
+
+    // Try matching against choice texts
+    for (i, choice) in event.choices.iter().enumerate() {
+        for choice_text in choice.all_texts() {
+            let choice_similarity = (scorer.similarity(text, &normalize_text(choice_text)) * config.choice_weight).min(1.0);
+            if choice_similarity >= threshold {
+                evidence.push(Evidence { field: Field::Choice(i), match_type: "choice_text", matched_text: choice_text, segment: text, score: choice_similarity });
+            }
+        }
+    }
+
+    // Try partial word matching for OCR errors
+    if event_name_similarity < threshold {
+        let (partial_similarity, matched_name) = event
+            .all_names()
+            .map(|name| ((calculate_partial_match(text, &normalize_text(name), scorer) * config.name_weight).min(1.0), name))
+            .fold((0.0, event.name.as_str()), |best, candidate| if candidate.0 > best.0 { candidate } else { best });
+        if partial_similarity >= threshold + config.partial_margin { // Higher threshold for partial matches
+            evidence.push(Evidence { field: Field::Name, match_type: "partial_event_name", matched_text: matched_name, segment: text, score: partial_similarity });
+        }
+    }
+
+    evidence
+}
+
+// Normalised lines of the OCR text, plus each pair of neighbouring lines
+// for titles and choices that wrapped. Text on one line stays one segment.
+fn segment_text(text: &str) -> Vec<String> {
+    let lines: Vec<&str> = text
+        .lines()
+        .filter(|line| !normalize_text(line).is_empty())
+        .take(MAX_SEGMENT_LINES)
+        .collect();
+    if lines.len() <= 1 {
+        return vec![normalize_text(text)];
+    }
+
+    let mut segments: Vec<String> = lines.iter().map(|line| normalize_text(line)).collect();
+    segments.extend(lines.windows(2).map(|pair| normalize_text(&pair.join("\n"))));
+    segments
+}
+
+// Matches every segment against the event and combines what they found: the
+// best match per field, each segment counting for one field only. The
+// strongest match sets the confidence, and every further one raises it, so a
+// title plus two choices outweighs a lone title match of another event.
+fn match_event<'a>(event: &'a JsonEvent, segments: &'a [String], scorer: &Scorer) -> Option<ScoredEvent<'a>> {
+    let mut best: Vec<Evidence> = Vec::new();
+    for segment in segments {
+        let strongest = text_evidence(event, segment, scorer)
+            .into_iter()
+            .max_by(|a, b| a.score.total_cmp(&b.score));
+        let Some(strongest) = strongest else {
+            continue;
+        };
+        match best.iter_mut().find(|evidence| evidence.field == strongest.field) {
+            Some(existing) if existing.score >= strongest.score => {}
+            Some(existing) => *existing = strongest,
+            None => best.push(strongest),
+        }
+    }
+
+    best.sort_by(|a, b| b.score.total_cmp(&a.score));
+    let (strongest, support) = best.split_first()?;
+    let missing = support.iter().fold(1.0, |missing, evidence| missing * (1.0 - scorer.config.support_weight * evidence.score));
+    let confidence = strongest.score + (1.0 - strongest.score) * (1.0 - missing);
+
+    Some(ScoredEvent { event, event_id: event.id(), confidence, evidence: best })
+}
+
+// A matched event before ranking, borrowing from the event and the OCR text
+struct ScoredEvent<'a> {
+    event: &'a JsonEvent,
+    event_id: String,
+    confidence: f32,
+    evidence: Vec<Evidence<'a>>, // Strongest first
+}
+
+impl ScoredEvent<'_> {
+    fn into_match(self) -> EventMatch {
+        let strongest = &self.evidence[0];
+        EventMatch {
+            event_id: self.event_id,
+            event: self.event.clone(),
+            match_confidence: self.confidence,
+            match_type: strongest.match_type.to_string(),
+            matched_text: strongest.matched_text.to_string(),
+            signals: self.evidence.iter().map(|evidence| evidence.signal(self.event)).collect(),
+            choice_outcomes: parse_choice_outcomes(self.event),
+            choice_scores: Vec::new(),
+            recommended_choice: None,
+            provenance: self.event.provenance.clone(),
+        }
+    }
+}
+
+pub fn score_event_matches(matches: &mut [EventMatch], profile: &WeightProfile) {
+    for event_match in matches.iter_mut() {
+        event_match.choice_scores = scoring::score_choices(&event_match.choice_outcomes, profile);
+        event_match.recommended_choice = scoring::recommend_choice(&event_match.choice_scores);
+    }
+}
+
+pub fn parse_choice_outcomes(event: &JsonEvent) -> Vec<ChoiceOutcome> {
+    event.choices
+        .iter()
+        .map(|choice| outcome::parse_choice_outcome(&choice.number, &choice.outcome))
+        .collect()
+}
+
+// Lowercases, folds full-width characters and drops punctuation. Spaces
+// between CJK characters are dropped too: Japanese has none, but Tesseract
+// tends to put them between every character.
+pub fn normalize_text(text: &str) -> String {
+    let cleaned: String = text
+        .chars()
+        .map(language::fold_width)
+        .flat_map(char::to_lowercase)
+        .filter(|c| c.is_alphanumeric() || c.is_whitespace())
+        .collect();
+
+    let mut normalized = String::with_capacity(cleaned.len());
+    for word in cleaned.split_whitespace() {
+        let joins_cjk = normalized.chars().last().is_some_and(language::is_cjk)
+            && word.chars().next().is_some_and(language::is_cjk);
+        if !normalized.is_empty() && !joins_cjk {
+            normalized.push(' ');
+        }
+        normalized.push_str(word);
+    }
+    normalized
+}
+
+fn calculate_partial_match(ocr_text: &str, event_text: &str, scorer: &Scorer) -> f32 {
+    // CJK text has no words to compare, so compare character pairs instead
+    if ocr_text.chars().chain(event_text.chars()).any(language::is_cjk) {
+        return bigram_overlap(ocr_text, event_text);
+    }
+
+    let ocr_words: Vec<&str> = ocr_text.split_whitespace().collect();
+    let event_words: Vec<&str> = event_text.split_whitespace().collect();
+
+    if ocr_words.is_empty() || event_words.is_empty() {
+        return 0.0;
+    }
+
+    let mut total_score = 0.0_f32;
+    let mut matched_words = 0;
+
+    for ocr_word in &ocr_words {
+        let mut best_match = 0.0_f32;
+        for event_word in &event_words {
+            let word_similarity = scorer.similarity(ocr_word, event_word);
+            if word_similarity > best_match {
+                best_match = word_similarity;
+            }
+        }
+        if best_match > scorer.config.word_threshold {
+            total_score += best_match;
+            matched_words += 1;
+        }
+    }
+
+    if matched_words > 0 {
+        total_score / ocr_words.len() as f32
+    } else {
+        0.0
+    }
+}
+
+// Dice coefficient over character bigrams, ignoring spaces
+fn bigram_overlap(a: &str, b: &str) -> f32 {
+    let bigrams = |text: &str| {
+        let chars: Vec<char> = text.chars().filter(|c| !c.is_whitespace()).collect();
+        chars.windows(2).map(|pair| (pair[0], pair[1])).collect::<Vec<_>>()
+    };
+    let a_bigrams = bigrams(a);
+    let mut b_bigrams = bigrams(b);
+    if a_bigrams.is_empty() || b_bigrams.is_empty() {
+        return 0.0;
+    }
+
+    let total = a_bigrams.len() + b_bigrams.len();
+    let mut shared = 0;
+    for bigram in a_bigrams {
+        if let Some(position) = b_bigrams.iter().position(|other| *other == bigram) {
+            b_bigrams.swap_remove(position);
+            shared += 1;
+        }
+    }
+    2.0 * shared as f32 / total as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::Choice;
+    use crate::run_context::ContextMode;
+    use crate::search_index::SearchIndex;
+
+    fn event(name: &str, character_name: &str, relation_type: &str, choices: &[&str]) -> JsonEvent {
+        JsonEvent {
+            name: name.to_string(),
+            names: Default::default(),
+            character_name: character_name.to_string(),
+            relation_type: relation_type.to_string(),
+            choices: choices
+                .iter()
+                .enumerate()
+                .map(|(i, text)| Choice {
+                    text: text.to_string(),
+                    texts: Default::default(),
+                    number: (i + 1).to_string(),
+                    outcome: "10 Energy".to_string(),
+                })
+                .collect(),
+            provenance: Default::default(),
+        }
+    }
+
+    fn events() -> Vec<JsonEvent> {
+        vec![
+            event("At Summer Camp (Year 2)", "Vodka", "Umamusume", &["Beach running, please.", "Swimming, please."]),
+            event("At Summer Camp (Year 2)", "Agnes Tachyon", "Umamusume", &["Beach running, please.", "Rest, please."]),
+            event("At Summer Camp (Year 2)", "Agnes Tachyon", "Umamusume", &["Beach running, please.", "Rest, please."]),
+            event("Hamburger Helper!", "Kitasan Black", "Support Card", &["Can't let them go to waste, right?"]),
+        ]
+    }
+
+    #[test]
+    fn multi_line_captures_combine_evidence() {
+        let events = events();
+        let matches = match_events_with_text("At Sumrner Carnp (Year 2)\nBeach runing, please\nRest, please.", &events);
+
+        // The duplicate entry only takes one slot; the choices it shows
+        // rank it above the event with the same title
+        let names: Vec<&str> = matches.iter().map(|event_match| event_match.event.character_name.as_str()).collect();
+        assert_eq!(names[..2], ["Agnes Tachyon", "Vodka"]);
+        assert!(matches[0].match_confidence > matches[1].match_confidence);
+        assert_eq!(matches[0].signals.len(), 3);
+        assert_eq!(matches[0].choice_outcomes.len(), 2);
+        assert_ne!(matches[0].event_id, matches[1].event_id);
+    }
+
+    #[test]
+    fn run_context_filters_before_ranking() {
+        let events = events();
+        let index = SearchIndex::build(&events);
+        let context = RunContext { trainee: Some("Vodka".to_string()), support_cards: Vec::new(), mode: ContextMode::Filter };
+        let config = MatcherConfig::default();
+        let confusions = ConfusionTable::builtin();
+
+        let matches = match_events_in_context("At Summer Camp (Year 2)", &events, &index, &context, &config, confusions);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].event.character_name, "Vodka");
+        assert_eq!(matches[0].match_confidence, 1.0);
+    }
+
+    #[test]
+    fn results_are_limited_by_the_config() {
+        let events = events();
+        let index = SearchIndex::build(&events);
+        let config = MatcherConfig { max_results: 1, metric: SimilarityMetric::Combined, ..MatcherConfig::default() };
+
+        let context = RunContext::default();
+
+        let matches = match_events_in_context("At Surnmer Carnp", &events, &index, &context, &config, ConfusionTable::builtin());
+        assert_eq!(matches.len(), 1);
+        assert!(matches[0].event.name.starts_with("At Summer Camp"));
+        assert!(match_events_with_text("zzzz qqqq", &events).is_empty());
+    }
+
+    #[test]
+    fn normalizes_full_width_and_cjk_text() {
+        assert_eq!(normalize_text("Ｈａｍｂｕｒｇｅｒ　Ｈｅｌｐｅｒ！"), "hamburger helper");
+
+        // Tesseract's spaces between CJK characters are dropped, and the text
+        // stays one segment instead of being split into words
+        assert_eq!(normalize_text("夏 合 宿（2年目）"), "夏合宿2年目");
+        assert_eq!(normalize_text("Vodka の 夏合宿"), "vodka の夏合宿");
+        assert_eq!(segment_text("夏 合 宿 の 思 い 出"), ["夏合宿の思い出"]);
+    }
+
+    #[test]
+    fn cjk_partial_matches_compare_bigrams() {
+        let scorer = Scorer { config: &MatcherConfig::default(), confusions: ConfusionTable::builtin() };
+        let title = "夏合宿2年目";
+
+        // One misread character (目 as 日) still shares most character pairs
+        let slip = calculate_partial_match("夏合宿2年日", title, &scorer);
+        let unrelated = calculate_partial_match("夏合宿2年日", "お正月", &scorer);
+        assert_eq!(slip, bigram_overlap("夏合宿2年日", title));
+        assert!((slip - 0.8).abs() < 1e-6);
+        assert_eq!(unrelated, 0.0);
+
+        assert_eq!(bigram_overlap(title, title), 1.0);
+        assert_eq!(bigram_overlap("夏", title), 0.0);
+    }
+}
@@ -0,0 +1,95 @@
+// Thresholds and weights of the event matcher, saved in the config
+// directory and adjustable while the app runs.
+
+use crate::error::AppError;
+use crate::matcher::SimilarityMetric;
+use crate::settings_file;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+pub const MATCHER_CONFIG_FILE: &str = "matcher_config.json";
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct MatcherConfig {
+    pub threshold: f32, // Minimum (weighted) similarity for a field to match
+    pub partial_margin: f32, // Partial name matches need threshold + this
+    pub word_threshold: f32, // Minimum similarity of a word in partial name matching
+    pub max_results: usize,
+    pub name_weight: f32, // Scales event name similarity
+    pub choice_weight: f32, // Scales choice text similarity
+    pub support_weight: f32, // Share of the gap to 1 each further matched field closes
+    pub metric: SimilarityMetric,
+}
+
+impl Default for MatcherConfig {
+    fn default() -> Self {
+        MatcherConfig {
+            threshold: 0.6,
+            partial_margin: 0.1,
+            word_threshold: 0.7,
+            max_results: 5,
+            name_weight: 1.0,
+            choice_weight: 1.0,
+            support_weight: 0.5,
+            metric: SimilarityMetric::default(),
+        }
+    }
+}
+
+impl MatcherConfig {
+    pub fn validate(&self) -> Result<(), AppError> {
+        let fractions = [
+            ("threshold", self.threshold),
+            ("partial_margin", self.partial_margin),
+            ("word_threshold", self.word_threshold),
+            ("support_weight", self.support_weight),
+        ];
+        for (name, value) in fractions {
+            if !(0.0..=1.0).contains(&value) {
+                return Err(AppError::invalid_input(format!("{} must be between 0 and 1", name)));
+            }
+        }
+        for (name, value) in [("name_weight", self.name_weight), ("choice_weight", self.choice_weight)] {
+            if !(0.0..=2.0).contains(&value) {
+                return Err(AppError::invalid_input(format!("{} must be between 0 and 2", name)));
+            }
+        }
+        if !(1..=50).contains(&self.max_results) {
+            return Err(AppError::invalid_input("max_results must be between 1 and 50"));
+        }
+        Ok(())
+    }
+
+    // A config file given explicitly (e.g. to the CLI): unlike the saved
+    // config, an unreadable or invalid file is an error
+    pub fn read(path: &Path) -> Result<MatcherConfig, AppError> {
+        let config: MatcherConfig = settings_file::read(path, "matcher config")?;
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+pub struct MatcherConfigStore {
+    path: Option<PathBuf>,
+    config: MatcherConfig,
+}
+
+impl MatcherConfigStore {
+    pub fn load(path: Option<PathBuf>) -> Self {
+        let config = settings_file::load(path.as_deref(), "matcher config", MatcherConfig::validate).unwrap_or_default();
+        MatcherConfigStore { path, config }
+    }
+
+    pub fn config(&self) -> &MatcherConfig {
+        &self.config
+    }
+
+    // Only a saved config becomes active
+    pub fn set(&mut self, config: MatcherConfig) -> Result<(), AppError> {
+        config.validate()?;
+        settings_file::save(self.path.as_deref(), "matcher config", &config)?;
+        self.config = config;
+        Ok(())
+    }
+}
@@ -0,0 +1,245 @@
+// Evaluates the matcher on labelled OCR text at a range of thresholds: how
+// often the top match is the right event (precision) and how many of the
+// labelled events are found (recall), to choose `MatcherConfig` settings.
+
+use crate::error::AppError;
+use crate::events::JsonEvent;
+use crate::matcher::{match_events_in_context, EventMatch};
+use crate::matcher_config::MatcherConfig;
+use crate::ocr_confusion::ConfusionTable;
+use crate::run_context::RunContext;
+use crate::search_index::CandidateIndex;
+use serde::Serialize;
+
+// Thresholds evaluated when none are given: 0.50 to 0.95
+pub const DEFAULT_THRESHOLDS: &[f32] = &[0.5, 0.55, 0.6, 0.65, 0.7, 0.75, 0.8, 0.85, 0.9, 0.95];
+
+#[derive(Debug, Serialize, Clone)]
+pub struct LabelledSample {
+    pub text: String, // OCR output
+    pub expected: Option<String>, // Event name or ID; None when nothing should match
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ThresholdResult {
+    pub threshold: f32,
+    pub correct: usize, // Top match was the expected event
+    pub wrong: usize, // Top match was another event, or one where none was expected
+    pub missed: usize, // Expected event was not the top match
+    pub precision: Option<f32>, // None when nothing matched at all
+    pub recall: f32,
+    pub f1: f32,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct TuneMiss {
+    pub text: String,
+    pub expected: Option<String>,
+    pub matched: Option<String>, // Top match, if any
+    pub confidence: Option<f32>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct TuneReport {
+    pub samples: usize,
+    pub positives: usize, // Samples with an expected event
+    pub results: Vec<ThresholdResult>,
+    pub best_threshold: Option<f32>, // Highest F1
+    pub misses: Vec<TuneMiss>, // Wrong or missed samples at the best threshold
+}
+
+// One sample per line: OCR text, a tab, then the expected event name or ID
+// (empty or "-" when no event should match). "\n" in the OCR text stands
+// for a line break.
+pub fn parse_labelled(content: &str) -> Result<Vec<LabelledSample>, AppError> {
+    let mut samples = Vec::new();
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (text, expected) = line.split_once('\t').unwrap_or((line, ""));
+        if text.trim().is_empty() {
+            return Err(AppError::invalid_input(format!("line {}: no OCR text", number + 1)));
+        }
+
+        let expected = expected.trim();
+        samples.push(LabelledSample {
+            text: text.replace("\\n", "\n"),
+            expected: (!expected.is_empty() && expected != "-").then(|| expected.to_string()),
+        });
+    }
+    Ok(samples)
+}
+
+fn is_expected(event_match: &EventMatch, expected: &str) -> bool {
+    event_match.event_id == expected || event_match.event.name.trim().eq_ignore_ascii_case(expected)
+}
+
+// Runs every sample at every threshold (in ascending order), the rest of
+// `config` unchanged
+pub fn tune<I: CandidateIndex + ?Sized>(
+    samples: &[LabelledSample],
+    events: &[JsonEvent],
+    index: &I,
+    config: &MatcherConfig,
+    confusions: &ConfusionTable,
+    thresholds: &[f32],
+) -> TuneReport {
+    let positives = samples.iter().filter(|sample| sample.expected.is_some()).count();
+    let mut results = Vec::new();
+    let mut misses_by_threshold = Vec::new();
+    let mut thresholds = thresholds.to_vec();
+    thresholds.sort_by(f32::total_cmp);
+    thresholds.dedup();
+
+    for threshold in thresholds {
+        let config = MatcherConfig { threshold, ..config.clone() };
+        let (mut correct, mut wrong, mut missed) = (0, 0, 0);
+        let mut misses = Vec::new();
+
+        for sample in samples {
+            let matches = match_events_in_context(&sample.text, events, index, &RunContext::default(), &config, confusions);
+            let top = matches.first();
+            let right = match (&sample.expected, top) {
+                (Some(expected), Some(top)) if is_expected(top, expected) => true,
+                (Some(_), Some(_)) => {
+                    wrong += 1;
+                    missed += 1;
+                    false
+                }
+                (Some(_), None) => {
+                    missed += 1;
+                    false
+                }
+                (None, Some(_)) => {
+                    wrong += 1;
+                    false
+                }
+                (None, None) => true,
+            };
+            if right {
+                correct += usize::from(sample.expected.is_some());
+                continue;
+            }
+            misses.push(TuneMiss {
+                text: sample.text.clone(),
+                expected: sample.expected.clone(),
+                matched: top.map(|top| top.event.name.clone()),
+                confidence: top.map(|top| top.match_confidence),
+            });
+        }
+
+        let precision = (correct + wrong > 0).then(|| correct as f32 / (correct + wrong) as f32);
+        let recall = if positives > 0 { correct as f32 / positives as f32 } else { 0.0 };
+        let f1 = match precision {
+            Some(precision) if precision + recall > 0.0 => 2.0 * precision * recall / (precision + recall),
+            _ => 0.0,
+        };
+        results.push(ThresholdResult { threshold, correct, wrong, missed, precision, recall, f1 });
+        misses_by_threshold.push(misses);
+    }
+
+    // Ties go to the lower threshold, which finds more
+    let best = results
+        .iter()
+        .enumerate()
+        .filter(|(_, result)| result.f1 > 0.0)
+        .max_by(|(_, a), (_, b)| a.f1.total_cmp(&b.f1).then_with(|| b.threshold.total_cmp(&a.threshold)))
+        .map(|(i, _)| i);
+
+    TuneReport {
+        samples: samples.len(),
+        positives,
+        best_threshold: best.map(|i| results[i].threshold),
+        misses: best.map(|i| misses_by_threshold.swap_remove(i)).unwrap_or_default(),
+        results,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::Choice;
+    use crate::search_index::SearchIndex;
+
+    fn event(name: &str, choice: &str) -> JsonEvent {
+        JsonEvent {
+            name: name.to_string(),
+            names: Default::default(),
+            character_name: String::new(),
+            relation_type: String::new(),
+            choices: vec![Choice {
+                text: choice.to_string(),
+                texts: Default::default(),
+                number: "1".to_string(),
+                outcome: "10 Energy".to_string(),
+            }],
+            provenance: Default::default(),
+        }
+    }
+
+    fn run(samples: &str, thresholds: &[f32]) -> TuneReport {
+        let events = vec![
+            event("Hamburger Helper!", "Can't let them go to waste, right?"),
+            event("Medicine That Makes You Faster?", "Drink it right away"),
+        ];
+        let index = SearchIndex::build(&events);
+        let samples = parse_labelled(samples).unwrap();
+        tune(&samples, &events, &index, &MatcherConfig::default(), ConfusionTable::builtin(), thresholds)
+    }
+
+    #[test]
+    fn parses_labelled_lines() {
+        let samples = parse_labelled("# comment\nHamburger Helper!\tHamburger Helper!\r\n\nline one\\nline two\t-\nno label\n").unwrap();
+        assert_eq!(samples.len(), 3);
+        assert_eq!(samples[0].expected.as_deref(), Some("Hamburger Helper!"));
+        assert_eq!(samples[1].text, "line one\nline two");
+        assert_eq!(samples[1].expected, None);
+        assert_eq!(samples[2].expected, None);
+
+        let error = parse_labelled("ok\tA\n\tB\n").unwrap_err().to_string();
+        assert!(error.contains("line 2: no OCR text"), "{}", error);
+    }
+
+    #[test]
+    fn counts_precision_recall_and_f1() {
+        let report = run(
+            "Hamburger Helper!\thamburger helper!\n\
+             Medicine That Makes You Faster?\tHamburger Helper!\n\
+             zzzz qqqq\tMedicine That Makes You Faster?\n\
+             Hamburgr Helpr\t-\n\
+             xxxx yyyy\t-\n",
+            &[0.6],
+        );
+        assert_eq!((report.samples, report.positives), (5, 3));
+
+        let result = &report.results[0];
+        // Right, wrong event, nothing found, match where none was expected, right nothing
+        assert_eq!((result.correct, result.wrong, result.missed), (1, 2, 2));
+        assert_eq!(result.precision, Some(1.0 / 3.0));
+        assert_eq!(result.recall, 1.0 / 3.0);
+        assert!((result.f1 - 1.0 / 3.0).abs() < 1e-6);
+        assert_eq!(report.best_threshold, Some(0.6));
+        assert_eq!(report.misses.len(), 3);
+    }
+
+    #[test]
+    fn picks_the_lowest_best_threshold() {
+        // Exact matches score 1 at every threshold, so F1 ties everywhere
+        let report = run("Hamburger Helper!\tHamburger Helper!\n", &[0.9, 0.5, 0.7, 0.5]);
+        let thresholds: Vec<f32> = report.results.iter().map(|result| result.threshold).collect();
+        assert_eq!(thresholds, [0.5, 0.7, 0.9]);
+        assert_eq!(report.best_threshold, Some(0.5));
+        assert!(report.misses.is_empty());
+    }
+
+    #[test]
+    fn no_best_threshold_without_correct_matches() {
+        let report = run("zzzz qqqq\tHamburger Helper!\n", &[0.6]);
+        assert_eq!(report.results[0].precision, None);
+        assert_eq!(report.results[0].f1, 0.0);
+        assert_eq!(report.best_threshold, None);
+        assert!(report.misses.is_empty());
+    }
+}
@@ -0,0 +1,447 @@
+// OCR-aware string similarity: an edit distance where the substitutions
+// Tesseract commonly makes ("rn" for "m", "0" for "o", "5" for "s", ...)
+// cost less than other edits. The confusion table can be learned from
+// pairs of OCR output and the text it should have been.
+
+use crate::error::AppError;
+use crate::matcher::normalize_text;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tracing::info;
+
+// Learned confusions in the config directory, used on top of the built-in ones
+pub const OCR_CONFUSIONS_FILE: &str = "ocr_confusions.json";
+
+// Longest run of characters a confusion can cover on either side
+const MAX_CONFUSION_CHARS: usize = 3;
+
+// Learned costs never go below this, so a confusion stays an edit
+const MIN_COST: f32 = 0.1;
+
+// Built-in Tesseract confusions on normalised (lowercased) text: what OCR
+// read, what the text said, and the cost of that edit
+const DEFAULT_CONFUSIONS: &[(&str, &str, f32)] = &[
+    ("rn", "m", 0.3),
+    ("m", "rn", 0.4),
+    ("nn", "m", 0.5),
+    ("in", "m", 0.5),
+    ("ri", "n", 0.5),
+    ("cl", "d", 0.4),
+    ("d", "cl", 0.5),
+    ("vv", "w", 0.3),
+    ("w", "vv", 0.5),
+    ("ii", "u", 0.5),
+    ("li", "h", 0.5),
+    ("l", "i", 0.3),
+    ("i", "l", 0.3),
+    ("1", "l", 0.3),
+    ("1", "i", 0.3),
+    ("l", "1", 0.4),
+    ("i", "1", 0.4),
+    ("j", "i", 0.5),
+    ("0", "o", 0.2),
+    ("o", "0", 0.3),
+    ("5", "s", 0.3),
+    ("s", "5", 0.4),
+    ("8", "b", 0.4),
+    ("b", "8", 0.5),
+    ("6", "b", 0.5),
+    ("2", "z", 0.5),
+    ("z", "2", 0.5),
+    ("9", "g", 0.5),
+    ("g", "q", 0.5),
+    ("q", "g", 0.5),
+    ("u", "v", 0.5),
+    ("v", "u", 0.5),
+    ("c", "e", 0.5),
+    ("e", "c", 0.5),
+    ("h", "b", 0.5),
+    ("t", "f", 0.5),
+    ("f", "t", 0.5),
+];
+
+// Characters OCR read, the characters they stand for, and the cost
+type Rule = (Vec<char>, Vec<char>, f32);
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Confusion {
+    pub ocr: String, // What Tesseract read
+    pub expected: String, // What the text actually said
+    pub cost: f32, // 0 (free) to 1 (an ordinary edit)
+}
+
+// Confusions keyed by the first character OCR read, for the distance
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(from = "Vec<Confusion>", into = "Vec<Confusion>")]
+pub struct ConfusionTable {
+    entries: Vec<Confusion>,
+    rules: HashMap<char, Vec<Rule>>,
+}
+
+impl From<Vec<Confusion>> for ConfusionTable {
+    fn from(entries: Vec<Confusion>) -> Self {
+        let mut table = ConfusionTable { entries: Vec::new(), rules: HashMap::new() };
+        table.merge(entries);
+        table
+    }
+}
+
+impl From<ConfusionTable> for Vec<Confusion> {
+    fn from(table: ConfusionTable) -> Self {
+        table.entries
+    }
+}
+
+impl Default for ConfusionTable {
+    fn default() -> Self {
+        DEFAULT_CONFUSIONS
+            .iter()
+            .map(|&(ocr, expected, cost)| Confusion { ocr: ocr.to_string(), expected: expected.to_string(), cost })
+            .collect::<Vec<_>>()
+            .into()
+    }
+}
+
+impl ConfusionTable {
+    // The built-in table, built once
+    pub fn builtin() -> &'static ConfusionTable {
+        static BUILTIN: OnceLock<ConfusionTable> = OnceLock::new();
+        BUILTIN.get_or_init(ConfusionTable::default)
+    }
+
+    // The built-in table with the confusions saved at `path` (e.g. learned
+    // ones) on top. A missing or invalid file leaves the built-in table.
+    pub fn load(path: Option<PathBuf>) -> Self {
+        let mut table = ConfusionTable::default();
+        let Some(content) = path.and_then(|path| std::fs::read_to_string(path).ok()) else {
+            return table;
+        };
+
+        match serde_json::from_str::<Vec<Confusion>>(&content) {
+            Ok(entries) => {
+                info!("Loaded {} OCR confusions", entries.len());
+                table.merge(entries);
+            }
+            Err(e) => info!("Ignoring invalid OCR confusions file: {}", e),
+        }
+        table
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), AppError> {
+        let save_failed = |reason: String| AppError::SaveFailed { path: path.display().to_string(), reason };
+        let json = serde_json::to_string_pretty(&self.entries).map_err(|e| save_failed(e.to_string()))?;
+        std::fs::write(path, json).map_err(|e| save_failed(e.to_string()))
+    }
+
+    pub fn entries(&self) -> &[Confusion] {
+        &self.entries
+    }
+
+    // Adds confusions, replacing the cost of ones already in the table.
+    // Entries are normalised like the matched text; empty ones and ones
+    // without a finite cost are dropped.
+    pub fn merge(&mut self, entries: impl IntoIterator<Item = Confusion>) {
+        for entry in entries {
+            if !entry.cost.is_finite() {
+                info!("Warning: Ignoring OCR confusion {:?} -> {:?} without a finite cost", entry.ocr, entry.expected);
+                continue;
+            }
+            let ocr: Vec<char> = normalize_text(&entry.ocr).chars().collect();
+            let expected: Vec<char> = normalize_text(&entry.expected).chars().collect();
+            if ocr.is_empty() || expected.is_empty() || ocr == expected {
+                continue;
+            }
+            let cost = entry.cost.clamp(0.0, 1.0);
+
+            let rules = self.rules.entry(ocr[0]).or_default();
+            match rules.iter_mut().find(|rule| rule.0 == ocr && rule.1 == expected) {
+                Some(rule) => rule.2 = cost,
+                None => rules.push((ocr.clone(), expected.clone(), cost)),
+            }
+
+            let entry = Confusion { ocr: ocr.into_iter().collect(), expected: expected.into_iter().collect(), cost };
+            match self.entries.iter_mut().find(|existing| existing.ocr == entry.ocr && existing.expected == entry.expected) {
+                Some(existing) => existing.cost = cost,
+                None => self.entries.push(entry),
+            }
+        }
+    }
+
+    // Edit distance from normalised OCR text to normalised expected text:
+    // insertions, deletions and substitutions cost 1, table confusions their cost
+    pub fn distance(&self, ocr: &str, expected: &str) -> f32 {
+        let ocr: Vec<char> = ocr.chars().collect();
+        let expected: Vec<char> = expected.chars().collect();
+        let width = expected.len() + 1;
+        let mut costs = vec![f32::INFINITY; (ocr.len() + 1) * width];
+        costs[0] = 0.0;
+
+        // Every edit moves forward, so relaxing cells in order is enough
+        for i in 0..=ocr.len() {
+            for j in 0..=expected.len() {
+                let cost = costs[i * width + j];
+                let mut relax = |to_i: usize, to_j: usize, step: f32| {
+                    let cell = &mut costs[to_i * width + to_j];
+                    if cost + step < *cell {
+                        *cell = cost + step;
+                    }
+                };
+
+                if i < ocr.len() {
+                    relax(i + 1, j, 1.0);
+                }
+                if j < expected.len() {
+                    relax(i, j + 1, 1.0);
+                }
+                if i < ocr.len() && j < expected.len() {
+                    relax(i + 1, j + 1, if ocr[i] == expected[j] { 0.0 } else { 1.0 });
+                }
+                let Some(rules) = ocr.get(i).and_then(|c| self.rules.get(c)) else {
+                    continue;
+                };
+                for (from, to, step) in rules {
+                    if ocr[i..].starts_with(from) && expected[j..].starts_with(to) {
+                        relax(i + from.len(), j + to.len(), *step);
+                    }
+                }
+            }
+        }
+        costs[ocr.len() * width + expected.len()]
+    }
+
+    // 1 for equal text down to 0, scaled by the longer text
+    pub fn similarity(&self, ocr: &str, expected: &str) -> f32 {
+        let longest = ocr.chars().count().max(expected.chars().count());
+        if longest == 0 {
+            return 1.0;
+        }
+        (1.0 - self.distance(ocr, expected) / longest as f32).max(0.0)
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct LearnedConfusion {
+    pub ocr: String,
+    pub expected: String,
+    pub count: usize, // Times OCR made this edit in the corpus
+    pub opportunities: usize, // Times the expected text occurs in the corpus
+    pub cost: f32,
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct LearnReport {
+    pub pairs: usize,
+    pub exact: usize, // Pairs OCR got right
+    pub skipped: usize, // Pairs too different to be the same text
+    pub confusions: Vec<LearnedConfusion>, // Most frequent first
+}
+
+impl LearnReport {
+    pub fn table(&self) -> ConfusionTable {
+        self.confusions
+            .iter()
+            .map(|learned| Confusion { ocr: learned.ocr.clone(), expected: learned.expected.clone(), cost: learned.cost })
+            .collect::<Vec<_>>()
+            .into()
+    }
+}
+
+// Learns confusions from (OCR output, correct text) pairs: each pair is
+// aligned, runs of edits become candidate confusions, and a confusion seen
+// `min_count` times gets a cost of 1 minus how often the expected text was
+// misread that way.
+pub fn learn_confusions(pairs: &[(String, String)], min_count: usize) -> LearnReport {
+    let mut report = LearnReport { pairs: pairs.len(), ..LearnReport::default() };
+    let mut counts: BTreeMap<(String, String), usize> = BTreeMap::new();
+    let mut expected_texts = Vec::new();
+
+    for (ocr, expected) in pairs {
+        let ocr = normalize_text(ocr);
+        let expected = normalize_text(expected);
+        if ocr == expected {
+            report.exact += 1;
+            expected_texts.push(expected);
+            continue;
+        }
+
+        // More than half the text edited is a different text, not a misread
+        let edits = align(&ocr, &expected);
+        let edited: usize = edits.iter().map(|(from, to)| from.chars().count().max(to.chars().count())).sum();
+        if edited * 2 > expected.chars().count().max(1) {
+            report.skipped += 1;
+            continue;
+        }
+        for edit in edits {
+            let too_long = edit.0.chars().count() > MAX_CONFUSION_CHARS || edit.1.chars().count() > MAX_CONFUSION_CHARS;
+            if !edit.0.is_empty() && !edit.1.is_empty() && !too_long {
+                *counts.entry(edit).or_default() += 1;
+            }
+        }
+        expected_texts.push(expected);
+    }
+
+    for ((ocr, expected), count) in counts {
+        if count < min_count.max(1) {
+            continue;
+        }
+        let opportunities = expected_texts.iter().map(|text| occurrences(text, &expected)).sum::<usize>().max(count);
+        let cost = (1.0 - count as f32 / opportunities as f32).max(MIN_COST);
+        report.confusions.push(LearnedConfusion { ocr, expected, count, opportunities, cost });
+    }
+    report.confusions.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.cost.total_cmp(&b.cost)));
+    report
+}
+
+// Runs of consecutive edits in a Levenshtein alignment, as (OCR text, expected text)
+fn align(ocr: &str, expected: &str) -> Vec<(String, String)> {
+    let ocr: Vec<char> = ocr.chars().collect();
+    let expected: Vec<char> = expected.chars().collect();
+    let width = expected.len() + 1;
+    let mut distances = vec![0usize; (ocr.len() + 1) * width];
+    for i in 0..=ocr.len() {
+        for j in 0..=expected.len() {
+            distances[i * width + j] = match (i, j) {
+                (0, _) => j,
+                (_, 0) => i,
+                _ => {
+                    let substitution = distances[(i - 1) * width + j - 1] + usize::from(ocr[i - 1] != expected[j - 1]);
+                    let deletion = distances[(i - 1) * width + j] + 1;
+                    let insertion = distances[i * width + j - 1] + 1;
+                    substitution.min(deletion).min(insertion)
+                }
+            };
+        }
+    }
+
+    // Walk back from the end, collecting edited characters until a match
+    let mut edits = Vec::new();
+    let (mut from, mut to) = (Vec::new(), Vec::new());
+    let (mut i, mut j) = (ocr.len(), expected.len());
+    while i > 0 || j > 0 {
+        let distance = distances[i * width + j];
+        if i > 0 && j > 0 && ocr[i - 1] == expected[j - 1] && distance == distances[(i - 1) * width + j - 1] {
+            push_edit(&mut edits, &mut from, &mut to);
+            i -= 1;
+            j -= 1;
+        } else if i > 0 && j > 0 && distance == distances[(i - 1) * width + j - 1] + 1 {
+            from.push(ocr[i - 1]);
+            to.push(expected[j - 1]);
+            i -= 1;
+            j -= 1;
+        } else if i > 0 && distance == distances[(i - 1) * width + j] + 1 {
+            from.push(ocr[i - 1]);
+            i -= 1;
+        } else {
+            to.push(expected[j - 1]);
+            j -= 1;
+        }
+    }
+    push_edit(&mut edits, &mut from, &mut to);
+    edits
+}
+
+fn push_edit(edits: &mut Vec<(String, String)>, from: &mut Vec<char>, to: &mut Vec<char>) {
+    if from.is_empty() && to.is_empty() {
+        return;
+    }
+    // Collected back to front
+    edits.push((from.drain(..).rev().collect(), to.drain(..).rev().collect()));
+}
+
+fn occurrences(text: &str, pattern: &str) -> usize {
+    (0..text.len()).filter(|&start| text.is_char_boundary(start) && text[start..].starts_with(pattern)).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(items: &[(&str, &str)]) -> Vec<(String, String)> {
+        items.iter().map(|&(ocr, expected)| (ocr.to_string(), expected.to_string())).collect()
+    }
+
+    #[test]
+    fn confusions_cost_less_than_edits() {
+        let table = ConfusionTable::default();
+        assert_eq!(table.distance("rn", "m"), 0.3);
+        assert_eq!(table.distance("c0rner", "corner"), 0.2);
+        assert_eq!(table.distance("abc", "abc"), 0.0);
+        assert_eq!(table.distance("x", "y"), 1.0);
+        assert_eq!(table.distance("", "abc"), 3.0);
+        assert!(table.similarity("rnood", "mood") > table.similarity("xood", "mood"));
+    }
+
+    #[test]
+    fn merge_normalises_and_rejects_bad_costs() {
+        let mut table = ConfusionTable::default();
+        let before = table.entries().len();
+        table.merge([
+            Confusion { ocr: "X".to_string(), expected: "y".to_string(), cost: f32::NAN },
+            Confusion { ocr: "q".to_string(), expected: "p".to_string(), cost: f32::INFINITY },
+            Confusion { ocr: "a".to_string(), expected: "a".to_string(), cost: 0.5 },
+            Confusion { ocr: "".to_string(), expected: "a".to_string(), cost: 0.5 },
+        ]);
+        assert_eq!(table.entries().len(), before);
+
+        table.merge([
+            Confusion { ocr: "RN".to_string(), expected: "M".to_string(), cost: 0.1 },
+            Confusion { ocr: "x".to_string(), expected: "y".to_string(), cost: 7.0 },
+        ]);
+        assert_eq!(table.entries().len(), before + 1);
+        assert_eq!(table.distance("rn", "m"), 0.1);
+        assert_eq!(table.distance("x", "y"), 1.0);
+    }
+
+    #[test]
+    fn align_coalesces_runs_of_edits() {
+        assert_eq!(align("rnood", "mood"), vec![("rn".to_string(), "m".to_string())]);
+        assert_eq!(align("c0rner", "corner"), vec![("0".to_string(), "o".to_string())]);
+        assert_eq!(
+            align("5peed b0nd", "speed bond"),
+            vec![("0".to_string(), "o".to_string()), ("5".to_string(), "s".to_string())]
+        );
+        assert!(align("same", "same").is_empty());
+    }
+
+    #[test]
+    fn learning_counts_and_skips() {
+        let report = learn_confusions(
+            &pairs(&[
+                ("rnood up", "mood up"),
+                ("good rnood", "good mood"),
+                ("mood", "mood"),
+                ("something else entirely", "mood"),
+            ]),
+            2,
+        );
+        assert_eq!(report.pairs, 4);
+        assert_eq!(report.exact, 1);
+        assert_eq!(report.skipped, 1);
+        assert_eq!(report.confusions.len(), 1);
+
+        let learned = &report.confusions[0];
+        assert_eq!((learned.ocr.as_str(), learned.expected.as_str()), ("rn", "m"));
+        assert_eq!((learned.count, learned.opportunities), (2, 3));
+        assert!((learned.cost - 1.0 / 3.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn learning_respects_min_count() {
+        let corpus = pairs(&[("rnood", "mood"), ("c0rner", "corner")]);
+        assert_eq!(learn_confusions(&corpus, 2).confusions.len(), 0);
+        assert_eq!(learn_confusions(&corpus, 1).confusions.len(), 2);
+        // 0 is treated as 1
+        assert_eq!(learn_confusions(&corpus, 0).confusions.len(), 2);
+    }
+
+    #[test]
+    fn learned_costs_stay_above_the_floor() {
+        let report = learn_confusions(&pairs(&[("rnood", "mood"), ("rnore", "more")]), 1);
+        let learned = &report.confusions[0];
+        assert_eq!(learned.count, learned.opportunities);
+        assert_eq!(learned.cost, MIN_COST);
+        assert_eq!(report.table().distance("rn", "m"), MIN_COST);
+    }
+}
@@ -0,0 +1,271 @@
+// Long-lived Tesseract instances shared by all captures. Initialising
+// Tesseract (and probing for tessdata) is slow, so configured instances are
+// kept in a small pool and handed out per recognition instead.
+
+use crate::error::AppError;
+use crate::language;
+use crate::ocr_layout::{self, OcrLine};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tesseract::Tesseract;
+use tracing::info;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct OcrConfig {
+    pub language: String, // Tesseract codes joined by '+', e.g. "eng+jpn"
+    pub whitelist: Option<String>, // None: the language's default, "": no whitelist
+    pub page_seg_mode: String,
+    pub pool_size: usize, // Idle instances kept around for concurrent captures
+    pub word_confidence_floor: Option<f32>, // Words below this (0-100) are left out of matching
+}
+
+impl Default for OcrConfig {
+    fn default() -> Self {
+        OcrConfig {
+            language: "eng".to_string(),
+            whitelist: None,
+            page_seg_mode: "3".to_string(), // Fully automatic page segmentation
+            pool_size: 2,
+            word_confidence_floor: None,
+        }
+    }
+}
+
+impl OcrConfig {
+    pub fn validate(&self) -> Result<(), AppError> {
+        language::validate_language(&self.language)
+    }
+
+    pub fn effective_whitelist(&self) -> Option<String> {
+        match &self.whitelist {
+            Some(whitelist) if whitelist.is_empty() => None,
+            Some(whitelist) => Some(whitelist.clone()),
+            None => language::default_whitelist(&self.language),
+        }
+    }
+}
+
+pub struct OcrOutput {
+    pub text: String,
+    pub confidence: f32,
+    pub lines: Vec<OcrLine>, // In the coordinates of the recognised image
+}
+
+struct EngineState {
+    config: OcrConfig,
+    tessdata_path: Option<Option<String>>, // Outer None: not resolved yet
+    generation: u64, // Bumped on config changes so stale instances are dropped
+    idle: Vec<Tesseract>,
+}
+
+pub struct OcrEngine {
+    state: Mutex<EngineState>,
+}
+
+impl OcrEngine {
+    pub fn new(config: OcrConfig) -> Self {
+        OcrEngine {
+            state: Mutex::new(EngineState {
+                config,
+                tessdata_path: None,
+                generation: 0,
+                idle: Vec::new(),
+            }),
+        }
+    }
+
+    pub fn config(&self) -> OcrConfig {
+        self.state.lock().unwrap().config.clone()
+    }
+
+    // Drops all idle instances; the next recognition initialises fresh ones
+    pub fn set_config(&self, config: OcrConfig) {
+        let mut state = self.state.lock().unwrap();
+        if state.config.language != config.language {
+            state.tessdata_path = None;
+        }
+        state.config = config;
+        state.generation += 1;
+        state.idle.clear();
+        info!("OCR engine reconfigured: {:?}", state.config);
+    }
+
+    pub fn needs_tessdata_path(&self) -> bool {
+        self.state.lock().unwrap().tessdata_path.is_none()
+    }
+
+    pub fn set_tessdata_path(&self, path: Option<String>) {
+        let mut state = self.state.lock().unwrap();
+        if state.tessdata_path.as_ref() != Some(&path) {
+            state.tessdata_path = Some(path);
+            state.generation += 1;
+            state.idle.clear();
+        }
+    }
+
+    // Initialises one instance ahead of the first capture
+    pub async fn warm_up(self: &Arc<Self>) -> Result<(), AppError> {
+        let engine = Arc::clone(self);
+        tokio::task::spawn_blocking(move || {
+            let (tesseract, generation) = engine.checkout()?;
+            engine.checkin(tesseract, generation);
+            Ok(())
+        })
+        .await
+        .map_err(|e| AppError::internal(format!("OCR warm-up task failed: {}", e)))?
+    }
+
+    // Recognises a PNG-encoded image. Blocking: the app runs it on the
+    // blocking thread pool, one task per OCR variant
+    pub fn recognize_blocking(&self, image_png: &[u8]) -> Result<OcrOutput, AppError> {
+        let (tesseract, generation) = self.checkout()?;
+
+        let mut tesseract = tesseract
+            .set_image_from_mem(image_png)
+            .map_err(|e| AppError::OcrFailed { reason: format!("could not set image: {}", e) })?;
+
+        let text = tesseract
+            .get_text()
+            .map_err(|e| AppError::OcrFailed { reason: format!("could not extract text: {}", e) })?;
+        let confidence = tesseract.mean_text_conf() as f32;
+
+        // Layout is a nice-to-have, don't fail the recognition without it
+        let lines = match tesseract.get_tsv_text(0) {
+            Ok(tsv) => ocr_layout::parse_tsv(&tsv),
+            Err(e) => {
+                info!("Warning: Could not get word layout: {}", e);
+                Vec::new()
+            }
+        };
+
+        self.checkin(tesseract, generation);
+
+        Ok(OcrOutput { text, confidence, lines })
+    }
+
+    fn checkout(&self) -> Result<(Tesseract, u64), AppError> {
+        let (config, tessdata_path, generation) = {
+            let mut state = self.state.lock().unwrap();
+            if let Some(tesseract) = state.idle.pop() {
+                return Ok((tesseract, state.generation));
+            }
+            (state.config.clone(), state.tessdata_path.clone().flatten(), state.generation)
+        };
+
+        // Initialise outside the lock so other captures are not blocked
+        let tesseract = create_instance(&config, tessdata_path.as_deref())?;
+        Ok((tesseract, generation))
+    }
+
+    fn checkin(&self, tesseract: Tesseract, generation: u64) {
+        let mut state = self.state.lock().unwrap();
+        if state.generation == generation && state.idle.len() < state.config.pool_size.max(1) {
+            state.idle.push(tesseract);
+        }
+    }
+}
+
+fn create_instance(config: &OcrConfig, tessdata_path: Option<&str>) -> Result<Tesseract, AppError> {
+    // Initialize Tesseract with tessdata path (try both with and without explicit path)
+    let tesseract = if let Some(path) = tessdata_path {
+        info!("Initializing Tesseract with tessdata path: {}", path);
+        Tesseract::new(Some(path), Some(&config.language))
+    } else {
+        info!("Initializing Tesseract with default paths");
+        Tesseract::new(None, Some(&config.language))
+    }.map_err(|e| AppError::TessdataMissing {
+        language: config.language.clone(),
+        tessdata_path: tessdata_path.map(str::to_string),
+        reason: e.to_string(),
+    })?;
+
+    // Configure Tesseract for better text recognition
+    let tesseract = match config.effective_whitelist() {
+        Some(whitelist) => tesseract
+            .set_variable("tessedit_char_whitelist", &whitelist)
+            .map_err(|e| AppError::OcrFailed { reason: format!("could not set character whitelist: {}", e) })?,
+        None => tesseract,
+    };
+
+    let tesseract = tesseract
+        .set_variable("tessedit_pageseg_mode", &config.page_seg_mode)
+        .map_err(|e| AppError::OcrFailed { reason: format!("could not set page segmentation mode: {}", e) })?;
+
+    Ok(tesseract)
+}
+
+pub fn encode_png(image: &image::DynamicImage) -> Result<Vec<u8>, AppError> {
+    let mut image_bytes = Vec::new();
+    let mut cursor = std::io::Cursor::new(&mut image_bytes);
+    
+    image.write_to(&mut cursor, image::ImageFormat::Png)
+        .map_err(|e| AppError::ImageFailed { reason: format!("could not encode PNG: {}", e) })?;
+    Ok(image_bytes)
+}
+
+// True when the directory holds traineddata for every code of `language`
+fn has_language_data(tessdata_path: &Path, language: &str) -> bool {
+    language::language_codes(language).all(|code| tessdata_path.join(format!("{}.traineddata", code)).exists())
+}
+
+// Probes the usual tessdata locations for one holding every language;
+// `resource_dir` is the app's bundled resources directory, when there is one
+pub fn find_tessdata_path(resource_dir: Option<&Path>, language: &str) -> Option<String> {
+    let mut possible_paths = vec![];
+    
+    // First priority: bundled tessdata
+    if let Some(resource_path) = resource_dir {
+        let bundled_tessdata = resource_path.join("tessdata");
+        info!("Checking bundled tessdata path: {}", bundled_tessdata.display());
+        
+        if has_language_data(&bundled_tessdata, language) {
+            info!("Found bundled tessdata at: {}", bundled_tessdata.display());
+            return Some(bundled_tessdata.to_string_lossy().to_string());
+        }
+    }
+    
+    // Fallback to environment variable
+    if let Ok(tessdata_prefix) = std::env::var("TESSDATA_PREFIX") {
+        possible_paths.push(tessdata_prefix);
+    }
+    
+    // Add executable directory paths
+    if let Ok(exe_path) = std::env::current_exe() {
+        if let Some(exe_dir) = exe_path.parent() {
+            possible_paths.push(exe_dir.join("tessdata").to_string_lossy().to_string());
+            possible_paths.push(exe_dir.join("resources").join("tessdata").to_string_lossy().to_string());
+        }
+    }
+    
+    // Add platform-specific paths as fallback
+    if cfg!(target_os = "windows") {
+        possible_paths.extend(vec![
+            "C:\\Program Files\\Tesseract-OCR\\tessdata".to_string(),
+            "C:\\Program Files (x86)\\Tesseract-OCR\\tessdata".to_string(),
+            "tessdata".to_string(), // Current directory
+        ]);
+    } else {
+        possible_paths.extend(vec![
+            "/usr/share/tesseract-ocr/4.00/tessdata".to_string(),
+            "/usr/share/tesseract-ocr/tessdata".to_string(),
+            "/usr/local/share/tessdata".to_string(),
+            "/opt/homebrew/share/tessdata".to_string(), // macOS Homebrew
+            "tessdata".to_string(),
+        ]);
+    }
+    
+    for path in possible_paths {
+        let tessdata_path = std::path::Path::new(&path);
+        info!("Checking tessdata path: {}", tessdata_path.display());
+        
+        if has_language_data(tessdata_path, language) {
+            info!("Found tessdata at: {}", tessdata_path.display());
+            return Some(path);
+        }
+    }
+    
+    info!("No tessdata for '{}' found in any checked locations", language);
+    None
+}
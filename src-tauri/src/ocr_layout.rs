@@ -0,0 +1,188 @@
+// Word and line layout of a recognition, parsed from Tesseract's TSV output,
+// so low-confidence words can be highlighted and left out of matching.
+
+use serde::{Deserialize, Serialize};
+
+// TSV rows with this level describe a single word
+const WORD_LEVEL: &str = "5";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub struct BoundingBox {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl BoundingBox {
+    fn union(&self, other: &BoundingBox) -> BoundingBox {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        let right = (self.x + self.width).max(other.x + other.width);
+        let bottom = (self.y + self.height).max(other.y + other.height);
+        BoundingBox { x, y, width: right - x, height: bottom - y }
+    }
+
+    // Undoes the preprocessing scale and crop, so boxes refer to the original capture
+    fn to_capture(self, scale: f32, offset: (f32, f32)) -> BoundingBox {
+        let unscale = |value: u32| value as f32 / scale;
+        BoundingBox {
+            x: (unscale(self.x) + offset.0).round() as u32,
+            y: (unscale(self.y) + offset.1).round() as u32,
+            width: unscale(self.width).round() as u32,
+            height: unscale(self.height).round() as u32,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct OcrWord {
+    pub text: String,
+    pub confidence: f32, // 0-100, as reported by Tesseract
+    pub bbox: BoundingBox,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct OcrLine {
+    pub text: String,
+    pub confidence: f32, // Mean of the word confidences
+    pub bbox: BoundingBox,
+    pub words: Vec<OcrWord>,
+}
+
+impl OcrLine {
+    fn from_words(words: Vec<OcrWord>) -> Option<OcrLine> {
+        let first = words.first()?;
+        let bbox = words.iter().skip(1).fold(first.bbox, |bbox, word| bbox.union(&word.bbox));
+        let text = words.iter().map(|word| word.text.as_str()).collect::<Vec<_>>().join(" ");
+        let confidence = words.iter().map(|word| word.confidence).sum::<f32>() / words.len() as f32;
+        Some(OcrLine { text, confidence, bbox, words })
+    }
+}
+
+// Columns: level page_num block_num par_num line_num word_num left top width height conf text
+pub fn parse_tsv(tsv: &str) -> Vec<OcrLine> {
+    let mut lines = Vec::new();
+    let mut current_key = None;
+    let mut current_words = Vec::new();
+
+    for row in tsv.lines() {
+        let columns: Vec<&str> = row.splitn(12, '\t').collect();
+        if columns.len() < 12 || columns[0] != WORD_LEVEL {
+            continue;
+        }
+
+        let text = columns[11].trim();
+        let confidence: f32 = columns[10].parse().unwrap_or(-1.0);
+        if text.is_empty() || confidence < 0.0 {
+            continue;
+        }
+        let number = |i: usize| columns[i].parse::<u32>().unwrap_or(0);
+
+        // Words are listed in reading order, grouped by block, paragraph and line
+        let key = (number(2), number(3), number(4));
+        if current_key != Some(key) {
+            lines.extend(OcrLine::from_words(std::mem::take(&mut current_words)));
+            current_key = Some(key);
+        }
+        current_words.push(OcrWord {
+            text: text.to_string(),
+            confidence,
+            bbox: BoundingBox { x: number(6), y: number(7), width: number(8), height: number(9) },
+        });
+    }
+    lines.extend(OcrLine::from_words(current_words));
+
+    lines
+}
+
+// Maps boxes from the preprocessed image back onto the capture it came from
+pub fn map_to_capture(lines: &mut [OcrLine], scale: f32, offset: (f32, f32)) {
+    if scale <= 0.0 {
+        return;
+    }
+
+    for line in lines {
+        line.bbox = line.bbox.to_capture(scale, offset);
+        for word in &mut line.words {
+            word.bbox = word.bbox.to_capture(scale, offset);
+        }
+    }
+}
+
+// The recognised text without words below `floor`. None when nothing is
+// left, so callers can fall back to the full text.
+pub fn text_above_confidence(lines: &[OcrLine], floor: f32) -> Option<String> {
+    let kept: Vec<String> = lines
+        .iter()
+        .map(|line| {
+            line.words
+                .iter()
+                .filter(|word| word.confidence >= floor)
+                .map(|word| word.text.as_str())
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .filter(|line| !line.is_empty())
+        .collect();
+
+    if kept.is_empty() {
+        None
+    } else {
+        Some(kept.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TSV: &str = "level\tpage_num\tblock_num\tpar_num\tline_num\tword_num\tleft\ttop\twidth\theight\tconf\ttext
+1\t1\t0\t0\t0\t0\t0\t0\t400\t200\t-1\t
+4\t1\t1\t1\t1\t0\t20\t10\t300\t40\t-1\t
+5\t1\t1\t1\t1\t1\t20\t10\t100\t40\t96.5\tSummer
+5\t1\t1\t1\t1\t2\t140\t12\t60\t38\t41.0\tCamp
+5\t1\t1\t1\t1\t3\t210\t10\t20\t40\t-1\tghost
+5\t1\t1\t1\t2\t1\t20\t60\t80\t40\t90.0\tRest
+5\t1\t1\t1\t2\tbroken row
+";
+
+    #[test]
+    fn parses_word_rows_into_lines() {
+        let lines = parse_tsv(TSV);
+        assert_eq!(lines.len(), 2);
+
+        // Only level 5 rows with a confidence are words
+        assert_eq!(lines[0].text, "Summer Camp");
+        assert_eq!(lines[0].words.len(), 2);
+        assert_eq!(lines[0].confidence, (96.5 + 41.0) / 2.0);
+        assert_eq!(lines[0].bbox, BoundingBox { x: 20, y: 10, width: 180, height: 40 });
+        assert_eq!(lines[1].text, "Rest");
+
+        assert!(parse_tsv("").is_empty());
+        assert!(parse_tsv("not\ta\ttsv").is_empty());
+    }
+
+    #[test]
+    fn maps_boxes_back_to_the_capture() {
+        // Upscaled 2x, then trimmed 5 capture pixels from the left and 3 from the top
+        let mut lines = parse_tsv(TSV);
+        map_to_capture(&mut lines, 2.0, (5.0, 3.0));
+        assert_eq!(lines[0].words[0].bbox, BoundingBox { x: 15, y: 8, width: 50, height: 20 });
+        assert_eq!(lines[0].bbox, BoundingBox { x: 15, y: 8, width: 90, height: 20 });
+
+        // A scale of zero leaves the boxes alone
+        let mut unscaled = parse_tsv(TSV);
+        map_to_capture(&mut unscaled, 0.0, (5.0, 3.0));
+        assert_eq!(unscaled, parse_tsv(TSV));
+    }
+
+    #[test]
+    fn drops_words_below_the_floor() {
+        let lines = parse_tsv(TSV);
+        assert_eq!(text_above_confidence(&lines, 0.0).as_deref(), Some("Summer Camp\nRest"));
+        assert_eq!(text_above_confidence(&lines, 50.0).as_deref(), Some("Summer\nRest"));
+        assert_eq!(text_above_confidence(&lines, 95.0).as_deref(), Some("Summer"));
+        assert_eq!(text_above_confidence(&lines, 99.0), None);
+    }
+}
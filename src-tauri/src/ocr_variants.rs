@@ -0,0 +1,259 @@
+// Runs the same capture through several preprocessing variants and votes on
+// the result, so a wrong inversion or threshold guess does not lose a capture.
+
+use crate::error::AppError;
+use crate::matcher::EventMatch;
+use crate::ocr_engine::{self, OcrEngine, OcrOutput};
+use crate::ocr_layout;
+use crate::preprocess::{self, PreprocessConfig, PreprocessOutput, PreprocessStep};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+const MAX_VARIANTS: usize = 8;
+
+// How much each signal contributes to a variant's score (sums to 1.0)
+const OCR_CONFIDENCE_WEIGHT: f32 = 0.3;
+const MATCH_CONFIDENCE_WEIGHT: f32 = 0.5;
+const AGREEMENT_WEIGHT: f32 = 0.2;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct OcrVariant {
+    pub name: String,
+    pub steps: Vec<PreprocessStep>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct VariantConfig {
+    pub enabled: bool,
+    pub variants: Vec<OcrVariant>, // Run in addition to the configured pipeline
+}
+
+impl Default for VariantConfig {
+    fn default() -> Self {
+        let variant = |name: &str, steps: Vec<PreprocessStep>| OcrVariant { name: name.to_string(), steps };
+        let preset_steps = |name: &str| preprocess::preset(name).map(|config| config.steps).unwrap_or_default();
+        let contrast = PreprocessStep::Contrast { amount: 20.0 };
+
+        VariantConfig {
+            enabled: false,
+            variants: vec![
+                variant("inverted", vec![PreprocessStep::Upscale { factor: 2 }, PreprocessStep::Invert, contrast.clone()]),
+                variant("not_inverted", vec![PreprocessStep::Upscale { factor: 2 }, contrast.clone()]),
+                variant("upscale_3x", vec![PreprocessStep::Upscale { factor: 3 }, PreprocessStep::AutoInvert, contrast]),
+                variant("otsu", preset_steps("otsu")),
+                variant("sauvola", preset_steps("sauvola")),
+            ],
+        }
+    }
+}
+
+impl VariantConfig {
+    pub fn validate(&self) -> Result<(), AppError> {
+        if self.variants.len() > MAX_VARIANTS {
+            return Err(AppError::invalid_input(format!("At most {} OCR variants can be run", MAX_VARIANTS)));
+        }
+
+        for variant in &self.variants {
+            if variant.name.trim().is_empty() {
+                return Err(AppError::invalid_input("OCR variant name must not be empty"));
+            }
+            PreprocessConfig { steps: variant.steps.clone(), ..PreprocessConfig::default() }.validate()?;
+        }
+        Ok(())
+    }
+
+    // The configured pipeline first, then the extra variants when enabled
+    pub fn pipelines(&self, configured: &PreprocessConfig) -> Vec<(String, PreprocessConfig)> {
+        let mut pipelines = vec![("configured".to_string(), configured.clone())];
+        if self.enabled {
+            pipelines.extend(self.variants.iter().map(|variant| {
+                let config = PreprocessConfig {
+                    color_key: configured.color_key.clone(),
+                    steps: variant.steps.clone(),
+                    debug_stages: configured.debug_stages,
+                };
+                (variant.name.clone(), config)
+            }));
+        }
+        pipelines
+    }
+}
+
+pub struct VariantRun {
+    pub name: String,
+    pub preprocessed: PreprocessOutput,
+    pub ocr: OcrOutput,
+}
+
+impl VariantRun {
+    // Text handed to the matcher: words below the floor are dropped, unless
+    // that would leave nothing to match
+    pub fn match_text(&self, word_confidence_floor: Option<f32>) -> String {
+        word_confidence_floor
+            .and_then(|floor| ocr_layout::text_above_confidence(&self.ocr.lines, floor))
+            .unwrap_or_else(|| self.ocr.text.trim().to_string())
+    }
+}
+
+// Blocking: preprocesses the capture with one pipeline and runs OCR on it
+pub fn run_variant(
+    engine: &OcrEngine,
+    image: &image::DynamicImage,
+    name: String,
+    config: &PreprocessConfig,
+) -> Result<VariantRun, AppError> {
+    let preprocessed = preprocess::preprocess_image(image, config);
+    let image_bytes = ocr_engine::encode_png(&preprocessed.image)?;
+    let mut ocr = engine.recognize_blocking(&image_bytes)?;
+
+    // Report word boxes relative to the capture rather than the upscaled, trimmed image
+    ocr_layout::map_to_capture(&mut ocr.lines, preprocessed.scale, preprocessed.offset);
+    info!("Variant '{}': confidence {}, text {:?}", name, ocr.confidence, ocr.text.trim());
+
+    Ok(VariantRun { name, preprocessed, ocr })
+}
+
+pub struct VariantCandidate<'a> {
+    pub name: &'a str,
+    pub text: &'a str,
+    pub ocr_confidence: f32,
+    pub matches: &'a [EventMatch],
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct VariantScore {
+    pub name: String,
+    pub text: String,
+    pub ocr_confidence: f32,
+    pub match_confidence: f32, // Best event match for this variant's text
+    pub top_event: Option<String>,
+    pub votes: usize, // Variants (including this one) agreeing on the top event
+    pub score: f32,
+}
+
+// Scores every candidate and returns the index of the winner. Earlier
+// candidates win ties, so the configured pipeline is preferred.
+pub fn vote(candidates: &[VariantCandidate]) -> (usize, Vec<VariantScore>) {
+    // Agreement is on the event id: events sharing a name (e.g. one per
+    // character) are different events
+    let top_ids: Vec<Option<&str>> = candidates
+        .iter()
+        .map(|candidate| candidate.matches.first().map(|top| top.event_id.as_str()))
+        .collect();
+
+    let scores: Vec<VariantScore> = candidates
+        .iter()
+        .zip(&top_ids)
+        .map(|(candidate, top_id)| {
+            let top = candidate.matches.first();
+            let match_confidence = top.map_or(0.0, |top| top.match_confidence);
+            let votes = match top_id {
+                Some(id) => top_ids.iter().filter(|other| **other == Some(*id)).count(),
+                None => 0,
+            };
+            let agreement = if candidates.len() > 1 {
+                votes.saturating_sub(1) as f32 / (candidates.len() - 1) as f32
+            } else {
+                0.0
+            };
+
+            let score = OCR_CONFIDENCE_WEIGHT * (candidate.ocr_confidence / 100.0).clamp(0.0, 1.0)
+                + MATCH_CONFIDENCE_WEIGHT * match_confidence
+                + AGREEMENT_WEIGHT * agreement;
+
+            VariantScore {
+                name: candidate.name.to_string(),
+                text: candidate.text.to_string(),
+                ocr_confidence: candidate.ocr_confidence,
+                match_confidence,
+                top_event: top.map(|top| top.event.name.clone()),
+                votes,
+                score,
+            }
+        })
+        .collect();
+
+    let mut best = 0;
+    for (i, score) in scores.iter().enumerate() {
+        if score.score > scores[best].score {
+            best = i;
+        }
+    }
+    (best, scores)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::JsonEvent;
+
+    fn event_match(name: &str, character_name: &str, match_confidence: f32) -> EventMatch {
+        let event = JsonEvent {
+            name: name.to_string(),
+            names: Default::default(),
+            character_name: character_name.to_string(),
+            relation_type: "Umamusume".to_string(),
+            choices: Vec::new(),
+            provenance: Default::default(),
+        };
+        EventMatch {
+            event_id: event.id(),
+            provenance: event.provenance.clone(),
+            event,
+            match_confidence,
+            match_type: "event_name".to_string(),
+            matched_text: name.to_string(),
+            signals: Vec::new(),
+            choice_outcomes: Vec::new(),
+            choice_scores: Vec::new(),
+            recommended_choice: None,
+        }
+    }
+
+    fn candidate<'a>(name: &'a str, ocr_confidence: f32, matches: &'a [EventMatch]) -> VariantCandidate<'a> {
+        VariantCandidate { name, text: "", ocr_confidence, matches }
+    }
+
+    #[test]
+    fn majority_wins() {
+        let lone = [event_match("Hamburger Helper!", "Kitasan Black", 0.9)];
+        let agreeing = [event_match("At Summer Camp (Year 2)", "Vodka", 0.8)];
+        let candidates = [
+            candidate("configured", 80.0, &lone),
+            candidate("inverted", 80.0, &agreeing),
+            candidate("otsu", 80.0, &agreeing),
+        ];
+
+        let (best, scores) = vote(&candidates);
+        assert_eq!(best, 1);
+        assert_eq!(scores.iter().map(|score| score.votes).collect::<Vec<_>>(), [1, 2, 2]);
+        assert_eq!(scores[1].top_event.as_deref(), Some("At Summer Camp (Year 2)"));
+    }
+
+    #[test]
+    fn tie_falls_back_to_confidence() {
+        let first = [event_match("Hamburger Helper!", "Kitasan Black", 0.7)];
+        let second = [event_match("At Summer Camp (Year 2)", "Vodka", 0.9)];
+        let (best, scores) = vote(&[candidate("configured", 80.0, &first), candidate("otsu", 80.0, &second)]);
+        assert_eq!(best, 1);
+        assert!(scores.iter().all(|score| score.votes == 1));
+
+        // Equal scores keep the earlier candidate
+        let (best, _) = vote(&[candidate("configured", 80.0, &first), candidate("otsu", 80.0, &first)]);
+        assert_eq!(best, 0);
+    }
+
+    #[test]
+    fn same_name_events_do_not_agree() {
+        let vodka = [event_match("At Summer Camp (Year 2)", "Vodka", 0.8)];
+        let tachyon = [event_match("At Summer Camp (Year 2)", "Agnes Tachyon", 0.8)];
+        let (_, scores) = vote(&[candidate("configured", 80.0, &vodka), candidate("otsu", 80.0, &tachyon)]);
+        assert!(scores.iter().all(|score| score.votes == 1));
+
+        let (_, scores) = vote(&[candidate("configured", 80.0, &vodka), candidate("otsu", 80.0, &[])]);
+        assert_eq!(scores[0].votes, 1);
+        assert_eq!(scores[1].votes, 0);
+        assert_eq!(scores[1].top_event, None);
+    }
+}
@@ -0,0 +1,373 @@
+// Structured view of the semicolon-separated `Choice.outcome` strings
+// (e.g. "-5 Energy;Last trained stat +5;5 Bond with Director Akikawa").
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Stat {
+    Speed,
+    Stamina,
+    Power,
+    Guts,
+    Wit,
+}
+
+impl Stat {
+    pub const ALL: [Stat; 5] = [Stat::Speed, Stat::Stamina, Stat::Power, Stat::Guts, Stat::Wit];
+
+    fn from_label(label: &str) -> Option<Stat> {
+        match label.to_lowercase().as_str() {
+            "speed" | "spd" => Some(Stat::Speed),
+            "stamina" | "sta" => Some(Stat::Stamina),
+            "power" | "pow" => Some(Stat::Power),
+            "guts" => Some(Stat::Guts),
+            "wit" | "wits" | "wisdom" | "int" | "intelligence" => Some(Stat::Wit),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Effect {
+    Stat { stat: Stat, amount: i32 },
+    AllStats { amount: i32 },
+    LastTrainedStat { amount: i32 },
+    // "3 status +5 (random)": `count` random stats each gain `amount`
+    RandomStats { count: u32, amount: i32 },
+    Energy { amount: i32 },
+    MaxEnergy { amount: i32 },
+    Mood { amount: i32 },
+    SkillPoints { amount: i32 },
+    Bond { amount: i32 },
+    NpcBond { name: String, amount: i32 },
+    SkillHint { skill: String, level: u32, random: bool },
+    ConditionGained { condition: String, random: bool },
+    ConditionsCured,
+    ObjectiveChanged { race: String },
+    // "A and/or B": at least one of the options applies
+    Alternatives { options: Vec<Effect> },
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct ParsedOutcome {
+    pub effects: Vec<Effect>,
+    // Segments of the raw string that did not match any known effect
+    pub unparsed: Vec<String>,
+}
+
+// Per-choice parse result exposed next to the original outcome text
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChoiceOutcome {
+    pub number: String,
+    pub outcome: String,
+    pub effects: Vec<Effect>,
+    pub unparsed: Vec<String>,
+}
+
+// Status conditions that can be granted by events. Anything else without a
+// "Skill Hint" suffix is reported as unparsed rather than guessed at.
+const KNOWN_CONDITIONS: &[&str] = &[
+    "charming",
+    "hot topic",
+    "fast learner",
+    "practice perfect",
+    "shining brightly",
+    "slow metabolism",
+    "night owl",
+    "migraine",
+    "skin outbreak",
+    "slacker",
+    "practice poor",
+    "overweight",
+];
+
+const NEGATIVE_CONDITIONS: &[&str] = &[
+    "slow metabolism",
+    "night owl",
+    "migraine",
+    "skin outbreak",
+    "slacker",
+    "practice poor",
+    "overweight",
+];
+
+pub fn is_negative_condition(condition: &str) -> bool {
+    let key = condition.trim_end_matches('◯').trim().to_lowercase();
+    NEGATIVE_CONDITIONS.contains(&key.as_str())
+}
+
+pub fn parse_outcome(outcome: &str) -> ParsedOutcome {
+    let mut parsed = ParsedOutcome::default();
+
+    for segment in outcome.split(';') {
+        let segment = collapse_whitespace(segment);
+        if segment.is_empty() {
+            continue;
+        }
+
+        match parse_segment(&segment) {
+            Some(effect) => parsed.effects.push(effect),
+            None => parsed.unparsed.push(segment),
+        }
+    }
+
+    parsed
+}
+
+pub fn parse_choice_outcome(number: &str, outcome: &str) -> ChoiceOutcome {
+    let parsed = parse_outcome(outcome);
+    ChoiceOutcome {
+        number: number.to_string(),
+        outcome: outcome.to_string(),
+        effects: parsed.effects,
+        unparsed: parsed.unparsed,
+    }
+}
+
+fn parse_segment(segment: &str) -> Option<Effect> {
+    // Alternatives have to be split before anything else so that each side
+    // is parsed on its own
+    if segment.contains(" and/or ") {
+        let options = segment
+            .split(" and/or ")
+            .map(parse_segment)
+            .collect::<Option<Vec<_>>>()?;
+        return Some(Effect::Alternatives { options });
+    }
+
+    let lower = segment.to_lowercase();
+
+    if let Some(rest) = lower.strip_prefix("last trained stat") {
+        return parse_signed(rest.trim()).map(|amount| Effect::LastTrainedStat { amount });
+    }
+
+    if let Some(race) = strip_prefix_ci(segment, "objective race changed to ") {
+        return Some(Effect::ObjectiveChanged { race: race.trim().to_string() });
+    }
+
+    if lower == "recover from negative conditions" || lower == "cure all negative conditions" {
+        return Some(Effect::ConditionsCured);
+    }
+
+    if let Some(hint) = strip_suffix_ci(segment, "skill hint") {
+        return parse_skill_hint(hint.trim());
+    }
+
+    if let Some(effect) = parse_numeric(segment) {
+        return Some(effect);
+    }
+
+    parse_condition(segment)
+}
+
+// "<amount> <label>" effects such as "10 Speed", "-5 Energy" or
+// "5 Bond with Director Akikawa"
+fn parse_numeric(segment: &str) -> Option<Effect> {
+    let (amount_str, label) = segment.split_once(' ')?;
+    let amount = parse_signed(amount_str)?;
+    let label = label.trim();
+    let lower = label.to_lowercase();
+
+    if let Some(stat) = Stat::from_label(label) {
+        return Some(Effect::Stat { stat, amount });
+    }
+
+    match lower.as_str() {
+        "all stats" | "all stat" => return Some(Effect::AllStats { amount }),
+        "energy" => return Some(Effect::Energy { amount }),
+        "max energy" => return Some(Effect::MaxEnergy { amount }),
+        "mood" => return Some(Effect::Mood { amount }),
+        "skill pts" | "skill points" | "skill pt" => return Some(Effect::SkillPoints { amount }),
+        "bond" => return Some(Effect::Bond { amount }),
+        _ => {}
+    }
+
+    if let Some(name) = strip_prefix_ci(label, "bond with ") {
+        return Some(Effect::NpcBond { name: name.trim().to_string(), amount });
+    }
+
+    // "3 status +5 (random)"
+    if let Some(rest) = lower.strip_prefix("status ") {
+        let rest = rest.trim_end_matches("(random)").trim();
+        let per_stat = parse_signed(rest)?;
+        let count = u32::try_from(amount).ok()?;
+        return Some(Effect::RandomStats { count, amount: per_stat });
+    }
+
+    None
+}
+
+// "Corner Adept ◯ +1", "(Random) Non-Standard Distance ◯", "Focus"
+fn parse_skill_hint(hint: &str) -> Option<Effect> {
+    let (hint, random) = strip_random_marker(hint);
+
+    let (skill, level) = match hint.rsplit_once(' ') {
+        Some((skill, level)) if level.starts_with('+') => (skill.trim(), parse_signed(level)?),
+        _ => (hint, 1),
+    };
+
+    if skill.is_empty() {
+        return None;
+    }
+
+    Some(Effect::SkillHint {
+        skill: skill.to_string(),
+        level: u32::try_from(level).ok()?,
+        random,
+    })
+}
+
+fn parse_condition(segment: &str) -> Option<Effect> {
+    let (condition, random) = strip_random_marker(segment);
+    let key = condition.trim_end_matches('◯').trim().to_lowercase();
+
+    if KNOWN_CONDITIONS.contains(&key.as_str()) {
+        Some(Effect::ConditionGained { condition: condition.to_string(), random })
+    } else {
+        None
+    }
+}
+
+fn strip_random_marker(text: &str) -> (&str, bool) {
+    if let Some(rest) = strip_prefix_ci(text, "(random)") {
+        (rest.trim(), true)
+    } else if let Some(rest) = strip_suffix_ci(text, "(random)") {
+        (rest.trim(), true)
+    } else {
+        (text, false)
+    }
+}
+
+fn parse_signed(text: &str) -> Option<i32> {
+    let text = text.trim();
+    let text = text.strip_prefix('+').unwrap_or(text);
+    text.parse::<i32>().ok()
+}
+
+fn strip_prefix_ci<'a>(text: &'a str, prefix: &str) -> Option<&'a str> {
+    let head = text.get(..prefix.len())?;
+    if head.eq_ignore_ascii_case(prefix) {
+        Some(&text[prefix.len()..])
+    } else {
+        None
+    }
+}
+
+fn strip_suffix_ci<'a>(text: &'a str, suffix: &str) -> Option<&'a str> {
+    let start = text.len().checked_sub(suffix.len())?;
+    let tail = text.get(start..)?;
+    if tail.eq_ignore_ascii_case(suffix) {
+        Some(&text[..start])
+    } else {
+        None
+    }
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_stats_energy_and_npc_bond() {
+        let parsed = parse_outcome("-5 Energy;Last trained stat +5;5 Bond with Director Akikawa");
+        assert_eq!(
+            parsed.effects,
+            vec![
+                Effect::Energy { amount: -5 },
+                Effect::LastTrainedStat { amount: 5 },
+                Effect::NpcBond { name: "Director Akikawa".to_string(), amount: 5 },
+            ]
+        );
+        assert!(parsed.unparsed.is_empty());
+    }
+
+    #[test]
+    fn parses_numeric_labels() {
+        let parsed = parse_outcome("5 Guts;4 Max Energy;5 Bond;1 Mood;15 Skill Pts;5 All Stats");
+        assert_eq!(
+            parsed.effects,
+            vec![
+                Effect::Stat { stat: Stat::Guts, amount: 5 },
+                Effect::MaxEnergy { amount: 4 },
+                Effect::Bond { amount: 5 },
+                Effect::Mood { amount: 1 },
+                Effect::SkillPoints { amount: 15 },
+                Effect::AllStats { amount: 5 },
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_random_stats() {
+        let parsed = parse_outcome("3 status +5 (random)");
+        assert_eq!(parsed.effects, vec![Effect::RandomStats { count: 3, amount: 5 }]);
+    }
+
+    #[test]
+    fn parses_skill_hints() {
+        let hint = |skill: &str, level, random| Effect::SkillHint { skill: skill.to_string(), level, random };
+
+        assert_eq!(parse_outcome("Corner Adept ◯ Skill Hint").effects, vec![hint("Corner Adept ◯", 1, false)]);
+        assert_eq!(parse_outcome("Rainy Days ◯ +1 Skill Hint").effects, vec![hint("Rainy Days ◯", 1, false)]);
+        assert_eq!(parse_outcome("Huge Lead +3 Skill Hint").effects, vec![hint("Huge Lead", 3, false)]);
+        assert_eq!(
+            parse_outcome("(Random) Non-Standard Distance ◯ Skill Hint").effects,
+            vec![hint("Non-Standard Distance ◯", 1, true)]
+        );
+    }
+
+    #[test]
+    fn parses_conditions() {
+        let parsed = parse_outcome("10 Speed;10 Power;Charming ◯ (Random)");
+        assert_eq!(
+            parsed.effects[2],
+            Effect::ConditionGained { condition: "Charming ◯".to_string(), random: true }
+        );
+
+        let parsed = parse_outcome("4 Mood;Recover from negative Conditions;5 Bond");
+        assert_eq!(parsed.effects[1], Effect::ConditionsCured);
+        assert!(is_negative_condition("Night Owl"));
+        assert!(!is_negative_condition("Charming ◯"));
+    }
+
+    #[test]
+    fn parses_alternatives() {
+        let parsed = parse_outcome("20 Wit;Practice Perfect ◯ (Random) and/or Hot Topic (Random)");
+        assert_eq!(
+            parsed.effects,
+            vec![
+                Effect::Stat { stat: Stat::Wit, amount: 20 },
+                Effect::Alternatives {
+                    options: vec![
+                        Effect::ConditionGained { condition: "Practice Perfect ◯".to_string(), random: true },
+                        Effect::ConditionGained { condition: "Hot Topic".to_string(), random: true },
+                    ],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_objective_change() {
+        let parsed = parse_outcome("5 Power;Objective race changed to Japanese Oaks");
+        assert_eq!(parsed.effects[1], Effect::ObjectiveChanged { race: "Japanese Oaks".to_string() });
+    }
+
+    #[test]
+    fn keeps_unknown_segments_unparsed() {
+        let parsed = parse_outcome("60 Skill Pts;*Skill acquisition is random.;  Nothing  happens ");
+        assert_eq!(parsed.effects, vec![Effect::SkillPoints { amount: 60 }]);
+        assert_eq!(parsed.unparsed, vec!["*Skill acquisition is random.", "Nothing happens"]);
+
+        // An alternative with an unknown side is unparsed as a whole
+        let parsed = parse_outcome("Hot Topic (Random) and/or Something else");
+        assert!(parsed.effects.is_empty());
+        assert_eq!(parsed.unparsed, vec!["Hot Topic (Random) and/or Something else"]);
+    }
+}
@@ -0,0 +1,16 @@
+// Per-user locations shared by the app and the CLI.
+
+use std::path::PathBuf;
+use tracing::info;
+
+// Created on first use; None if it cannot be
+pub fn get_config_dir() -> Option<PathBuf> {
+    let dir = dirs::config_dir()?.join("uma-helper");
+    match std::fs::create_dir_all(&dir) {
+        Ok(()) => Some(dir),
+        Err(e) => {
+            info!("Warning: Could not create config directory {}: {}", dir.display(), e);
+            None
+        }
+    }
+}
@@ -0,0 +1,550 @@
+// Image preprocessing applied to every capture before it is handed to Tesseract.
+// The work is a configurable pipeline of steps over a grayscale image, so
+// different binarisation strategies can be tried per game screen.
+
+use crate::cleanup::{self, CleanupOutput};
+use crate::color_key::{self, ColorKey};
+use crate::error::AppError;
+use image::{imageops, GrayImage, Luma};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum MorphologyOp {
+    Erode,  // Thin strokes
+    Dilate, // Thicken strokes
+    Open,   // Remove specks smaller than the radius
+    Close,  // Fill gaps in strokes smaller than the radius
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PreprocessStep {
+    Upscale { factor: u32 }, // Lanczos3
+    AutoInvert, // Invert when most of the image is dark (white text)
+    Invert,
+    Contrast { amount: f32 },
+    Otsu, // Global threshold from the histogram
+    Sauvola { window: u32, k: f32 },
+    Niblack { window: u32, k: f32 },
+    Morphology { op: MorphologyOp, radius: u32 }, // On dark text over a light background
+    TrimBorders { tolerance: u8, margin: u32 }, // Crop uniform rows/columns at the edges
+    RemoveComponents { min_area: u32, max_height_fraction: f32, max_aspect: f32 }, // Drop shapes that cannot be text
+    Deskew { max_angle: f32 }, // Straighten text rotated by up to this many degrees
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct PreprocessConfig {
+    pub color_key: Option<ColorKey>, // Replaces the plain grayscale conversion when set
+    pub steps: Vec<PreprocessStep>,
+    pub debug_stages: bool, // Keep every intermediate image for the debug output
+}
+
+impl Default for PreprocessConfig {
+    fn default() -> Self {
+        PreprocessConfig {
+            color_key: None,
+            steps: vec![
+                PreprocessStep::Upscale { factor: 2 },
+                PreprocessStep::AutoInvert,
+                PreprocessStep::Contrast { amount: 20.0 },
+            ],
+            debug_stages: false,
+        }
+    }
+}
+
+impl PreprocessConfig {
+    // Rejects parameters that would make a capture unreasonably slow or large
+    pub fn validate(&self) -> Result<(), AppError> {
+        if let Some(color_key) = &self.color_key {
+            color_key.validate()?;
+        }
+        for step in &self.steps {
+            match step {
+                PreprocessStep::Upscale { factor } if !(1..=4).contains(factor) => {
+                    return Err(AppError::invalid_input("Upscale factor must be between 1 and 4"));
+                }
+                PreprocessStep::Sauvola { window, .. } | PreprocessStep::Niblack { window, .. }
+                    if !(3..=255).contains(window) || window % 2 == 0 =>
+                {
+                    // Windows are centred on the pixel, so the size must be odd
+                    return Err(AppError::invalid_input("Threshold window must be an odd size between 3 and 255 pixels"));
+                }
+                PreprocessStep::Morphology { radius, .. } if *radius > 8 => {
+                    return Err(AppError::invalid_input("Morphology radius must be at most 8 pixels"));
+                }
+                PreprocessStep::TrimBorders { margin, .. } if *margin > 64 => {
+                    return Err(AppError::invalid_input("Border margin must be at most 64 pixels"));
+                }
+                PreprocessStep::RemoveComponents { max_height_fraction, .. }
+                    if !(*max_height_fraction > 0.0 && *max_height_fraction <= 1.0) =>
+                {
+                    return Err(AppError::invalid_input("Component height fraction must be above 0 and at most 1"));
+                }
+                PreprocessStep::RemoveComponents { max_aspect, .. } if !(1.0..).contains(max_aspect) => {
+                    return Err(AppError::invalid_input("Component aspect ratio must be at least 1"));
+                }
+                PreprocessStep::Deskew { max_angle } if !(*max_angle > 0.0 && *max_angle <= 15.0) => {
+                    return Err(AppError::invalid_input("Deskew angle must be between 0 and 15 degrees"));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct PreprocessPreset {
+    pub name: &'static str,
+    pub config: PreprocessConfig,
+}
+
+// Starting points for the pipeline editor and the CLI's --pipeline option
+pub fn presets() -> Vec<PreprocessPreset> {
+    let with_steps = |steps: Vec<PreprocessStep>| PreprocessConfig { steps, ..PreprocessConfig::default() };
+    let base = || vec![PreprocessStep::Upscale { factor: 2 }, PreprocessStep::AutoInvert];
+
+    vec![
+        PreprocessPreset { name: "default", config: PreprocessConfig::default() },
+        PreprocessPreset {
+            name: "otsu",
+            config: with_steps([base(), vec![PreprocessStep::Otsu]].concat()),
+        },
+        PreprocessPreset {
+            name: "sauvola",
+            config: with_steps(
+                [
+                    base(),
+                    vec![
+                        PreprocessStep::Sauvola { window: 31, k: 0.34 },
+                        PreprocessStep::Morphology { op: MorphologyOp::Open, radius: 1 },
+                    ],
+                ]
+                .concat(),
+            ),
+        },
+        PreprocessPreset {
+            name: "cleanup",
+            config: with_steps(
+                [
+                    base(),
+                    vec![
+                        PreprocessStep::TrimBorders { tolerance: 12, margin: 8 },
+                        PreprocessStep::Otsu,
+                        PreprocessStep::RemoveComponents { min_area: 6, max_height_fraction: 0.8, max_aspect: 12.0 },
+                        PreprocessStep::Deskew { max_angle: 5.0 },
+                    ],
+                ]
+                .concat(),
+            ),
+        },
+        PreprocessPreset {
+            name: "niblack",
+            config: with_steps(
+                [
+                    base(),
+                    vec![
+                        PreprocessStep::Niblack { window: 31, k: -0.2 },
+                        PreprocessStep::Morphology { op: MorphologyOp::Open, radius: 1 },
+                    ],
+                ]
+                .concat(),
+            ),
+        },
+    ]
+}
+
+pub fn preset(name: &str) -> Option<PreprocessConfig> {
+    presets().into_iter().find(|preset| preset.name == name).map(|preset| preset.config)
+}
+
+// One intermediate image of the pipeline; `name` identifies the step
+pub struct PreprocessStage {
+    pub name: String,
+    pub image: image::DynamicImage,
+}
+
+pub struct PreprocessOutput {
+    pub image: image::DynamicImage,
+    pub stages: Vec<PreprocessStage>, // Empty unless `debug_stages` is set
+    pub scale: f32, // Output pixels per capture pixel
+    pub offset: (f32, f32), // Capture position of the output's top left corner
+}
+
+pub fn preprocess_image_for_ocr(image: &image::DynamicImage) -> image::DynamicImage {
+    preprocess_image(image, &PreprocessConfig::default()).image
+}
+
+pub fn preprocess_image(image: &image::DynamicImage, config: &PreprocessConfig) -> PreprocessOutput {
+    // Convert to grayscale for better OCR, keeping only the text colours if keyed
+    let (mut gray_image, first_stage) = match &config.color_key {
+        Some(color_key) => (color_key::extract(image, color_key), "color_key"),
+        None => (image.to_luma8(), "grayscale"),
+    };
+    let mut stages = Vec::new();
+    if config.debug_stages {
+        stages.push(PreprocessStage {
+            name: first_stage.to_string(),
+            image: image::DynamicImage::ImageLuma8(gray_image.clone()),
+        });
+    }
+
+    // Track upscaling and cropping so OCR boxes can be mapped back to the capture
+    let mut scale = 1.0;
+    let mut offset = (0.0, 0.0);
+    for step in &config.steps {
+        let output = apply_step(gray_image, step, config.debug_stages);
+        gray_image = output.image;
+        offset.0 += output.trimmed.0 as f32 / scale;
+        offset.1 += output.trimmed.1 as f32 / scale;
+        if let PreprocessStep::Upscale { factor } = step {
+            scale *= (*factor).max(1) as f32;
+        }
+
+        if config.debug_stages {
+            if let Some(overlay) = output.overlay {
+                stages.push(PreprocessStage {
+                    name: format!("{}_removed", step_name(step)),
+                    image: image::DynamicImage::ImageLuma8(overlay),
+                });
+            }
+            stages.push(PreprocessStage {
+                name: step_name(step),
+                image: image::DynamicImage::ImageLuma8(gray_image.clone()),
+            });
+        }
+    }
+
+    PreprocessOutput {
+        image: image::DynamicImage::ImageLuma8(gray_image),
+        stages,
+        scale,
+        offset,
+    }
+}
+
+fn step_name(step: &PreprocessStep) -> String {
+    match step {
+        PreprocessStep::Upscale { factor } => format!("upscale_{}x", factor),
+        PreprocessStep::AutoInvert => "auto_invert".to_string(),
+        PreprocessStep::Invert => "invert".to_string(),
+        PreprocessStep::Contrast { .. } => "contrast".to_string(),
+        PreprocessStep::Otsu => "otsu".to_string(),
+        PreprocessStep::Sauvola { .. } => "sauvola".to_string(),
+        PreprocessStep::Niblack { .. } => "niblack".to_string(),
+        PreprocessStep::Morphology { op, .. } => format!("{:?}", op).to_lowercase(),
+        PreprocessStep::TrimBorders { .. } => "trim_borders".to_string(),
+        PreprocessStep::RemoveComponents { .. } => "remove_components".to_string(),
+        PreprocessStep::Deskew { .. } => "deskew".to_string(),
+    }
+}
+
+fn apply_step(gray_image: GrayImage, step: &PreprocessStep, debug: bool) -> CleanupOutput {
+    let image = match step {
+        PreprocessStep::TrimBorders { tolerance, margin } => {
+            return cleanup::trim_borders(gray_image, *tolerance, *margin, debug);
+        }
+        PreprocessStep::RemoveComponents { min_area, max_height_fraction, max_aspect } => {
+            return cleanup::remove_components(gray_image, *min_area, *max_height_fraction, *max_aspect, debug);
+        }
+        PreprocessStep::Deskew { max_angle } => return cleanup::deskew(gray_image, *max_angle),
+        PreprocessStep::Upscale { factor } => {
+            let factor = (*factor).max(1);
+            let (width, height) = gray_image.dimensions();
+            imageops::resize(&gray_image, width * factor, height * factor, imageops::FilterType::Lanczos3)
+        }
+        PreprocessStep::AutoInvert => {
+            // Analyze image to determine if we should invert (for white text on dark background)
+            if analyze_text_brightness(&gray_image) {
+                info!("Inverting image for white text detection");
+                invert_image(&gray_image)
+            } else {
+                gray_image
+            }
+        }
+        PreprocessStep::Invert => invert_image(&gray_image),
+        PreprocessStep::Contrast { amount } => imageops::contrast(&gray_image, *amount),
+        PreprocessStep::Otsu => {
+            let threshold = otsu_threshold(&gray_image);
+            info!("Otsu threshold: {}", threshold);
+            threshold_image(&gray_image, |_, _| threshold as f64)
+        }
+        PreprocessStep::Sauvola { window, k } => {
+            // T = m * (1 + k * (s / R - 1)), R being the maximum standard deviation
+            let stats = WindowStats::new(&gray_image);
+            let k = *k as f64;
+            threshold_image(&gray_image, |x, y| {
+                let (mean, std_dev) = stats.mean_std_dev(x, y, *window);
+                mean * (1.0 + k * (std_dev / 128.0 - 1.0))
+            })
+        }
+        PreprocessStep::Niblack { window, k } => {
+            // T = m + k * s
+            let stats = WindowStats::new(&gray_image);
+            let k = *k as f64;
+            threshold_image(&gray_image, |x, y| {
+                let (mean, std_dev) = stats.mean_std_dev(x, y, *window);
+                mean + k * std_dev
+            })
+        }
+        PreprocessStep::Morphology { op, radius } => {
+            // Text is dark, so growing strokes is a min filter and thinning them a max filter
+            match op {
+                MorphologyOp::Dilate => rank_filter(&gray_image, *radius, u8::min),
+                MorphologyOp::Erode => rank_filter(&gray_image, *radius, u8::max),
+                MorphologyOp::Open => {
+                    rank_filter(&rank_filter(&gray_image, *radius, u8::max), *radius, u8::min)
+                }
+                MorphologyOp::Close => {
+                    rank_filter(&rank_filter(&gray_image, *radius, u8::min), *radius, u8::max)
+                }
+            }
+        }
+    };
+    CleanupOutput::image(image)
+}
+
+// Pixels above their threshold become white, the rest black
+fn threshold_image(gray_image: &GrayImage, threshold: impl Fn(u32, u32) -> f64) -> GrayImage {
+    let mut binary = GrayImage::new(gray_image.width(), gray_image.height());
+    for (x, y, pixel) in gray_image.enumerate_pixels() {
+        let value = if pixel[0] as f64 > threshold(x, y) { 255 } else { 0 };
+        binary.put_pixel(x, y, Luma([value]));
+    }
+    binary
+}
+
+fn otsu_threshold(gray_image: &GrayImage) -> u8 {
+    let mut histogram = [0u64; 256];
+    for pixel in gray_image.pixels() {
+        histogram[pixel[0] as usize] += 1;
+    }
+
+    let total = gray_image.pixels().len() as f64;
+    let total_sum: f64 = histogram.iter().enumerate().map(|(value, &count)| value as f64 * count as f64).sum();
+
+    // Pick the threshold that maximises the between-class variance
+    let mut background_count = 0.0;
+    let mut background_sum = 0.0;
+    let mut best_threshold = 0;
+    let mut best_variance = 0.0;
+    for (value, &count) in histogram.iter().enumerate() {
+        background_count += count as f64;
+        if background_count == 0.0 {
+            continue;
+        }
+        let foreground_count = total - background_count;
+        if foreground_count == 0.0 {
+            break;
+        }
+
+        background_sum += value as f64 * count as f64;
+        let background_mean = background_sum / background_count;
+        let foreground_mean = (total_sum - background_sum) / foreground_count;
+        let variance = background_count * foreground_count * (background_mean - foreground_mean).powi(2);
+        if variance > best_variance {
+            best_variance = variance;
+            best_threshold = value as u8;
+        }
+    }
+    best_threshold
+}
+
+// Integral images for constant time mean/standard deviation over any window
+struct WindowStats {
+    width: u32,
+    height: u32,
+    sum: Vec<f64>,
+    sum_sq: Vec<f64>,
+}
+
+impl WindowStats {
+    fn new(gray_image: &GrayImage) -> Self {
+        let (width, height) = gray_image.dimensions();
+        let stride = width as usize + 1;
+        let mut sum = vec![0.0; stride * (height as usize + 1)];
+        let mut sum_sq = vec![0.0; stride * (height as usize + 1)];
+
+        for y in 0..height as usize {
+            let mut row_sum = 0.0;
+            let mut row_sum_sq = 0.0;
+            for x in 0..width as usize {
+                let value = gray_image.get_pixel(x as u32, y as u32)[0] as f64;
+                row_sum += value;
+                row_sum_sq += value * value;
+                sum[(y + 1) * stride + x + 1] = sum[y * stride + x + 1] + row_sum;
+                sum_sq[(y + 1) * stride + x + 1] = sum_sq[y * stride + x + 1] + row_sum_sq;
+            }
+        }
+
+        WindowStats { width, height, sum, sum_sq }
+    }
+
+    // Window centred on (x, y), clipped at the image border
+    fn mean_std_dev(&self, x: u32, y: u32, window: u32) -> (f64, f64) {
+        let half = window.max(3) / 2;
+        let left = x.saturating_sub(half) as usize;
+        let top = y.saturating_sub(half) as usize;
+        let right = (x + half + 1).min(self.width) as usize;
+        let bottom = (y + half + 1).min(self.height) as usize;
+
+        let stride = self.width as usize + 1;
+        let area_sum = |table: &[f64]| {
+            table[bottom * stride + right] - table[top * stride + right] - table[bottom * stride + left]
+                + table[top * stride + left]
+        };
+
+        let count = ((right - left) * (bottom - top)) as f64;
+        let mean = area_sum(&self.sum) / count;
+        let variance = (area_sum(&self.sum_sq) / count - mean * mean).max(0.0);
+        (mean, variance.sqrt())
+    }
+}
+
+// Replaces each pixel with the min/max over a square neighbourhood
+// (separable: rows first, then columns)
+fn rank_filter(gray_image: &GrayImage, radius: u32, pick: fn(u8, u8) -> u8) -> GrayImage {
+    if radius == 0 {
+        return gray_image.clone();
+    }
+
+    let (width, height) = gray_image.dimensions();
+    let mut horizontal = GrayImage::new(width, height);
+    for y in 0..height {
+        for x in 0..width {
+            let from = x.saturating_sub(radius);
+            let to = (x + radius).min(width - 1);
+            let value = (from..=to).map(|nx| gray_image.get_pixel(nx, y)[0]).reduce(pick).unwrap_or(0);
+            horizontal.put_pixel(x, y, Luma([value]));
+        }
+    }
+
+    let mut filtered = GrayImage::new(width, height);
+    for y in 0..height {
+        for x in 0..width {
+            let from = y.saturating_sub(radius);
+            let to = (y + radius).min(height - 1);
+            let value = (from..=to).map(|ny| horizontal.get_pixel(x, ny)[0]).reduce(pick).unwrap_or(0);
+            filtered.put_pixel(x, y, Luma([value]));
+        }
+    }
+    filtered
+}
+
+fn analyze_text_brightness(gray_image: &GrayImage) -> bool {
+    // Sample pixels to determine if the image has more dark background (indicating white text)
+    let (width, height) = gray_image.dimensions();
+    let mut dark_pixels = 0;
+    let mut total_pixels = 0;
+
+    // Sample every 4th pixel to check brightness distribution
+    for y in (0..height).step_by(4) {
+        for x in (0..width).step_by(4) {
+            let pixel = gray_image.get_pixel(x, y);
+            let brightness = pixel[0];
+
+            if brightness < 128 {
+                dark_pixels += 1;
+            }
+            total_pixels += 1;
+        }
+    }
+
+    // If more than 60% of pixels are dark, likely white text on dark background
+    let dark_ratio = dark_pixels as f32 / total_pixels as f32;
+    info!("Dark pixel ratio: {:.2}, should_invert: {}", dark_ratio, dark_ratio > 0.6);
+
+    dark_ratio > 0.6
+}
+
+fn invert_image(gray_image: &GrayImage) -> GrayImage {
+    let (width, height) = gray_image.dimensions();
+    let mut inverted = image::ImageBuffer::new(width, height);
+
+    for (x, y, pixel) in gray_image.enumerate_pixels() {
+        let inverted_value = 255 - pixel[0];
+        inverted.put_pixel(x, y, image::Luma([inverted_value]));
+    }
+
+    inverted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(steps: Vec<PreprocessStep>) -> PreprocessConfig {
+        PreprocessConfig { steps, ..PreprocessConfig::default() }
+    }
+
+    // Dark "text" columns on a background that brightens from left to right,
+    // as under uneven lighting
+    fn gradient_glyph() -> GrayImage {
+        GrayImage::from_fn(64, 16, |x, _| {
+            let background = 100 + x as u8 * 2;
+            Luma([if x % 8 < 2 { background - 60 } else { background }])
+        })
+    }
+
+    fn is_text(x: u32) -> bool {
+        x % 8 < 2
+    }
+
+    #[test]
+    fn otsu_splits_a_bimodal_image_at_the_valley() {
+        let image = GrayImage::from_fn(20, 10, |x, y| {
+            let noise = (y % 3) as u8;
+            Luma([if x < 8 { 40 + noise } else { 200 + noise }])
+        });
+        let threshold = otsu_threshold(&image);
+        assert!((42..200).contains(&threshold), "threshold {}", threshold);
+
+        let binary = apply_step(image, &PreprocessStep::Otsu, false).image;
+        assert_eq!(binary.get_pixel(0, 0)[0], 0);
+        assert_eq!(binary.get_pixel(19, 9)[0], 255);
+    }
+
+    #[test]
+    fn local_thresholds_binarise_a_gradient_lit_glyph() {
+        // A single global threshold cannot separate the dark background on the
+        // left from the text on the right
+        let image = gradient_glyph();
+        assert!(is_text(56) && !is_text(2));
+        assert!(image.get_pixel(56, 0)[0] > image.get_pixel(2, 0)[0]);
+
+        for step in [PreprocessStep::Sauvola { window: 15, k: 0.2 }, PreprocessStep::Niblack { window: 15, k: -0.2 }] {
+            let binary = apply_step(gradient_glyph(), &step, false).image;
+            for x in 0..64 {
+                let expected = if is_text(x) { 0 } else { 255 };
+                assert_eq!(binary.get_pixel(x, 8)[0], expected, "{:?} at x = {}", step, x);
+            }
+        }
+    }
+
+    #[test]
+    fn open_and_close_remove_specks_and_fill_holes() {
+        let mut speck = GrayImage::from_pixel(9, 9, Luma([255]));
+        speck.put_pixel(4, 4, Luma([0]));
+        let opened = apply_step(speck, &PreprocessStep::Morphology { op: MorphologyOp::Open, radius: 1 }, false).image;
+        assert!(opened.pixels().all(|pixel| pixel[0] == 255));
+
+        let mut hole = GrayImage::from_pixel(9, 9, Luma([0]));
+        hole.put_pixel(4, 4, Luma([255]));
+        let closed = apply_step(hole, &PreprocessStep::Morphology { op: MorphologyOp::Close, radius: 1 }, false).image;
+        assert!(closed.pixels().all(|pixel| pixel[0] == 0));
+    }
+
+    #[test]
+    fn validation_rejects_bad_windows() {
+        assert!(config(vec![PreprocessStep::Sauvola { window: 31, k: 0.34 }]).validate().is_ok());
+        for window in [0, 1, 16, 257] {
+            assert!(config(vec![PreprocessStep::Sauvola { window, k: 0.34 }]).validate().is_err(), "{}", window);
+            assert!(config(vec![PreprocessStep::Niblack { window, k: -0.2 }]).validate().is_err(), "{}", window);
+        }
+        assert!(presets().iter().all(|preset| preset.config.validate().is_ok()));
+    }
+}
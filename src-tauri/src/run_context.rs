@@ -0,0 +1,187 @@
+// The run currently being trained (trainee plus support card deck), used to
+// narrow event matches to the characters that can actually show up.
+
+use crate::error::AppError;
+use crate::events::JsonEvent;
+use crate::settings_file;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+pub const RUN_CONTEXT_FILE: &str = "run_context.json";
+
+pub const MAX_SUPPORT_CARDS: usize = 6;
+
+const UMAMUSUME: &str = "Umamusume";
+const SUPPORT_CARD: &str = "Support Card";
+
+// Added to the confidence of events that belong to the run
+const CONTEXT_BOOST: f32 = 0.1;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ContextMode {
+    Filter, // Drop events of other characters
+    #[default]
+    Boost, // Keep them, but rank the run's events first
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct RunContext {
+    pub trainee: Option<String>,
+    pub support_cards: Vec<String>,
+    pub mode: ContextMode,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CharacterOption {
+    pub relation_type: String, // "Umamusume" or "Support Card"
+    pub name: String,
+}
+
+// `character_name` lists every outfit/card sharing the event, comma separated
+pub fn character_names(event: &JsonEvent) -> impl Iterator<Item = &str> {
+    event.character_name.split(',').map(str::trim).filter(|name| !name.is_empty())
+}
+
+// Distinct trainees and support cards to choose a run context from
+pub fn list_characters(events: &[JsonEvent]) -> Vec<CharacterOption> {
+    let options: BTreeSet<CharacterOption> = events
+        .iter()
+        .filter(|event| event.relation_type == UMAMUSUME || event.relation_type == SUPPORT_CARD)
+        .flat_map(|event| {
+            character_names(event).map(|name| CharacterOption {
+                relation_type: event.relation_type.clone(),
+                name: name.to_string(),
+            })
+        })
+        .collect();
+
+    options.into_iter().collect()
+}
+
+impl RunContext {
+    pub fn is_empty(&self) -> bool {
+        self.trainee.is_none() && self.support_cards.is_empty()
+    }
+
+    pub fn validate(&self, events: &[JsonEvent]) -> Result<(), AppError> {
+        if self.support_cards.len() > MAX_SUPPORT_CARDS {
+            return Err(AppError::invalid_input(format!("A deck holds at most {} support cards", MAX_SUPPORT_CARDS)));
+        }
+
+        let characters = list_characters(events);
+        let is_known = |relation_type: &str, name: &str| {
+            characters
+                .iter()
+                .any(|option| option.relation_type == relation_type && option.name == name)
+        };
+
+        if let Some(trainee) = &self.trainee {
+            if !is_known(UMAMUSUME, trainee) {
+                return Err(AppError::invalid_input(format!("Unknown Umamusume: {}", trainee)));
+            }
+        }
+        for card in &self.support_cards {
+            if !is_known(SUPPORT_CARD, card) {
+                return Err(AppError::invalid_input(format!("Unknown support card: {}", card)));
+            }
+        }
+        Ok(())
+    }
+
+    // Confidence boost for events of this run, None for events that cannot occur in it
+    fn relevance(&self, event: &JsonEvent) -> Option<f32> {
+        let involves = |name: &str| character_names(event).any(|character| character.eq_ignore_ascii_case(name));
+
+        match event.relation_type.as_str() {
+            UMAMUSUME => match &self.trainee {
+                Some(trainee) if involves(trainee) => Some(CONTEXT_BOOST),
+                Some(_) => None,
+                None => Some(0.0),
+            },
+            SUPPORT_CARD if self.support_cards.is_empty() => Some(0.0),
+            SUPPORT_CARD if self.support_cards.iter().any(|card| involves(card)) => Some(CONTEXT_BOOST),
+            SUPPORT_CARD => None,
+            _ => Some(0.0), // Scenario events happen in every run
+        }
+    }
+
+    // The confidence of a match of `event` in this run, or None when it is
+    // filtered out. Called before ranking, so filtered events make room for others.
+    pub fn adjust(&self, event: &JsonEvent, confidence: f32) -> Option<f32> {
+        if self.is_empty() {
+            return Some(confidence);
+        }
+
+        match self.relevance(event) {
+            Some(boost) => Some((confidence + boost).min(1.0)),
+            None if self.mode == ContextMode::Boost => Some(confidence),
+            None => None,
+        }
+    }
+}
+
+pub struct RunContextStore {
+    path: Option<PathBuf>,
+    context: RunContext,
+}
+
+impl RunContextStore {
+    pub fn load(path: Option<PathBuf>) -> Self {
+        let context = settings_file::load(path.as_deref(), "run context", |_: &RunContext| Ok(())).unwrap_or_default();
+        RunContextStore { path, context }
+    }
+
+    pub fn context(&self) -> &RunContext {
+        &self.context
+    }
+
+    // Checked against `events`, and only applied once saved
+    pub fn set(&mut self, context: RunContext, events: &[JsonEvent]) -> Result<(), AppError> {
+        context.validate(events)?;
+        settings_file::save(self.path.as_deref(), "run context", &context)?;
+        self.context = context;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(name: &str, character_name: &str, relation_type: &str) -> JsonEvent {
+        JsonEvent {
+            name: name.to_string(),
+            names: Default::default(),
+            character_name: character_name.to_string(),
+            relation_type: relation_type.to_string(),
+            choices: Vec::new(),
+            provenance: Default::default(),
+        }
+    }
+
+    #[test]
+    fn set_validates_before_saving() {
+        let events = vec![
+            event("At Tachyon's Pace", "Agnes Tachyon", UMAMUSUME),
+            event("Hamburger Helper!", "Kitasan Black, Nice Nature", SUPPORT_CARD),
+        ];
+        let mut store = RunContextStore { path: None, context: RunContext::default() };
+
+        let unknown = RunContext { trainee: Some("Vodka".to_string()), ..RunContext::default() };
+        assert!(store.set(unknown, &events).is_err());
+        let too_many = RunContext { support_cards: vec!["Nice Nature".to_string(); 7], ..RunContext::default() };
+        assert!(store.set(too_many, &events).is_err());
+        assert!(store.context().is_empty());
+
+        let context = RunContext {
+            trainee: Some("Agnes Tachyon".to_string()),
+            support_cards: vec!["Nice Nature".to_string()],
+            mode: ContextMode::Filter,
+        };
+        store.set(context, &events).unwrap();
+        assert_eq!(store.context().support_cards, ["Nice Nature"]);
+    }
+}
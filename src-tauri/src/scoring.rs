@@ -0,0 +1,360 @@
+// Ranks the choices of a matched event using a user-defined weight profile.
+
+use crate::error::AppError;
+use crate::outcome::{self, ChoiceOutcome, Effect, Stat};
+use crate::settings_file;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct WeightProfile {
+    pub name: String,
+    pub speed: f32,
+    pub stamina: f32,
+    pub power: f32,
+    pub guts: f32,
+    pub wit: f32,
+    pub energy: f32,
+    pub max_energy: f32,
+    pub mood: f32,
+    pub skill_points: f32,
+    pub bond: f32,
+    pub npc_bond: f32,
+    pub skill_hint: f32,          // Points per hint level
+    pub positive_condition: f32,  // Charming, Hot Topic, ...
+    pub negative_condition: f32,  // Slow Metabolism, Migraine, ... (usually negative)
+    pub conditions_cured: f32,
+    pub random_factor: f32,       // Multiplier for effects that only may happen
+}
+
+impl Default for WeightProfile {
+    fn default() -> Self {
+        WeightProfile {
+            name: "Balanced".to_string(),
+            speed: 1.0,
+            stamina: 1.0,
+            power: 1.0,
+            guts: 1.0,
+            wit: 1.0,
+            energy: 0.5,
+            max_energy: 1.0,
+            mood: 2.0,
+            skill_points: 0.5,
+            bond: 0.8,
+            npc_bond: 0.3,
+            skill_hint: 10.0,
+            positive_condition: 15.0,
+            negative_condition: -20.0,
+            conditions_cured: 10.0,
+            random_factor: 0.5,
+        }
+    }
+}
+
+impl WeightProfile {
+    fn stat_weight(&self, stat: Stat) -> f32 {
+        match stat {
+            Stat::Speed => self.speed,
+            Stat::Stamina => self.stamina,
+            Stat::Power => self.power,
+            Stat::Guts => self.guts,
+            Stat::Wit => self.wit,
+        }
+    }
+
+    fn total_stat_weight(&self) -> f32 {
+        Stat::ALL.iter().map(|stat| self.stat_weight(*stat)).sum()
+    }
+
+    fn average_stat_weight(&self) -> f32 {
+        self.total_stat_weight() / Stat::ALL.len() as f32
+    }
+
+    fn effect_score(&self, effect: &Effect) -> f32 {
+        match effect {
+            Effect::Stat { stat, amount } => self.stat_weight(*stat) * *amount as f32,
+            Effect::AllStats { amount } => self.total_stat_weight() * *amount as f32,
+            // The trained stat is unknown here, so assume an average one
+            Effect::LastTrainedStat { amount } => self.average_stat_weight() * *amount as f32,
+            Effect::RandomStats { count, amount } => {
+                self.average_stat_weight() * (*count as f32) * (*amount as f32)
+            }
+            Effect::Energy { amount } => self.energy * *amount as f32,
+            Effect::MaxEnergy { amount } => self.max_energy * *amount as f32,
+            Effect::Mood { amount } => self.mood * *amount as f32,
+            Effect::SkillPoints { amount } => self.skill_points * *amount as f32,
+            Effect::Bond { amount } => self.bond * *amount as f32,
+            Effect::NpcBond { amount, .. } => self.npc_bond * *amount as f32,
+            Effect::SkillHint { level, random, .. } => {
+                self.random_weight(*random) * self.skill_hint * *level as f32
+            }
+            Effect::ConditionGained { condition, random } => {
+                let weight = if outcome::is_negative_condition(condition) {
+                    self.negative_condition
+                } else {
+                    self.positive_condition
+                };
+                self.random_weight(*random) * weight
+            }
+            Effect::ConditionsCured => self.conditions_cured,
+            Effect::ObjectiveChanged { .. } => 0.0,
+            Effect::Alternatives { options } => {
+                if options.is_empty() {
+                    0.0
+                } else {
+                    let total: f32 = options.iter().map(|option| self.effect_score(option)).sum();
+                    total / options.len() as f32
+                }
+            }
+        }
+    }
+
+    fn random_weight(&self, random: bool) -> f32 {
+        if random {
+            self.random_factor
+        } else {
+            1.0
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChoiceScore {
+    pub number: String,
+    pub score: f32,
+    // Effects that could not be parsed are not scored, so flag the score as partial
+    pub partial: bool,
+    pub effects: usize, // Parsed effects the score is made of
+}
+
+pub fn score_choices(choices: &[ChoiceOutcome], profile: &WeightProfile) -> Vec<ChoiceScore> {
+    choices
+        .iter()
+        .map(|choice| ChoiceScore {
+            number: choice.number.clone(),
+            score: choice.effects.iter().map(|effect| profile.effect_score(effect)).sum(),
+            partial: !choice.unparsed.is_empty(),
+            effects: choice.effects.len(),
+        })
+        .collect()
+}
+
+// Scores closer than this count as equal
+const SCORE_EPSILON: f32 = 1e-4;
+
+// Highest scoring choice; None when there is nothing to choose between: a
+// single choice, no parsed effects, all scores 0, or a tie for the best
+pub fn recommend_choice(scores: &[ChoiceScore]) -> Option<String> {
+    if scores.len() < 2 || scores.iter().all(|score| score.effects == 0) {
+        return None;
+    }
+    if scores.iter().all(|score| score.score.abs() < SCORE_EPSILON) {
+        return None;
+    }
+
+    // The first of equal scores is kept, the tie is then found below
+    let best = scores.iter().fold(&scores[0], |best, score| {
+        if score.score > best.score + SCORE_EPSILON {
+            score
+        } else {
+            best
+        }
+    });
+    let tied = scores
+        .iter()
+        .filter(|score| (score.score - best.score).abs() < SCORE_EPSILON)
+        .count();
+    (tied == 1).then(|| best.number.clone())
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProfileList {
+    pub active: String,
+    pub profiles: Vec<WeightProfile>,
+}
+
+impl Default for ProfileList {
+    fn default() -> Self {
+        let profile = WeightProfile::default();
+        ProfileList {
+            active: profile.name.clone(),
+            profiles: vec![profile],
+        }
+    }
+}
+
+pub const WEIGHT_PROFILES_FILE: &str = "weight_profiles.json";
+
+// Weight profiles persisted as JSON in the user's config directory
+pub struct ProfileStore {
+    path: Option<PathBuf>,
+    list: ProfileList,
+}
+
+impl ProfileStore {
+    pub fn load(path: Option<PathBuf>) -> Self {
+        let list = settings_file::load(path.as_deref(), "weight profiles", |list: &ProfileList| {
+            if list.profiles.is_empty() {
+                return Err(AppError::invalid_input("no profiles"));
+            }
+            Ok(())
+        })
+        .unwrap_or_default();
+
+        ProfileStore { path, list }
+    }
+
+    pub fn list(&self) -> &ProfileList {
+        &self.list
+    }
+
+    pub fn active(&self) -> WeightProfile {
+        self.list
+            .profiles
+            .iter()
+            .find(|profile| profile.name == self.list.active)
+            .or_else(|| self.list.profiles.first())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn save_profile(&mut self, profile: WeightProfile) -> Result<(), AppError> {
+        if profile.name.trim().is_empty() {
+            return Err(AppError::invalid_input("Profile name must not be empty"));
+        }
+
+        let mut list = self.list.clone();
+        match list.profiles.iter_mut().find(|existing| existing.name == profile.name) {
+            Some(existing) => *existing = profile,
+            None => list.profiles.push(profile),
+        }
+        self.replace(list)
+    }
+
+    pub fn set_active(&mut self, name: &str) -> Result<(), AppError> {
+        if !self.list.profiles.iter().any(|profile| profile.name == name) {
+            return Err(AppError::invalid_input(format!("Unknown weight profile: {}", name)));
+        }
+
+        let list = ProfileList { active: name.to_string(), ..self.list.clone() };
+        self.replace(list)
+    }
+
+    pub fn delete_profile(&mut self, name: &str) -> Result<(), AppError> {
+        if !self.list.profiles.iter().any(|profile| profile.name == name) {
+            return Err(AppError::invalid_input(format!("Unknown weight profile: {}", name)));
+        }
+        if self.list.profiles.len() <= 1 {
+            return Err(AppError::invalid_input("Cannot delete the last weight profile"));
+        }
+
+        let mut list = self.list.clone();
+        list.profiles.retain(|profile| profile.name != name);
+        if list.active == name {
+            list.active = list.profiles[0].name.clone();
+        }
+        self.replace(list)
+    }
+
+    // Saves `list` and only then makes it current, so a failed write
+    // leaves the profiles as they are on disk
+    fn replace(&mut self, list: ProfileList) -> Result<(), AppError> {
+        settings_file::save(self.path.as_deref(), "weight profiles", &list)?;
+        self.list = list;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::outcome::parse_choice_outcome;
+
+    fn scores(outcomes: &[&str]) -> Vec<ChoiceScore> {
+        let choices: Vec<ChoiceOutcome> = outcomes
+            .iter()
+            .enumerate()
+            .map(|(i, outcome)| parse_choice_outcome(&(i + 1).to_string(), outcome))
+            .collect();
+        score_choices(&choices, &WeightProfile::default())
+    }
+
+    #[test]
+    fn effect_scores_use_the_profile_weights() {
+        let profile = WeightProfile { power: 2.0, ..WeightProfile::default() };
+        assert_eq!(profile.effect_score(&Effect::Stat { stat: Stat::Power, amount: 10 }), 20.0);
+        assert_eq!(profile.effect_score(&Effect::AllStats { amount: 5 }), 30.0);
+        assert_eq!(profile.effect_score(&Effect::LastTrainedStat { amount: 5 }), 6.0);
+        assert_eq!(profile.effect_score(&Effect::Energy { amount: -10 }), -5.0);
+        let hint = Effect::SkillHint { skill: "Focus".to_string(), level: 2, random: true };
+        assert_eq!(profile.effect_score(&hint), 10.0);
+        let condition = Effect::ConditionGained { condition: "Migraine".to_string(), random: false };
+        assert_eq!(profile.effect_score(&condition), -20.0);
+        assert_eq!(profile.effect_score(&Effect::ObjectiveChanged { race: "Japanese Oaks".to_string() }), 0.0);
+
+        // Alternatives count as their average
+        let options = vec![Effect::Mood { amount: 1 }, Effect::SkillPoints { amount: 10 }];
+        assert_eq!(profile.effect_score(&Effect::Alternatives { options }), 3.5);
+    }
+
+    #[test]
+    fn choices_sum_their_effects() {
+        let scores = scores(&["-5 Energy;Last trained stat +5;5 Bond with Director Akikawa", "10 Energy;Something odd"]);
+        assert_eq!(scores[0].score, -2.5 + 5.0 + 1.5);
+        assert_eq!((scores[0].partial, scores[0].effects), (false, 3));
+        assert_eq!(scores[1].score, 5.0);
+        assert_eq!((scores[1].partial, scores[1].effects), (true, 1));
+    }
+
+    #[test]
+    fn recommends_a_clear_best_choice() {
+        assert_eq!(recommend_choice(&scores(&["5 Speed", "10 Power", "5 Guts"])), Some("2".to_string()));
+        assert_eq!(recommend_choice(&scores(&["-5 Energy", "5 Skill Pts"])), Some("2".to_string()));
+    }
+
+    #[test]
+    fn no_recommendation_without_a_difference() {
+        // Equal best scores
+        assert_eq!(recommend_choice(&scores(&["10 Power", "10 Guts"])), None);
+        assert_eq!(recommend_choice(&scores(&["10 Power", "10 Guts", "5 Wit"])), None);
+        // Nothing parsed, or nothing worth anything
+        assert_eq!(recommend_choice(&scores(&["Something happens", "Something else"])), None);
+        assert_eq!(recommend_choice(&scores(&["Objective race changed to Japanese Oaks", ""])), None);
+        // Nothing to choose between
+        assert_eq!(recommend_choice(&scores(&["10 Power"])), None);
+    }
+
+    fn unwritable_store() -> ProfileStore {
+        let path = std::env::temp_dir().join("uma-helper-missing-dir").join("profiles.json");
+        ProfileStore { path: Some(path), list: ProfileList::default() }
+    }
+
+    #[test]
+    fn failed_saves_leave_profiles_unchanged() {
+        let mut store = unwritable_store();
+        let profile = WeightProfile { name: "Speed".to_string(), ..WeightProfile::default() };
+        assert!(store.save_profile(profile).is_err());
+        assert_eq!(store.list().profiles.len(), 1);
+
+        store.list.profiles.push(WeightProfile { name: "Speed".to_string(), ..WeightProfile::default() });
+        assert!(store.set_active("Speed").is_err());
+        assert_eq!(store.list().active, "Balanced");
+        assert!(store.delete_profile("Balanced").is_err());
+        assert_eq!(store.list().profiles.len(), 2);
+    }
+
+    #[test]
+    fn profile_changes_without_a_file() {
+        let mut store = ProfileStore { path: None, list: ProfileList::default() };
+        store.save_profile(WeightProfile { name: "Speed".to_string(), ..WeightProfile::default() }).unwrap();
+        store.set_active("Speed").unwrap();
+        store.delete_profile("Speed").unwrap();
+        assert_eq!(store.list().active, "Balanced");
+
+        assert!(store.delete_profile("Unknown").is_err());
+        assert!(store.delete_profile("Balanced").is_err()); // The last one
+        assert!(store.set_active("Unknown").is_err());
+        assert!(store.save_profile(WeightProfile { name: " ".to_string(), ..WeightProfile::default() }).is_err());
+    }
+}
@@ -0,0 +1,101 @@
+// Character-trigram inverted index over event names and choice texts, used to
+// narrow lookups down to a small candidate set before Jaro-Winkler scoring.
+
+use crate::events::JsonEvent;
+use crate::matcher::normalize_text;
+use std::collections::HashMap;
+
+type Trigram = [char; 3];
+
+// Narrows a lookup down to the events worth scoring. Implemented by the
+// in-memory trigram index and by the SQLite full-text index.
+pub trait CandidateIndex {
+    // Indices of at most `limit` plausible events, in database order. None
+    // when the text is too short to prune safely.
+    fn candidates(&self, text: &str, limit: usize) -> Option<Vec<usize>>;
+}
+
+pub struct SearchIndex {
+    postings: HashMap<Trigram, Vec<u32>>, // Trigram -> sorted event indices
+    event_count: usize,
+}
+
+impl SearchIndex {
+    pub fn build(events: &[JsonEvent]) -> Self {
+        let mut postings: HashMap<Trigram, Vec<u32>> = HashMap::new();
+
+        for (event_index, event) in events.iter().enumerate() {
+            let mut event_trigrams: Vec<Trigram> =
+                event.all_names().flat_map(|name| trigrams(&normalize_text(name))).collect();
+            for choice in &event.choices {
+                event_trigrams.extend(choice.all_texts().flat_map(|text| trigrams(&normalize_text(text))));
+            }
+            event_trigrams.sort_unstable();
+            event_trigrams.dedup();
+
+            // Events are visited in order, so every posting list stays sorted
+            for trigram in event_trigrams {
+                postings.entry(trigram).or_default().push(event_index as u32);
+            }
+        }
+
+        SearchIndex {
+            postings,
+            event_count: events.len(),
+        }
+    }
+
+    pub fn event_count(&self) -> usize {
+        self.event_count
+    }
+}
+
+impl CandidateIndex for SearchIndex {
+    // The `limit` events sharing the most trigrams with `text`
+    fn candidates(&self, text: &str, limit: usize) -> Option<Vec<usize>> {
+        let mut query_trigrams = trigrams(&normalize_text(text));
+        query_trigrams.sort_unstable();
+        query_trigrams.dedup();
+
+        if query_trigrams.len() < 2 {
+            return None;
+        }
+
+        let mut shared = vec![0_u32; self.event_count];
+        for trigram in &query_trigrams {
+            if let Some(events) = self.postings.get(trigram) {
+                for &event_index in events {
+                    shared[event_index as usize] += 1;
+                }
+            }
+        }
+
+        let mut ranked: Vec<(usize, u32)> = shared
+            .into_iter()
+            .enumerate()
+            .filter(|(_, count)| *count > 0)
+            .collect();
+        ranked.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        ranked.truncate(limit);
+
+        let mut candidates: Vec<usize> = ranked.into_iter().map(|(event_index, _)| event_index).collect();
+        candidates.sort_unstable();
+        Some(candidates)
+    }
+}
+
+// Trigrams of each word padded with spaces, so short words still contribute.
+// CJK text has no spaces, so a whole run of it counts as one word.
+fn trigrams(normalized: &str) -> Vec<Trigram> {
+    let mut result = Vec::new();
+    for word in normalized.split_whitespace() {
+        let padded: Vec<char> = std::iter::once(' ')
+            .chain(word.chars())
+            .chain(std::iter::once(' '))
+            .collect();
+        for window in padded.windows(3) {
+            result.push([window[0], window[1], window[2]]);
+        }
+    }
+    result
+}
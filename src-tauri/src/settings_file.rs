@@ -0,0 +1,96 @@
+// JSON settings files in the config directory (run context, weight profiles,
+// matcher config): read leniently when the app or CLI starts, where a missing
+// or invalid file means the defaults, and written pretty-printed.
+
+use crate::error::AppError;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::Path;
+use tracing::info;
+
+// The saved settings, or None (with a log line) when there are none or they
+// do not parse or pass `check`. `what` names the settings in the log.
+pub fn load<T: DeserializeOwned>(
+    path: Option<&Path>,
+    what: &str,
+    check: impl FnOnce(&T) -> Result<(), AppError>,
+) -> Option<T> {
+    let content = std::fs::read_to_string(path?).ok()?;
+    let result = serde_json::from_str::<T>(&content)
+        .map_err(|e| e.to_string())
+        .and_then(|value| check(&value).map(|()| value).map_err(|e| e.to_string()));
+    match result {
+        Ok(value) => Some(value),
+        Err(e) => {
+            info!("Ignoring invalid {} file: {}", what, e);
+            None
+        }
+    }
+}
+
+// A settings file given explicitly (e.g. to the CLI): unlike saved settings,
+// an unreadable or invalid file is an error
+pub fn read<T: DeserializeOwned>(path: &Path, what: &str) -> Result<T, AppError> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| AppError::invalid_input(format!("Could not read {}: {}", path.display(), e)))?;
+    serde_json::from_str(&content)
+        .map_err(|e| AppError::invalid_input(format!("Invalid {} {}: {}", what, path.display(), e)))
+}
+
+// Writes the settings; without a path (no config directory) they only live
+// as long as the process
+pub fn save<T: Serialize>(path: Option<&Path>, what: &str, value: &T) -> Result<(), AppError> {
+    let Some(path) = path else {
+        return Ok(());
+    };
+
+    let content = serde_json::to_string_pretty(value)
+        .map_err(|e| AppError::internal(format!("Failed to serialize {}: {}", what, e)))?;
+    std::fs::write(path, content).map_err(|e| AppError::SaveFailed {
+        path: path.display().to_string(),
+        reason: e.to_string(),
+    })?;
+
+    info!("Saved {} to: {}", what, path.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    type Settings = BTreeMap<String, u32>;
+
+    #[test]
+    fn saves_and_loads() {
+        let dir = std::env::temp_dir().join(format!("uma-helper-settings-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("settings.json");
+        let settings = Settings::from([("a".to_string(), 1)]);
+
+        save(Some(&path), "test settings", &settings).unwrap();
+        assert_eq!(load::<Settings>(Some(&path), "test settings", |_| Ok(())), Some(settings.clone()));
+        assert_eq!(read::<Settings>(&path, "test settings").unwrap(), settings);
+
+        // Settings that fail the check are ignored
+        let rejected = load::<Settings>(Some(&path), "test settings", |_| Err(AppError::invalid_input("no")));
+        assert_eq!(rejected, None);
+
+        std::fs::write(&path, "{ nope").unwrap();
+        assert_eq!(load::<Settings>(Some(&path), "test settings", |_| Ok(())), None);
+        assert!(read::<Settings>(&path, "test settings").unwrap_err().to_string().contains("Invalid test settings"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn missing_files_and_paths() {
+        let missing = std::env::temp_dir().join("uma-helper-missing-dir").join("settings.json");
+        assert_eq!(load::<Settings>(None, "test settings", |_| Ok(())), None);
+        assert_eq!(load::<Settings>(Some(&missing), "test settings", |_| Ok(())), None);
+        assert!(read::<Settings>(&missing, "test settings").unwrap_err().to_string().contains("Could not read"));
+
+        assert!(save(None, "test settings", &Settings::new()).is_ok());
+        assert!(save(Some(&missing), "test settings", &Settings::new()).is_err());
+    }
+}
@@ -0,0 +1,215 @@
+// Continuous watch mode: captures the configured region on an interval and
+// only runs OCR when the content changed and has settled.
+
+use crate::{capture_area_image, perform_ocr, record_lookup, resolve_color_key, AppState};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+use tracing::info;
+use uma_helper_lib::capture::CaptureArea;
+use uma_helper_lib::error::AppError;
+use uma_helper_lib::frame_hash::FrameHash;
+
+pub const WATCH_RESULT_EVENT: &str = "watch-ocr-result";
+pub const WATCH_STATUS_EVENT: &str = "watch-status";
+
+const MIN_INTERVAL_MS: u64 = 100;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct WatchConfig {
+    pub interval_ms: u64,
+    pub change_threshold: f32, // Fraction of hash bits (0.0-1.0) that must differ
+    pub color_key: Option<String>, // Colour-key preset for the watched region
+}
+
+impl Default for WatchConfig {
+    fn default() -> Self {
+        WatchConfig {
+            interval_ms: 750,
+            change_threshold: 0.08,
+            color_key: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct WatchStatus {
+    pub running: bool,
+    pub area: Option<CaptureArea>,
+    pub config: WatchConfig,
+    pub frames_captured: u64,
+    pub ocr_runs: u64,
+    pub last_change: Option<f32>, // Distance of the latest frame to the last OCR'd one
+    pub last_error: Option<AppError>,
+    #[serde(skip)]
+    session: u64,
+}
+
+#[derive(Default)]
+pub struct WatchController {
+    status: Arc<Mutex<WatchStatus>>,
+    stop_flag: Mutex<Option<Arc<AtomicBool>>>,
+}
+
+impl WatchController {
+    pub fn status(&self) -> WatchStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    pub fn start(&self, app_handle: AppHandle, area: CaptureArea, config: WatchConfig) -> WatchStatus {
+        self.stop();
+
+        let stop_flag = Arc::new(AtomicBool::new(false));
+        *self.stop_flag.lock().unwrap() = Some(Arc::clone(&stop_flag));
+
+        let session = {
+            let mut status = self.status.lock().unwrap();
+            let session = status.session + 1;
+            *status = WatchStatus {
+                running: true,
+                area: Some(area.clone()),
+                config: config.clone(),
+                session,
+                ..WatchStatus::default()
+            };
+            session
+        };
+
+        info!("Starting watch mode on {:?} with {:?}", area, config);
+        let status = Arc::clone(&self.status);
+        tauri::async_runtime::spawn(run_watch_loop(app_handle, area, config, stop_flag, status, session));
+
+        self.status()
+    }
+
+    // Returns false when watch mode was not running
+    pub fn stop(&self) -> bool {
+        match self.stop_flag.lock().unwrap().take() {
+            Some(stop_flag) => {
+                info!("Stopping watch mode");
+                stop_flag.store(true, Ordering::Relaxed);
+                self.status.lock().unwrap().running = false;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+async fn run_watch_loop(
+    app_handle: AppHandle,
+    area: CaptureArea,
+    config: WatchConfig,
+    stop_flag: Arc<AtomicBool>,
+    status: Arc<Mutex<WatchStatus>>,
+    session: u64,
+) {
+    let mut ticker = tokio::time::interval(Duration::from_millis(config.interval_ms.max(MIN_INTERVAL_MS)));
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+    // Validated when the watch was started
+    let color_key = resolve_color_key(config.color_key.as_deref()).unwrap_or_default();
+
+    let mut previous_frame: Option<FrameHash> = None;
+    let mut last_processed: Option<FrameHash> = None;
+
+    loop {
+        ticker.tick().await;
+        if stop_flag.load(Ordering::Relaxed) {
+            break;
+        }
+
+        // Screen capture is blocking, keep it off the async workers
+        let capture_area = area.clone();
+        let source = Arc::clone(&app_handle.state::<AppState>().capture);
+        let frame = match tauri::async_runtime::spawn_blocking(move || capture_area_image(&source, &capture_area)).await {
+            Ok(Ok(frame)) => frame,
+            // A replay without looping has nothing more to show
+            Ok(Err(e @ AppError::ReplayFinished { .. })) => {
+                info!("Watch mode: {}", e);
+                status.lock().unwrap().last_error = Some(e);
+                break;
+            }
+            Ok(Err(e)) => {
+                report_error(&app_handle, &status, e);
+                continue;
+            }
+            Err(e) => {
+                report_error(&app_handle, &status, AppError::internal(format!("Capture task failed: {}", e)));
+                continue;
+            }
+        };
+
+        // Only OCR once the content differs from the last processed frame and
+        // has stopped changing (dialogs animate in over several frames)
+        let hash = FrameHash::new(&frame);
+        let settled = previous_frame
+            .as_ref()
+            .is_some_and(|previous| previous.distance(&hash) <= config.change_threshold);
+        let change = last_processed.as_ref().map_or(1.0, |last| last.distance(&hash));
+        previous_frame = Some(hash.clone());
+
+        {
+            let mut status = status.lock().unwrap();
+            status.frames_captured += 1;
+            status.last_change = Some(change);
+        }
+
+        if !settled || change <= config.change_threshold {
+            continue;
+        }
+
+        info!("Watch mode detected a change ({:.2}), running OCR", change);
+        let state = app_handle.state::<AppState>();
+        let result = perform_ocr(&frame, &state, Some(&app_handle), color_key.clone()).await;
+        // A failed frame counts as processed too, so an error that persists
+        // is not retried on every tick
+        last_processed = Some(hash);
+        match result {
+            Ok(result) => {
+                record_lookup(&state, "watch", &result.text, &result.matched_events);
+                {
+                    let mut status = status.lock().unwrap();
+                    status.ocr_runs += 1;
+                    status.last_error = None;
+                }
+                if let Err(e) = app_handle.emit(WATCH_RESULT_EVENT, &result) {
+                    info!("Warning: Could not emit watch result: {}", e);
+                }
+            }
+            Err(e) => {
+                info!("Watch mode OCR failed: {}", e);
+                report_error(&app_handle, &status, e);
+            }
+        }
+    }
+
+    // A newer session may already be running; only report our own shutdown
+    let final_status = {
+        let mut status = status.lock().unwrap();
+        if status.session != session {
+            return;
+        }
+        status.running = false;
+        status.clone()
+    };
+    info!("Watch mode stopped after {} frames", final_status.frames_captured);
+    let _ = app_handle.emit(WATCH_STATUS_EVENT, &final_status);
+}
+
+// Keeps the error in the status and tells the frontend when it is a new one,
+// so a capture failing on every tick is reported once
+fn report_error(app_handle: &AppHandle, status: &Mutex<WatchStatus>, error: AppError) {
+    let changed_status = {
+        let mut status = status.lock().unwrap();
+        let repeated = status.last_error.as_ref().is_some_and(|last| last.to_string() == error.to_string());
+        status.last_error = Some(error);
+        (!repeated).then(|| status.clone())
+    };
+    if let Some(changed_status) = changed_status {
+        let _ = app_handle.emit(WATCH_STATUS_EVENT, &changed_status);
+    }
+}